@@ -0,0 +1,325 @@
+//! Generate Rust types for a database schema from a build script.
+//!
+//! ```no_run
+//! // build.rs
+//! fn main() -> Result<(), rasql_build::BuildError> {
+//!     rasql_build::Builder::new()
+//!         .schema_dir("migrations")
+//!         .client(rasql_build::TokioPostgres)
+//!         .generate()
+//! }
+//! ```
+//!
+//! The generated code can then be brought into the crate with
+//! `include!(concat!(env!("OUT_DIR"), "/rasql_generated.rs"));`.
+
+use std::path::PathBuf;
+
+use rasql_core::{
+    rust::{build_env, CodeGenConfig},
+    sql::{migrations::discover_migrations, SchemaAnalyser, SchemaError, SignatureError},
+};
+use thiserror::Error;
+
+#[cfg(feature = "tokio-postgres")]
+pub use rasql_core::rust::type_gen::{UseDateTime, UseRustDecimal, UseSerdeJson, UseUuid};
+#[cfg(feature = "tokio-postgres")]
+pub use Client::TokioPostgres;
+
+/// The name of the file written to `OUT_DIR`.
+pub const GENERATED_FILE_NAME: &str = "rasql_generated.rs";
+
+/// The database client the generated code is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Client {
+    #[cfg(feature = "tokio-postgres")]
+    TokioPostgres,
+}
+
+impl Client {
+    /// The name rasql-query knows this client by.
+    #[cfg_attr(not(feature = "tokio-postgres"), allow(unused))]
+    fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "tokio-postgres")]
+            Client::TokioPostgres => "tokio-postgres",
+        }
+    }
+}
+
+#[derive(Error)]
+pub enum BuildError {
+    #[error("no schema directory was given to the builder")]
+    NoSchemaDir,
+    #[error("no client was given to the builder")]
+    NoClient,
+    #[error("`OUT_DIR` isn't set, rasql-build should be used from a build script")]
+    NoOutDir,
+    #[error(
+        "the schema has errors:\n{}",
+        .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("\n")
+    )]
+    Schema(Vec<SchemaError>),
+    #[error("{}:{}: {}", path.display(), source.line, source.message)]
+    FunctionSignatures {
+        path: PathBuf,
+        #[source]
+        source: SignatureError,
+    },
+    #[error(transparent)]
+    UnsupportedDataType(#[from] rasql_core::rust::type_gen::UnsupportedDataType),
+    #[error("`{0}` isn't a valid path for the generated code")]
+    InvalidModulePath(String),
+    #[error("generated code couldn't be parsed: {0}")]
+    InvalidGeneratedCode(#[from] syn::Error),
+    #[error("failed to access `{}`: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+// Build scripts usually return or unwrap this, both of which show the `Debug` output, so make
+// that as readable as the error message
+impl std::fmt::Debug for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
+/// Configures and runs code generation. See the [crate] docs for an example.
+pub struct Builder {
+    schema_dir: Option<PathBuf>,
+    function_signatures: Vec<PathBuf>,
+    client: Option<Client>,
+    config: CodeGenConfig,
+    out_dir: Option<PathBuf>,
+    generated_module_path: String,
+    #[cfg(feature = "tokio-postgres")]
+    use_rust_decimal: UseRustDecimal,
+    #[cfg(feature = "tokio-postgres")]
+    use_uuid: UseUuid,
+    #[cfg(feature = "tokio-postgres")]
+    use_date_time: UseDateTime,
+    #[cfg(feature = "tokio-postgres")]
+    use_serde_json: UseSerdeJson,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self {
+            schema_dir: None,
+            function_signatures: vec![],
+            client: None,
+            config: CodeGenConfig::default(),
+            out_dir: None,
+            generated_module_path: "crate".to_owned(),
+            #[cfg(feature = "tokio-postgres")]
+            use_rust_decimal: UseRustDecimal::DontUse,
+            #[cfg(feature = "tokio-postgres")]
+            use_uuid: UseUuid::DontUse,
+            #[cfg(feature = "tokio-postgres")]
+            use_date_time: UseDateTime::DontUse,
+            #[cfg(feature = "tokio-postgres")]
+            use_serde_json: UseSerdeJson::DontUse,
+        }
+    }
+
+    /// The directory of migrations making up the schema, relative to the crate root. See
+    /// [rasql_core::sql::migrations] for the layouts that are understood.
+    pub fn schema_dir(mut self, schema_dir: impl Into<PathBuf>) -> Self {
+        self.schema_dir = Some(schema_dir.into());
+        self
+    }
+
+    /// Add the signatures in a file, relative to the crate root, to the functions queries can
+    /// use. This is how functions from extensions rasql doesn't know about, like PostGIS, are
+    /// made available. See [rasql_core::sql::functions] for the format.
+    pub fn function_signatures(mut self, path: impl Into<PathBuf>) -> Self {
+        self.function_signatures.push(path.into());
+        self
+    }
+
+    pub fn client(mut self, client: Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn config(mut self, config: CodeGenConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Generate an async function for calling each function the schema creates. See
+    /// [CodeGenConfig::function_wrappers].
+    pub fn function_wrappers(mut self, function_wrappers: bool) -> Self {
+        self.config.function_wrappers = function_wrappers;
+        self
+    }
+
+    /// Write the generated code somewhere other than `OUT_DIR`.
+    pub fn out_dir(mut self, out_dir: impl Into<PathBuf>) -> Self {
+        self.out_dir = Some(out_dir.into());
+        self
+    }
+
+    /// Where the generated code is included, `crate` by default. rasql-query refers to the
+    /// enums generated for the schema's types through this, so queries using them need it
+    /// to be right.
+    pub fn generated_module_path(mut self, path: impl Into<String>) -> Self {
+        self.generated_module_path = path.into();
+        self
+    }
+
+    /// Map `NUMERIC` columns to `rust_decimal::Decimal`.
+    #[cfg(feature = "tokio-postgres")]
+    pub fn use_rust_decimal(mut self, use_rust_decimal: UseRustDecimal) -> Self {
+        self.use_rust_decimal = use_rust_decimal;
+        self
+    }
+
+    /// Map `UUID` columns to `uuid::Uuid`.
+    #[cfg(feature = "tokio-postgres")]
+    pub fn use_uuid(mut self, use_uuid: UseUuid) -> Self {
+        self.use_uuid = use_uuid;
+        self
+    }
+
+    /// Map `DATE`, `TIME` and `TIMESTAMP` columns to the types of `chrono` or `time`.
+    #[cfg(feature = "tokio-postgres")]
+    pub fn use_date_time(mut self, use_date_time: UseDateTime) -> Self {
+        self.use_date_time = use_date_time;
+        self
+    }
+
+    /// Map `JSON` and `JSONB` columns to `serde_json::Value`.
+    #[cfg(feature = "tokio-postgres")]
+    pub fn use_serde_json(mut self, use_serde_json: UseSerdeJson) -> Self {
+        self.use_serde_json = use_serde_json;
+        self
+    }
+
+    /// Analyse the schema and write the generated code to `rasql_generated.rs`, telling Cargo
+    /// to rerun the build script if any of the SQL changes.
+    #[cfg_attr(
+        not(feature = "tokio-postgres"),
+        allow(unreachable_code, unused_variables)
+    )]
+    pub fn generate(self) -> Result<(), BuildError> {
+        let schema_dir = self.schema_dir.as_deref().ok_or(BuildError::NoSchemaDir)?;
+        let client = self.client.ok_or(BuildError::NoClient)?;
+        if syn::parse_str::<syn::Path>(&self.generated_module_path).is_err() {
+            return Err(BuildError::InvalidModulePath(self.generated_module_path));
+        }
+        let out_dir = match self.out_dir.clone() {
+            Some(out_dir) => out_dir,
+            None => std::env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or(BuildError::NoOutDir)?,
+        };
+
+        // Watching the directory itself picks up migrations being added or removed
+        println!("cargo:rerun-if-changed={}", schema_dir.display());
+        let migrations = discover_migrations(schema_dir).map_err(BuildError::Schema)?;
+        let mut analyser = SchemaAnalyser::new();
+        for migration in &migrations {
+            println!("cargo:rerun-if-changed={}", migration.path.display());
+            analyser.analyse_file(&migration.path);
+        }
+        let mut catalog = analyser.finish().map_err(BuildError::Schema)?;
+
+        let mut function_signatures = vec![];
+        for path in &self.function_signatures {
+            println!("cargo:rerun-if-changed={}", path.display());
+            let io_error = |source| BuildError::Io {
+                path: path.clone(),
+                source,
+            };
+            let signatures = std::fs::read_to_string(path).map_err(io_error)?;
+            catalog
+                .functions_mut()
+                .add_signatures(&signatures)
+                .map_err(|source| BuildError::FunctionSignatures {
+                    path: path.clone(),
+                    source,
+                })?;
+            function_signatures.push(std::fs::canonicalize(path).map_err(io_error)?);
+        }
+
+        // Tell rasql-query how to find the schema and generate code that fits with ours
+        let schema_dir = std::fs::canonicalize(schema_dir).map_err(|source| BuildError::Io {
+            path: schema_dir.to_path_buf(),
+            source,
+        })?;
+        println!(
+            "cargo:rustc-env={}={}",
+            build_env::SCHEMA_DIR,
+            schema_dir.display()
+        );
+        println!("cargo:rustc-env={}={}", build_env::CLIENT, client.name());
+        println!(
+            "cargo:rustc-env={}={}",
+            build_env::GENERATED_MODULE_PATH,
+            self.generated_module_path
+        );
+        let function_signatures =
+            std::env::join_paths(function_signatures).map_err(|source| BuildError::Io {
+                path: self.function_signatures[0].clone(),
+                source: std::io::Error::new(std::io::ErrorKind::InvalidInput, source),
+            })?;
+        println!(
+            "cargo:rustc-env={}={}",
+            build_env::FUNCTION_SIGNATURES,
+            function_signatures.to_string_lossy()
+        );
+        #[cfg(feature = "tokio-postgres")]
+        {
+            println!(
+                "cargo:rustc-env={}={}",
+                build_env::USE_RUST_DECIMAL,
+                self.use_rust_decimal.name()
+            );
+            println!(
+                "cargo:rustc-env={}={}",
+                build_env::USE_UUID,
+                self.use_uuid.name()
+            );
+            println!(
+                "cargo:rustc-env={}={}",
+                build_env::USE_DATE_TIME,
+                self.use_date_time.name()
+            );
+            println!(
+                "cargo:rustc-env={}={}",
+                build_env::USE_SERDE_JSON,
+                self.use_serde_json.name()
+            );
+        }
+
+        let tokens = match client {
+            #[cfg(feature = "tokio-postgres")]
+            Client::TokioPostgres => rasql_core::rust::generate_catalog(
+                &catalog,
+                &self.config,
+                &rasql_core::rust::type_gen::TokioPostgresGenerator {
+                    use_rust_decimal: self.use_rust_decimal,
+                    use_uuid: self.use_uuid,
+                    use_date_time: self.use_date_time,
+                    use_serde_json: self.use_serde_json,
+                    generated_module_path: syn::parse_quote!(super),
+                },
+            )?,
+        };
+        let code = prettyplease::unparse(&syn::parse2(tokens)?);
+        let path = out_dir.join(GENERATED_FILE_NAME);
+        std::fs::write(&path, code).map_err(|source| BuildError::Io { path, source })
+    }
+}
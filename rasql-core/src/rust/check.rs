@@ -0,0 +1,464 @@
+//! Translation of domains' `CHECK` constraints into Rust, so that values can be checked before
+//! they're sent to the database. Only comparisons of `VALUE`, or of its length, against
+//! literals are understood; anything else can only be checked by Postgres itself.
+
+use sqlparser::ast::{
+    BinaryOperator, DataType, Expr, FunctionArg, FunctionArgExpr, FunctionArguments, UnaryOperator,
+    Value,
+};
+
+use crate::sql::types::canonical_type;
+
+/// What a domain is based on, as far as its checks are concerned.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Int {
+        min: i128,
+        max: i128,
+    },
+    Float,
+    Text,
+    /// The number of characters in a text value
+    Length,
+}
+
+impl Kind {
+    fn of(base: &DataType) -> Option<Self> {
+        let int = |min, max| Some(Kind::Int { min, max });
+        match canonical_type(base) {
+            DataType::Int2(_) => int(i16::MIN.into(), i16::MAX.into()),
+            DataType::Int4(_) => int(i32::MIN.into(), i32::MAX.into()),
+            DataType::Int8(_) => int(i64::MIN.into(), i64::MAX.into()),
+            DataType::Float4 | DataType::Float8 => Some(Kind::Float),
+            DataType::Text => Some(Kind::Text),
+            _ => None,
+        }
+    }
+}
+
+/// How tightly a translated condition binds, so that it's only parenthesised where it has to be.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Or,
+    And,
+    Comparison,
+    Atom,
+}
+
+/// A condition translated into Rust.
+pub(super) struct Condition {
+    pub tokens: proc_macro2::TokenStream,
+    precedence: Precedence,
+}
+
+impl Condition {
+    fn new(precedence: Precedence, tokens: proc_macro2::TokenStream) -> Self {
+        Self { tokens, precedence }
+    }
+
+    /// The condition, parenthesised if it binds less tightly than `precedence`.
+    fn at(&self, precedence: Precedence) -> proc_macro2::TokenStream {
+        let tokens = &self.tokens;
+        if self.precedence < precedence {
+            quote::quote!((#tokens))
+        } else {
+            tokens.clone()
+        }
+    }
+
+    /// Whether the condition holds for every value, as `VALUE IS NOT NULL` does.
+    pub fn is_always_true(&self) -> bool {
+        self.tokens.to_string() == "true"
+    }
+
+    pub fn not(&self) -> Self {
+        let condition = self.at(Precedence::Atom);
+        Condition::new(Precedence::Atom, quote::quote!(!#condition))
+    }
+}
+
+/// Translate the `CHECK` expression `check` of a domain over `base` into a Rust condition
+/// that's true when the value, bound to `value`, satisfies it. Returns `None` if the check
+/// can't be translated.
+pub(super) fn translate_check(base: &DataType, check: &Expr) -> Option<Condition> {
+    Checker {
+        kind: Kind::of(base)?,
+    }
+    .condition(check)
+}
+
+struct Checker {
+    kind: Kind,
+}
+
+impl Checker {
+    fn condition(&self, expr: &Expr) -> Option<Condition> {
+        match expr {
+            Expr::Nested(expr) => self.condition(expr),
+            Expr::UnaryOp {
+                op: UnaryOperator::Not,
+                expr,
+            } => Some(self.condition(expr)?.not()),
+            Expr::BinaryOp {
+                left,
+                op: op @ (BinaryOperator::And | BinaryOperator::Or),
+                right,
+            } => {
+                let (left, right) = (self.condition(left)?, self.condition(right)?);
+                Some(match op {
+                    BinaryOperator::And => {
+                        let (left, right) = (left.at(Precedence::And), right.at(Precedence::And));
+                        Condition::new(Precedence::And, quote::quote!(#left && #right))
+                    }
+                    _ => {
+                        let (left, right) = (left.at(Precedence::Or), right.at(Precedence::Or));
+                        Condition::new(Precedence::Or, quote::quote!(#left || #right))
+                    }
+                })
+            }
+            // Only non-NULL values get as far as being checked
+            Expr::IsNotNull(expr) => {
+                self.operand(expr)?;
+                Some(Condition::new(Precedence::Atom, quote::quote!(true)))
+            }
+            Expr::BinaryOp { left, op, right } => {
+                let (operand, kind, op, literal) = match self.operand(left) {
+                    Some((operand, kind)) => (operand, kind, op.clone(), right),
+                    None => {
+                        let (operand, kind) = self.operand(right)?;
+                        (operand, kind, flip(op)?, left)
+                    }
+                };
+                let op = match op {
+                    BinaryOperator::Eq => quote::quote!(==),
+                    BinaryOperator::NotEq => quote::quote!(!=),
+                    // Postgres orders text by its collation, which Rust can't follow
+                    _ if kind == Kind::Text => return None,
+                    BinaryOperator::Gt => quote::quote!(>),
+                    BinaryOperator::GtEq => quote::quote!(>=),
+                    BinaryOperator::Lt => quote::quote!(<),
+                    BinaryOperator::LtEq => quote::quote!(<=),
+                    _ => return None,
+                };
+                let literal = literal_of(kind, literal)?;
+                Some(Condition::new(
+                    Precedence::Comparison,
+                    quote::quote!(#operand #op #literal),
+                ))
+            }
+            Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                let (operand, kind) = self.operand(expr)?;
+                if kind == Kind::Text {
+                    return None;
+                }
+                let low = literal_of(kind, low)?;
+                let high = literal_of(kind, high)?;
+                let condition = Condition::new(
+                    Precedence::And,
+                    quote::quote!(#low <= #operand && #operand <= #high),
+                );
+                Some(if *negated { condition.not() } else { condition })
+            }
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let (operand, kind) = self.operand(expr)?;
+                let literals = list
+                    .iter()
+                    .map(|literal| literal_of(kind, literal))
+                    .collect::<Option<Vec<_>>>()?;
+                let condition =
+                    Condition::new(Precedence::Or, quote::quote!(#(#operand == #literals)||*));
+                Some(if *negated { condition.not() } else { condition })
+            }
+            _ => None,
+        }
+    }
+
+    /// The Rust for `VALUE` or its length, and what kind of value that is.
+    fn operand(&self, expr: &Expr) -> Option<(proc_macro2::TokenStream, Kind)> {
+        match expr {
+            Expr::Nested(expr) => self.operand(expr),
+            Expr::Identifier(ident)
+                if ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("value") =>
+            {
+                Some((quote::quote!(value), self.kind))
+            }
+            Expr::Function(function)
+                if self.kind == Kind::Text
+                    && function.filter.is_none()
+                    && function.over.is_none()
+                    && matches!(
+                        function.name.0.as_slice(),
+                        [name] if ["length", "char_length", "character_length"]
+                            .contains(&name.value.to_lowercase().as_str())
+                    ) =>
+            {
+                let FunctionArguments::List(arguments) = &function.args else {
+                    return None;
+                };
+                let [FunctionArg::Unnamed(FunctionArgExpr::Expr(argument))] =
+                    arguments.args.as_slice()
+                else {
+                    return None;
+                };
+                let (operand, Kind::Text) = self.operand(argument)? else {
+                    return None;
+                };
+                Some((quote::quote!(#operand.chars().count()), Kind::Length))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The operator that gives the same result with its operands swapped.
+fn flip(op: &BinaryOperator) -> Option<BinaryOperator> {
+    Some(match op {
+        BinaryOperator::Eq => BinaryOperator::Eq,
+        BinaryOperator::NotEq => BinaryOperator::NotEq,
+        BinaryOperator::Gt => BinaryOperator::Lt,
+        BinaryOperator::GtEq => BinaryOperator::LtEq,
+        BinaryOperator::Lt => BinaryOperator::Gt,
+        BinaryOperator::LtEq => BinaryOperator::GtEq,
+        _ => return None,
+    })
+}
+
+/// The Rust literal for `expr` when it's compared with a value of kind `kind`.
+fn literal_of(kind: Kind, expr: &Expr) -> Option<proc_macro2::TokenStream> {
+    let (negative, expr) = match expr {
+        Expr::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } => (true, &**expr),
+        Expr::UnaryOp {
+            op: UnaryOperator::Plus,
+            expr,
+        } => (false, &**expr),
+        expr => (false, expr),
+    };
+    match (kind, expr) {
+        (Kind::Text, Expr::Value(Value::SingleQuotedString(string))) => {
+            Some(quote::quote!(#string))
+        }
+        (Kind::Int { min, max }, Expr::Value(Value::Number(number, _))) => {
+            let number: i128 = number.parse().ok()?;
+            let number = if negative { -number } else { number };
+            (min..=max).contains(&number).then(|| {
+                let literal = proc_macro2::Literal::i128_unsuffixed(number.abs());
+                if number < 0 {
+                    quote::quote!(-#literal)
+                } else {
+                    quote::quote!(#literal)
+                }
+            })
+        }
+        (Kind::Float, Expr::Value(Value::Number(number, _))) => {
+            let number: f64 = number.parse().ok()?;
+            let literal = proc_macro2::Literal::f64_unsuffixed(number);
+            Some(if negative {
+                quote::quote!(-#literal)
+            } else {
+                quote::quote!(#literal)
+            })
+        }
+        (Kind::Length, Expr::Value(Value::Number(number, _))) if !negative => {
+            let number: usize = number.parse().ok()?;
+            let literal = proc_macro2::Literal::usize_unsuffixed(number);
+            Some(quote::quote!(#literal))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quote::quote;
+    use sqlparser::{dialect::PostgreSqlDialect, parser::Parser};
+
+    use super::*;
+
+    const INT2: DataType = DataType::Int2(None);
+    const INT4: DataType = DataType::Int4(None);
+    const INT8: DataType = DataType::Int8(None);
+
+    fn translate(base: &DataType, check: &str) -> Option<Condition> {
+        let check = Parser::new(&PostgreSqlDialect {})
+            .try_with_sql(check)
+            .and_then(|mut parser| parser.parse_expr())
+            .unwrap();
+        translate_check(base, &check)
+    }
+
+    /// Check `check` translates to `expected` for a domain over `base`, or can't be translated
+    /// if `expected` is `None`.
+    fn assert_translates(base: &DataType, check: &str, expected: Option<proc_macro2::TokenStream>) {
+        assert_eq!(
+            translate(base, check).map(|condition| condition.tokens.to_string()),
+            expected.map(|expected| expected.to_string()),
+            "{check}"
+        );
+    }
+
+    #[test]
+    fn comparisons() {
+        let cases = [
+            ("VALUE > 0", quote!(value > 0)),
+            ("value >= -5", quote!(value >= -5)),
+            ("VALUE = +3", quote!(value == 3)),
+            ("VALUE <> 3", quote!(value != 3)),
+            ("(VALUE) < 10", quote!(value < 10)),
+        ];
+        for (check, expected) in cases {
+            assert_translates(&INT4, check, Some(expected));
+        }
+        assert_translates(&DataType::Float8, "VALUE > 0.5", Some(quote!(value > 0.5)));
+    }
+
+    #[test]
+    fn flipped_operands() {
+        let cases = [
+            ("0 < VALUE", quote!(value > 0)),
+            ("0 <= VALUE", quote!(value >= 0)),
+            ("10 > VALUE", quote!(value < 10)),
+            ("10 >= VALUE", quote!(value <= 10)),
+            ("1 = VALUE", quote!(value == 1)),
+            ("1 != VALUE", quote!(value != 1)),
+        ];
+        for (check, expected) in cases {
+            assert_translates(&INT4, check, Some(expected));
+        }
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        let cases = [
+            (
+                "VALUE >= 1 AND VALUE <= 10",
+                quote!(value >= 1 && value <= 10),
+            ),
+            (
+                "VALUE = 1 OR VALUE = 2 AND VALUE <> 3",
+                quote!(value == 1 || value == 2 && value != 3),
+            ),
+            (
+                "(VALUE = 1 OR VALUE = 2) AND VALUE <> 3",
+                quote!((value == 1 || value == 2) && value != 3),
+            ),
+            ("((VALUE > 0))", quote!(value > 0)),
+            (
+                "NOT (VALUE > 0 AND VALUE < 5)",
+                quote!(!(value > 0 && value < 5)),
+            ),
+            ("NOT VALUE = 1", quote!(!(value == 1))),
+            ("VALUE BETWEEN 1 AND 5", quote!(1 <= value && value <= 5)),
+            (
+                "VALUE NOT BETWEEN 1 AND 5 OR VALUE = 3",
+                quote!(!(1 <= value && value <= 5) || value == 3),
+            ),
+            (
+                "VALUE IN (1, 2) AND VALUE > 0",
+                quote!((value == 1 || value == 2) && value > 0),
+            ),
+            ("VALUE NOT IN (1, 2)", quote!(!(value == 1 || value == 2))),
+        ];
+        for (check, expected) in cases {
+            assert_translates(&INT4, check, Some(expected));
+        }
+    }
+
+    #[test]
+    fn negation_for_error_reporting() {
+        let condition = translate(&INT4, "VALUE >= 1 AND VALUE <= 10").unwrap();
+        assert_eq!(
+            condition.not().tokens.to_string(),
+            quote!(!(value >= 1 && value <= 10)).to_string()
+        );
+    }
+
+    #[test]
+    fn int_literals_out_of_range() {
+        assert_translates(&INT2, "VALUE < 32767", Some(quote!(value < 32767)));
+        assert_translates(&INT2, "VALUE > -32768", Some(quote!(value > -32768)));
+        assert_translates(&INT2, "VALUE < 32768", None);
+        assert_translates(&INT2, "VALUE > -32769", None);
+        assert_translates(&INT4, "VALUE < 2147483648", None);
+        assert_translates(&INT4, "VALUE BETWEEN 0 AND 3000000000", None);
+        assert_translates(
+            &INT8,
+            "VALUE < 2147483648",
+            Some(quote!(value < 2147483648)),
+        );
+        assert_translates(&INT8, "VALUE < 9223372036854775808", None);
+        assert_translates(&INT4, "VALUE IN (1, 99999999999)", None);
+        assert_translates(&INT4, "VALUE > 1.5", None);
+    }
+
+    #[test]
+    fn text() {
+        assert_translates(&DataType::Text, "VALUE <> ''", Some(quote!(value != "")));
+        assert_translates(
+            &DataType::Varchar(None),
+            "VALUE IN ('a', 'b')",
+            Some(quote!(value == "a" || value == "b")),
+        );
+        assert_translates(
+            &DataType::Text,
+            "length(VALUE) <= 10",
+            Some(quote!(value.chars().count() <= 10)),
+        );
+        assert_translates(
+            &DataType::Text,
+            "5 < char_length(VALUE)",
+            Some(quote!(value.chars().count() > 5)),
+        );
+        assert_translates(&DataType::Text, "length(VALUE) > -1", None);
+        assert_translates(&DataType::Text, "VALUE = 1", None);
+    }
+
+    #[test]
+    fn text_ordering_is_rejected() {
+        for check in [
+            "VALUE > 'a'",
+            "'a' <= VALUE",
+            "VALUE BETWEEN 'a' AND 'z'",
+            "VALUE <> '' AND VALUE < 'z'",
+        ] {
+            assert_translates(&DataType::Text, check, None);
+        }
+    }
+
+    #[test]
+    fn is_not_null_always_holds() {
+        assert!(translate(&INT4, "VALUE IS NOT NULL")
+            .unwrap()
+            .is_always_true());
+        assert!(!translate(&INT4, "VALUE > 0").unwrap().is_always_true());
+        assert_translates(&INT4, "other IS NOT NULL", None);
+    }
+
+    #[test]
+    fn untranslatable_checks() {
+        for check in [
+            "VALUE % 2 = 0",
+            "other > 0",
+            "VALUE > other",
+            "abs(VALUE) < 5",
+            "\"value\" > 0",
+            "VALUE IS NULL",
+            "VALUE LIKE 'a%'",
+        ] {
+            assert_translates(&INT4, check, None);
+        }
+        assert_translates(&DataType::Boolean, "VALUE = true", None);
+        assert_translates(&DataType::Text, "length(other) > 0", None);
+    }
+}
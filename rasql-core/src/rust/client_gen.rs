@@ -1,5 +1,8 @@
 
 pub trait AsyncClientCodeGenerator<Client: rasql_traits::r#async::Client> {
+    /// Create a token stream naming the type `Client`.
+    fn generate_client_type() -> proc_macro2::TokenStream;
+
     /// Create a token stream for usage of `client` to prepare `statement_str` for
     /// later execution, evaluating to a value of type
     /// `Result<Client::PreparedStatement, Client::PrepareError>`
@@ -89,6 +92,10 @@ pub trait AsyncClientCodeGenerator<Client: rasql_traits::r#async::Client> {
 
 #[cfg(feature = "tokio-postgres")]
 impl AsyncClientCodeGenerator<tokio_postgres::Client> for super::type_gen::TokioPostgresGenerator {
+    fn generate_client_type() -> proc_macro2::TokenStream {
+        quote::quote!(tokio_postgres::Client)
+    }
+
     fn generate_prepare_statement(
         client: &syn::Expr,
         statement_str: &syn::Expr,
@@ -1,11 +1,37 @@
-pub mod type_gen;
+mod check;
 pub mod client_gen;
+#[cfg(all(test, feature = "tokio-postgres"))]
+mod tests;
+pub mod type_gen;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use client_gen::AsyncClientCodeGenerator;
 use convert_case::Casing;
-use type_gen::TypeGenerator;
+use syn::ext::IdentExt;
+use type_gen::{TypeGenerator, UnsupportedDataType};
+
+/// Environment variables rasql-build sets for the crate being built, so that rasql-query can
+/// find the schema and generate code the same way.
+pub mod build_env {
+    /// Absolute path to the directory of migrations making up the schema.
+    pub const SCHEMA_DIR: &str = "RASQL_SCHEMA_DIR";
+    /// Which database client code is generated for, e.g. `tokio-postgres`.
+    pub const CLIENT: &str = "RASQL_CLIENT";
+    /// The path the generated code is included at, e.g. `crate`.
+    pub const GENERATED_MODULE_PATH: &str = "RASQL_GENERATED_MODULE_PATH";
+    /// See [super::type_gen::UseRustDecimal::name].
+    pub const USE_RUST_DECIMAL: &str = "RASQL_USE_RUST_DECIMAL";
+    /// See [super::type_gen::UseUuid::name].
+    pub const USE_UUID: &str = "RASQL_USE_UUID";
+    /// See [super::type_gen::UseDateTime::name].
+    pub const USE_DATE_TIME: &str = "RASQL_USE_DATE_TIME";
+    /// See [super::type_gen::UseSerdeJson::name].
+    pub const USE_SERDE_JSON: &str = "RASQL_USE_SERDE_JSON";
+    /// Absolute paths to extra files of function signatures, joined the way `PATH` is. See
+    /// [crate::sql::functions] for their format.
+    pub const FUNCTION_SIGNATURES: &str = "RASQL_FUNCTION_SIGNATURES";
+}
 
 pub struct TableStruct {
     pub name: syn::Ident,
@@ -15,50 +41,517 @@ pub struct TableStruct {
 
 pub struct TableStructField {
     pub name: syn::Ident,
+    /// The type of the field, already wrapped in `Option` if the column is nullable
     pub r#type: syn::Type,
     pub db_alias: Option<String>,
+    pub nullable: bool,
 }
 
 pub struct GeneratedTableStruct(pub proc_macro2::TokenStream);
 
+/// A Rust enum for an enum type the schema creates, with a variant for each of its labels.
+pub struct EnumType {
+    pub name: syn::Ident,
+    pub variants: Vec<EnumTypeVariant>,
+    pub db_alias: Option<String>,
+}
+
+pub struct EnumTypeVariant {
+    pub name: syn::Ident,
+    pub db_alias: Option<String>,
+}
+
+/// A newtype for a domain the schema creates, wrapping the Rust type of the domain's base type.
+pub struct DomainType {
+    pub name: syn::Ident,
+    pub base_type: syn::Type,
+    /// The domain's name in the database, which values of the domain itself are told apart by
+    pub db_name: String,
+}
+
+/// A newtype for the values of a column promoted with [IdPromoteMode], wrapping the Rust type of
+/// the column.
+pub struct IdType {
+    pub name: syn::Ident,
+    pub underlying: syn::Type,
+    /// The name of the untrusted counterpart of the type, if it's a
+    /// [TrustedId](rasql_traits::TrustedId).
+    pub untrusted_name: Option<syn::Ident>,
+    /// Whether the underlying type is `Copy`, which the ID types can only be if it is
+    pub copy: bool,
+}
+
 pub struct TableStructImpls {
     pub base_table_impl: proc_macro2::TokenStream,
+    /// Methods for fetching the rows the table's foreign keys reference, and the rows of other
+    /// tables whose foreign keys reference it
+    pub foreign_key_impl: proc_macro2::TokenStream,
     pub table_with_pk_impl: Option<proc_macro2::TokenStream>,
+    /// An implementation of [TableWithUnique](rasql_traits::r#async::TableWithUnique) for each
+    /// of the table's unique keys
+    pub table_with_unique_impls: Vec<proc_macro2::TokenStream>,
+    pub upsert_impl: Option<proc_macro2::TokenStream>,
 }
 
-fn sql_ident_to_type_name(ident: &sqlparser::ast::Ident) -> syn::Ident {
-    let mut ident = ident.value.to_case(convert_case::Case::Pascal);
-    if ident.chars().next().unwrap().is_ascii_digit() {
+/// Make an identifier in `case` out of `ident`, which is made raw if it's a keyword, so columns
+/// like `type` become `r#type`. The few keywords that can't be raw get an underscore appended
+/// instead. Characters identifiers can't contain separate words, so `?column?` becomes `column`,
+/// and an underscore is prepended to names starting with a digit.
+fn rust_ident(ident: &str, case: convert_case::Case) -> syn::Ident {
+    let mut ident = ident
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+        .to_case(case);
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
         ident.insert(0, '_');
     }
-    syn::Ident::new(&ident, proc_macro2::Span::call_site())
+    match ident.as_str() {
+        "self" | "Self" | "super" | "crate" | "_" => quote::format_ident!("{ident}_"),
+        _ if syn::parse_str::<syn::Ident>(&ident).is_err() => {
+            syn::Ident::new_raw(&ident, proc_macro2::Span::call_site())
+        }
+        _ => syn::Ident::new(&ident, proc_macro2::Span::call_site()),
+    }
 }
 
-fn sql_ident_to_field_name(ident: &sqlparser::ast::Ident) -> syn::Ident {
-    let mut ident = ident.value.to_case(convert_case::Case::Snake);
-    if ident.chars().next().unwrap().is_ascii_digit() {
-        ident.insert(0, '_');
-    }
-    syn::Ident::new(&ident, proc_macro2::Span::call_site())
+pub fn sql_ident_to_type_name(ident: &sqlparser::ast::Ident) -> syn::Ident {
+    rust_ident(&ident.value, convert_case::Case::Pascal)
+}
+
+pub fn sql_ident_to_field_name(ident: &sqlparser::ast::Ident) -> syn::Ident {
+    rust_ident(&ident.value, convert_case::Case::Snake)
 }
 
 #[inline]
-fn sql_ident_to_module_name(ident: &sqlparser::ast::Ident) -> syn::Ident {
+pub fn sql_ident_to_module_name(ident: &sqlparser::ast::Ident) -> syn::Ident {
     sql_ident_to_field_name(ident)
 }
 
-fn generate_table_struct_and_impls<
+/// Generate a module for each schema in `catalog`, holding a struct for each of its tables,
+/// views and composite types, an enum for each of its enum types, a newtype for each of its
+/// domains, and a function calling each of its SQL functions if
+/// [CodeGenConfig::function_wrappers] is set. Tables' structs implement
+/// [Table](rasql_traits::r#async::Table), and
+/// [TableWithPK](rasql_traits::r#async::TableWithPK) if they have a primary key, and
+/// [TableWithUnique](rasql_traits::r#async::TableWithUnique) for each of their other unique keys,
+/// and [Upsert](rasql_traits::r#async::Upsert) if they have either, for the client `generator`
+/// generates code for. They also get methods for following foreign keys in either direction.
+pub fn generate_catalog<
+    Traits: rasql_traits::DbTraits,
+    Client: rasql_traits::r#async::Client<Traits = Traits>,
+    Generator: TypeGenerator<Traits> + AsyncClientCodeGenerator<Client>,
+>(
+    catalog: &crate::sql::Catalog,
+    config: &CodeGenConfig,
+    generator: &Generator,
+) -> Result<proc_macro2::TokenStream, UnsupportedDataType> {
+    let modules = catalog
+        .schemas()
+        .map(|schema| {
+            let module_name = sql_ident_to_module_name(&schema.name);
+            let module_config = config.module_configs.get(&module_name);
+            let use_statements = module_config
+                .into_iter()
+                .flat_map(|config| &config.use_statements);
+            let types = schema
+                .types
+                .values()
+                .filter_map(|r#type| match r#type {
+                    crate::sql::Type::Enum { name, variants } => {
+                        Some(Ok(generate_enum(name, variants, generator)))
+                    }
+                    // Tables' row types are the tables' structs
+                    crate::sql::Type::Composite { name, .. } if catalog.table(name).is_some() => {
+                        None
+                    }
+                    crate::sql::Type::Composite { name, fields } => Some(
+                        generate_composite_struct(catalog, name, fields, module_config, generator)
+                            .map(|(_, GeneratedTableStruct(composite_struct))| composite_struct),
+                    ),
+                    crate::sql::Type::Domain { name, base, checks } => {
+                        Some(generate_domain(catalog, name, base, checks, generator))
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let table_structs = schema
+                .tables
+                .values()
+                .map(|table| {
+                    let (
+                        GeneratedTableStruct(table_struct),
+                        TableStructImpls {
+                            base_table_impl,
+                            foreign_key_impl,
+                            table_with_pk_impl,
+                            table_with_unique_impls,
+                            upsert_impl,
+                        },
+                    ) = generate_table_struct_and_impls(
+                        catalog, table, config, generator, generator,
+                    )?;
+                    Ok(quote::quote! {
+                        #table_struct
+                        #base_table_impl
+                        #foreign_key_impl
+                        #table_with_pk_impl
+                        #(#table_with_unique_impls)*
+                        #upsert_impl
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let views = schema
+                .views
+                .values()
+                .map(|view| {
+                    generate_view_struct_and_impls(
+                        catalog,
+                        view,
+                        module_config,
+                        generator,
+                        generator,
+                    ).map(
+                        |(GeneratedTableStruct(view_struct), impls)| quote::quote!(#view_struct #impls),
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let function_wrappers = if config.function_wrappers {
+                schema
+                    .functions
+                    .values()
+                    .flat_map(|overloads| overloads.iter().enumerate())
+                    .map(|(overload, function)| {
+                        generate_function_wrapper(catalog, function, overload, generator)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?
+            } else {
+                vec![]
+            };
+            Ok(quote::quote!(
+                pub mod #module_name {
+                    #(#use_statements)*
+                    #(#types)*
+                    #(#table_structs)*
+                    #(#views)*
+                    #(#function_wrappers)*
+                }
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(quote::quote!(#(#modules)*))
+}
+
+pub fn generate_table_struct_and_impls<
     Traits: rasql_traits::DbTraits,
     TypeGen: TypeGenerator<Traits>,
     Client: rasql_traits::r#async::Client<Traits = Traits>,
     ClientGen: AsyncClientCodeGenerator<Client>,
 >(
+    catalog: &crate::sql::Catalog,
+    table: &crate::sql::Table,
+    config: &CodeGenConfig,
+    type_gen: &TypeGen,
+    client_gen: &ClientGen,
+) -> Result<(GeneratedTableStruct, TableStructImpls), UnsupportedDataType> {
+    let (table_struct, generated_table_struct) =
+        generate_table_struct(catalog, table, config, type_gen)?;
+    let impls = TableStructImpls {
+        base_table_impl: generate_base_table_impl(table, &table_struct, client_gen),
+        foreign_key_impl: generate_foreign_key_impl(
+            catalog,
+            table,
+            &table_struct,
+            config,
+            type_gen,
+            client_gen,
+        )?,
+        table_with_pk_impl: generate_table_with_pk_impl(table, &table_struct, client_gen),
+        table_with_unique_impls: unique_keys(catalog, table)
+            .into_iter()
+            .map(|(columns, predicate)| {
+                generate_table_with_unique_impl(
+                    table,
+                    &table_struct,
+                    &columns,
+                    predicate,
+                    client_gen,
+                )
+            })
+            .collect(),
+        upsert_impl: generate_upsert_impl(catalog, table, &table_struct, client_gen),
+    };
+    Ok((generated_table_struct, impls))
+}
+
+/// Generate the struct for a table's rows. Columns that reference a column promoted with
+/// [IdPromoteMode] through a foreign key use the referenced column's ID type, which is why the
+/// whole [CodeGenConfig] is needed.
+pub fn generate_table_struct<Traits: rasql_traits::DbTraits, TypeGen: TypeGenerator<Traits>>(
+    catalog: &crate::sql::Catalog,
+    table: &crate::sql::Table,
+    config: &CodeGenConfig,
+    type_gen: &TypeGen,
+) -> Result<(TableStruct, GeneratedTableStruct), UnsupportedDataType> {
+    let module_config = config
+        .module_configs
+        .get(&sql_ident_to_module_name(&table.name.0[0]));
+    let columns = table
+        .columns
+        .iter()
+        .map(|column| (&column.name, &column.data_type, table.is_nullable(column)));
+    let referenced_id = |column: &sqlparser::ast::Ident| {
+        referenced_id_type(catalog, config, table, column, &mut vec![])
+    };
+    generate_relation_struct(
+        catalog,
+        &table.name,
+        columns,
+        module_config,
+        referenced_id,
+        type_gen,
+    )
+}
+
+/// The path to the ID type of the promoted column that `column` of `table` references through a
+/// foreign key, following references from columns that aren't promoted themselves. `visited`
+/// holds the columns already followed, so a cycle of foreign keys ends.
+fn referenced_id_type(
+    catalog: &crate::sql::Catalog,
+    config: &CodeGenConfig,
     table: &crate::sql::Table,
+    column: &sqlparser::ast::Ident,
+    visited: &mut Vec<(sqlparser::ast::ObjectName, sqlparser::ast::Ident)>,
+) -> Option<syn::Type> {
+    visited.push((table.name.clone(), column.clone()));
+    table.constraints.iter().find_map(|constraint| {
+        let sqlparser::ast::TableConstraint::ForeignKey {
+            columns,
+            foreign_table,
+            referred_columns,
+            ..
+        } = constraint
+        else {
+            return None;
+        };
+        let index = columns.iter().position(|name| name == column)?;
+        let referred_column = referred_columns.get(index)?;
+        if visited.contains(&(foreign_table.clone(), referred_column.clone())) {
+            return None;
+        }
+        let foreign_table = catalog.table(foreign_table)?;
+        let module_name = sql_ident_to_module_name(&foreign_table.name.0[0]);
+        let struct_name = sql_ident_to_type_name(&foreign_table.name.0[1]);
+        let field_config = config
+            .module_configs
+            .get(&module_name)
+            .and_then(|config| config.struct_configs.get(&struct_name))
+            .and_then(|config| config.field_configs.get(referred_column));
+        match field_config {
+            Some(StructFieldCodeGenConfig {
+                override_type: None,
+                id_promote_mode: IdPromoteMode::Id | IdPromoteMode::TrustedId,
+                ..
+            }) => {
+                let id_name = id_type_name(&struct_name, referred_column);
+                Some(syn::parse_quote!(super::#module_name::#id_name))
+            }
+            _ => referenced_id_type(catalog, config, foreign_table, referred_column, visited),
+        }
+    })
+}
+
+/// The name of the ID type for `column` of the struct `struct_name`, e.g. `UsersId`.
+fn id_type_name(struct_name: &syn::Ident, column: &sqlparser::ast::Ident) -> syn::Ident {
+    quote::format_ident!("{}{}", struct_name, sql_ident_to_type_name(column))
+}
+
+/// Generate the struct for a view's rows, along with an implementation of
+/// [Relation](rasql_traits::r#async::Relation) for selecting them. Materialized views also get a
+/// `refresh` function.
+pub fn generate_view_struct_and_impls<
+    Traits: rasql_traits::DbTraits,
+    TypeGen: TypeGenerator<Traits>,
+    Client: rasql_traits::r#async::Client<Traits = Traits>,
+    ClientGen: AsyncClientCodeGenerator<Client>,
+>(
+    catalog: &crate::sql::Catalog,
+    view: &crate::sql::View,
     module_config: Option<&ModuleCodeGenConfig>,
     type_gen: &TypeGen,
     client_gen: &ClientGen,
-) -> (GeneratedTableStruct, TableStructImpls) {
-    let name = sql_ident_to_type_name(table.name.0.last().unwrap());
+) -> Result<(GeneratedTableStruct, proc_macro2::TokenStream), UnsupportedDataType> {
+    let columns = view
+        .columns
+        .iter()
+        .map(|column| (&column.name, &column.r#type, column.nullable));
+    let (table_struct, generated_table_struct) = generate_relation_struct(
+        catalog,
+        &view.name,
+        columns,
+        module_config,
+        |_| None,
+        type_gen,
+    )?;
+    let relation_impl = generate_relation_impl(&view.name, &table_struct, client_gen);
+    let refresh = view
+        .materialized
+        .then(|| generate_refresh(&view.name, &table_struct, client_gen));
+    Ok((
+        generated_table_struct,
+        quote::quote!(#relation_impl #refresh),
+    ))
+}
+
+/// Generate the struct for the composite type `name`, which has the fields `fields`.
+pub fn generate_composite_struct<Traits: rasql_traits::DbTraits, TypeGen: TypeGenerator<Traits>>(
+    catalog: &crate::sql::Catalog,
+    name: &sqlparser::ast::ObjectName,
+    fields: &[crate::sql::Field],
+    module_config: Option<&ModuleCodeGenConfig>,
+    type_gen: &TypeGen,
+) -> Result<(TableStruct, GeneratedTableStruct), UnsupportedDataType> {
+    let fields = fields
+        .iter()
+        .map(|field| (&field.name, &field.r#type, field.nullable));
+    generate_relation_struct(catalog, name, fields, module_config, |_| None, type_gen)
+}
+
+/// Generate the Rust enum for the enum type `name`, which has the labels `labels`. Labels that
+/// would get the same variant name are numbered, `InProgress`, `InProgress2` and so on.
+pub fn generate_enum<Traits: rasql_traits::DbTraits, TypeGen: TypeGenerator<Traits>>(
+    name: &sqlparser::ast::ObjectName,
+    labels: &[sqlparser::ast::Ident],
+    type_gen: &TypeGen,
+) -> proc_macro2::TokenStream {
+    let db_alias = |name: &syn::Ident, sql_name: &sqlparser::ast::Ident| {
+        (*name != sql_name.value).then(|| sql_name.value.clone())
+    };
+    let sql_name = name.0.last().unwrap();
+    let name = sql_ident_to_type_name(sql_name);
+    // Labels that only differ in case or punctuation, like 'in progress' and 'In-Progress',
+    // convert to the same variant name, so later ones are numbered to keep them apart
+    let mut variant_names = HashSet::new();
+    let enum_type = EnumType {
+        db_alias: db_alias(&name, sql_name),
+        variants: labels
+            .iter()
+            .map(|label| {
+                let base_name = sql_ident_to_type_name(label);
+                let mut name = base_name.clone();
+                let mut number = 2;
+                while !variant_names.insert(name.to_string()) {
+                    name = quote::format_ident!("{base_name}{number}");
+                    number += 1;
+                }
+                EnumTypeVariant {
+                    db_alias: db_alias(&name, label),
+                    name,
+                }
+            })
+            .collect(),
+        name,
+    };
+    type_gen.generate_enum(&enum_type)
+}
+
+/// Generate the newtype for the domain `name` over `base`. When every one of the domain's
+/// `checks` can be translated into Rust it also gets a `new` constructor, which checks the
+/// value before wrapping it. Otherwise values can only be wrapped with `new_unchecked`, leaving
+/// the database to enforce the checks.
+pub fn generate_domain<Traits: rasql_traits::DbTraits, TypeGen: TypeGenerator<Traits>>(
+    catalog: &crate::sql::Catalog,
+    name: &sqlparser::ast::ObjectName,
+    base: &sqlparser::ast::DataType,
+    checks: &[sqlparser::ast::Expr],
+    type_gen: &TypeGen,
+) -> Result<proc_macro2::TokenStream, UnsupportedDataType> {
+    let sql_name = name.0.last().unwrap();
+    let domain_type = DomainType {
+        name: sql_ident_to_type_name(sql_name),
+        base_type: type_gen.sql_datatype_to_rust_type(&catalog.qualified_type(base))?,
+        db_name: sql_name.value.clone(),
+    };
+    let newtype = type_gen.generate_domain(&domain_type);
+
+    let conditions = checks
+        .iter()
+        .map(|check| check::translate_check(base, check))
+        .collect::<Option<Vec<_>>>();
+    let constructor = conditions.map(|conditions| {
+        let DomainType {
+            name,
+            base_type,
+            db_name,
+        } = &domain_type;
+        let (checks, violations): (Vec<_>, Vec<_>) = checks
+            .iter()
+            .zip(conditions)
+            .filter(|(_, condition)| !condition.is_always_true())
+            .map(|(check, condition)| (check.to_string(), condition.not().tokens))
+            .unzip();
+        quote::quote!(
+            impl #name {
+                /// Wrap `value`, checking it satisfies the domain's `CHECK` constraints first.
+                pub fn new(value: #base_type) -> Result<Self, rasql_traits::CheckViolation> {
+                    #(
+                        if #violations {
+                            return Err(rasql_traits::CheckViolation {
+                                domain: #db_name,
+                                check: #checks,
+                            });
+                        }
+                    )*
+                    Ok(Self(value))
+                }
+            }
+        )
+    });
+    let DomainType {
+        name, base_type, ..
+    } = &domain_type;
+    let accessors = quote::quote!(
+        impl #name {
+            /// Wrap `value` without checking the domain's `CHECK` constraints, which the
+            /// database still enforces when it's stored.
+            pub fn new_unchecked(value: #base_type) -> Self {
+                Self(value)
+            }
+
+            pub fn get(&self) -> &#base_type {
+                &self.0
+            }
+
+            pub fn into_inner(self) -> #base_type {
+                self.0
+            }
+        }
+    );
+    Ok(quote::quote!(#newtype #accessors #constructor))
+}
+
+/// Generate the struct for the rows of the table or view `relation_name`, which has a column
+/// for each of `columns`' names, types and whether they're nullable. Composite types are
+/// generated the same way, with a field for each of theirs.
+///
+/// Columns promoted with [IdPromoteMode] get an ID type generated alongside the struct, while
+/// `referenced_id` gives the ID type a column that isn't promoted itself should use instead of
+/// its own type, if any.
+fn generate_relation_struct<'c, Traits: rasql_traits::DbTraits, TypeGen: TypeGenerator<Traits>>(
+    catalog: &crate::sql::Catalog,
+    relation_name: &sqlparser::ast::ObjectName,
+    columns: impl Iterator<
+        Item = (
+            &'c sqlparser::ast::Ident,
+            &'c sqlparser::ast::DataType,
+            bool,
+        ),
+    >,
+    module_config: Option<&ModuleCodeGenConfig>,
+    referenced_id: impl Fn(&sqlparser::ast::Ident) -> Option<syn::Type>,
+    type_gen: &TypeGen,
+) -> Result<(TableStruct, GeneratedTableStruct), UnsupportedDataType> {
+    let name = sql_ident_to_type_name(relation_name.0.last().unwrap());
     let default_struct_config = StructCodeGenConfig {
         field_configs: HashMap::new(),
         deny_extra_fields: false,
@@ -67,10 +560,15 @@ fn generate_table_struct_and_impls<
         .and_then(|config| config.struct_configs.get(&name))
         .unwrap_or(&default_struct_config);
 
-    let fields = table
-        .columns
-        .iter()
-        .map(|column| {
+    let mut id_types = vec![];
+    let fields = columns
+        .map(|(column_name, data_type, nullable)| {
+            let rust_type = || {
+                type_gen
+                    .sql_datatype_to_rust_type(&catalog.qualified_type(data_type))
+                    .map_err(|error| error.in_column(relation_name, column_name))
+            };
+            let id_name = id_type_name(&name, column_name);
             let default_field_config = StructFieldCodeGenConfig {
                 rename: None,
                 override_type: None,
@@ -80,54 +578,1381 @@ fn generate_table_struct_and_impls<
 
             let field_config = struct_config
                 .field_configs
-                .get(&column.name)
+                .get(column_name)
                 .unwrap_or(&default_field_config);
 
             let (name, db_alias) = match &field_config.rename {
-                Some(rename) => (rename.clone(), Some(column.name.value.clone())),
+                Some(rename) => (rename.clone(), Some(column_name.value.clone())),
                 None => {
-                    let name = sql_ident_to_field_name(&column.name);
-                    if name.to_string() == column.name.value {
+                    let name = sql_ident_to_field_name(column_name);
+                    if name.unraw() == column_name.value {
                         (name, None)
                     } else {
-                        (name, Some(column.name.value.clone()))
+                        (name, Some(column_name.value.clone()))
                     }
                 }
             };
 
+            // Overridden types are used exactly as given, so it's up to the override to handle NULL
             let r#type = match (&field_config.override_type, field_config.id_promote_mode) {
                 (Some(r#type), _) => r#type.clone(),
-                (None, IdPromoteMode::None) => type_gen.sql_datatype_to_rust_type(&column.data_type).unwrap(),
-                (None, IdPromoteMode::TrustedId) => todo!(),
-                (None, IdPromoteMode::Id) => todo!(),
+                (None, id_promote_mode) => {
+                    let r#type = match id_promote_mode {
+                        IdPromoteMode::None => match referenced_id(column_name) {
+                            Some(r#type) => r#type,
+                            None => rust_type()?,
+                        },
+                        IdPromoteMode::Id | IdPromoteMode::TrustedId => {
+                            let underlying = rust_type()?;
+                            let data_type = catalog.qualified_type(data_type);
+                            if !type_gen.sql_datatype_is_eq_and_hash(&data_type) {
+                                return Err(UnsupportedDataType::new(data_type)
+                                    .in_column(relation_name, column_name)
+                                    .as_id());
+                            }
+                            let id_type = IdType {
+                                untrusted_name: matches!(id_promote_mode, IdPromoteMode::TrustedId)
+                                    .then(|| quote::format_ident!("Untrusted{}", id_name)),
+                                name: id_name,
+                                underlying,
+                                copy: type_gen.sql_datatype_is_copy(&data_type),
+                            };
+                            let id_name = &id_type.name;
+                            let r#type = syn::parse_quote!(#id_name);
+                            id_types.push(generate_id_type(&id_type, type_gen));
+                            r#type
+                        }
+                    };
+                    if nullable {
+                        syn::Type::Verbatim(quote::quote! {Option<#r#type>})
+                    } else {
+                        r#type
+                    }
+                }
             };
 
-            TableStructField {
+            Ok(TableStructField {
                 name,
                 r#type,
                 db_alias,
-            }
+                nullable,
+            })
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
+
+    let db_alias = {
+        let table_name = &relation_name.0.last().unwrap().value;
+        (name != table_name).then(|| table_name.clone())
+    };
 
     let table_struct = TableStruct {
         name,
         fields,
-        db_alias: todo!(),
+        db_alias,
+    };
+    let generated_struct = type_gen.generate_table_struct(&table_struct);
+    let generated_table_struct =
+        GeneratedTableStruct(quote::quote!(#(#id_types)* #generated_struct));
+    Ok((table_struct, generated_table_struct))
+}
+
+/// Generate an ID type along with its implementation of [Id](rasql_traits::Id), or of
+/// [TrustedId](rasql_traits::TrustedId) and its untrusted counterpart's implementation of
+/// [UntrustedId](rasql_traits::UntrustedId).
+fn generate_id_type<Traits: rasql_traits::DbTraits, TypeGen: TypeGenerator<Traits>>(
+    id_type: &IdType,
+    type_gen: &TypeGen,
+) -> proc_macro2::TokenStream {
+    let IdType {
+        name,
+        underlying,
+        untrusted_name,
+        ..
+    } = id_type;
+    let newtypes = type_gen.generate_id(id_type);
+    let impls = match untrusted_name {
+        None => quote::quote! {
+            impl rasql_traits::Id for #name {
+                type Underlying = #underlying;
+
+                fn to_underlying(self) -> #underlying {
+                    self.0
+                }
+
+                fn from_underlying(underlying: #underlying) -> Self {
+                    Self(underlying)
+                }
+            }
+        },
+        Some(untrusted_name) => quote::quote! {
+            impl rasql_traits::TrustedId for #name {
+                type Underlying = #underlying;
+
+                type Untrusted = #untrusted_name;
+
+                unsafe fn from_untrusted_unchecked(untrusted: #untrusted_name) -> Self {
+                    Self(untrusted.0)
+                }
+
+                fn to_untrusted(self) -> #untrusted_name {
+                    #untrusted_name(self.0)
+                }
+
+                fn to_underlying(self) -> #underlying {
+                    self.0
+                }
+            }
+
+            impl rasql_traits::UntrustedId for #untrusted_name {
+                type Underlying = #underlying;
+
+                type Trusted = #name;
+
+                fn from_underlying(underlying: #underlying) -> Self {
+                    Self(underlying)
+                }
+
+                fn from_trusted(trusted: #name) -> Self {
+                    Self(trusted.0)
+                }
+
+                unsafe fn to_trusted_unchecked(self) -> #name {
+                    #name(self.0)
+                }
+            }
+        },
+    };
+    quote::quote!(#newtypes #impls)
+}
+
+/// Generate an async function that calls the SQL function `function`, which is the
+/// `overload`th function in its schema with that name. Rust doesn't have overloading, so
+/// overloads after the first are numbered, as in `f`, `f_2`, `f_3`.
+///
+/// Functions returning columns, whether declared with `RETURNS TABLE`, `OUT` parameters or a
+/// composite type, also get a struct for their rows. Every value a function returns is
+/// nullable, so each is an `Option`.
+fn generate_function_wrapper<
+    Traits: rasql_traits::DbTraits,
+    Client: rasql_traits::r#async::Client<Traits = Traits>,
+    Generator: TypeGenerator<Traits> + AsyncClientCodeGenerator<Client>,
+>(
+    catalog: &crate::sql::Catalog,
+    function: &crate::sql::Function,
+    overload: usize,
+    generator: &Generator,
+) -> Result<proc_macro2::TokenStream, UnsupportedDataType> {
+    use crate::sql::{FunctionReturns, Type};
+
+    let mut name = sql_ident_to_field_name(&function.name.0[1]);
+    if overload > 0 {
+        name = quote::format_ident!("{name}_{}", overload + 1);
+    }
+    let doc = format!(" Call the SQL function `{}`.", function.name);
+
+    let client_type = Generator::generate_client_type();
+    let client: syn::Expr = syn::parse_quote!(client);
+    let statement: syn::Expr = syn::parse_quote!(&__rasql_statement);
+    let row: syn::Expr = syn::parse_quote!((&__rasql_row));
+
+    let parameter_names: Vec<_> = function
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(index, parameter)| {
+            let name = match &parameter.name {
+                Some(name) => sql_ident_to_field_name(name),
+                None => quote::format_ident!("arg{}", index + 1),
+            };
+            // Don't clash with the client
+            if name == "client" {
+                quote::format_ident!("client_")
+            } else {
+                name
+            }
+        })
+        .collect();
+    let parameter_types = function
+        .parameters
+        .iter()
+        .map(|parameter| {
+            generator.sql_datatype_to_rust_param_type(&catalog.qualified_type(&parameter.r#type))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let parameter_refs: Vec<syn::Expr> = parameter_names
+        .iter()
+        .map(|name| syn::parse_quote!(&#name))
+        .collect();
+    let parameter_refs: Vec<_> = parameter_refs.iter().collect();
+
+    // Casting each parameter picks out this overload, whatever the others are
+    let args = function
+        .parameters
+        .iter()
+        .enumerate()
+        .map(|(index, parameter)| format!("${}::{}", index + 1, parameter.r#type))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let call = format!("{}({args})", quoted_name(&function.name));
+
+    let columns = match &function.returns {
+        FunctionReturns::Columns(columns) => Some(columns),
+        FunctionReturns::Value(sqlparser::ast::DataType::Custom(type_name, _)) => {
+            match catalog.r#type(type_name) {
+                Some(Type::Composite { fields, .. }) => Some(fields),
+                _ => None,
+            }
+        }
+        _ => None,
     };
-    (
-        GeneratedTableStruct(type_gen.generate_table_struct(&table_struct)),
-        TableStructImpls {
-            base_table_impl: todo!(),
-            table_with_pk_impl: todo!(),
+    let sql = match (columns, &function.returns) {
+        (Some(_), _) => format!("SELECT * FROM {call}"),
+        (None, FunctionReturns::Void) => format!("SELECT {call}"),
+        (None, _) => format!("SELECT {call} AS value"),
+    };
+    let prepare = Generator::generate_prepare_statement(&client, &syn::parse_quote!(#sql));
+    let query_many =
+        Generator::generate_query_many_with_statement(&client, &statement, &parameter_refs);
+    let query_one =
+        Generator::generate_query_one_or_none_with_statement(&client, &statement, &parameter_refs);
+
+    let mut row_struct = None;
+    let (output_type, output) = match (columns, &function.returns) {
+        (Some(columns), _) => {
+            let row_name =
+                sql_ident_to_type_name(&sqlparser::ast::Ident::new(format!("{name}_row")));
+            let field_names: Vec<_> = columns
+                .iter()
+                .map(|column| sql_ident_to_field_name(&column.name))
+                .collect();
+            let field_types = columns
+                .iter()
+                .map(|column| {
+                    generator.sql_datatype_to_rust_type(&catalog.qualified_type(&column.r#type))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let field_reads = columns.iter().map(|column| {
+                let column_name = &column.name.value;
+                Generator::generate_row_read_column(&row, &syn::parse_quote!(#column_name))
+            });
+            let row_doc = format!(" A row returned by `{}`.", function.name);
+            row_struct = Some(quote::quote! {
+                #[doc = #row_doc]
+                #[derive(Debug, Clone)]
+                pub struct #row_name {
+                    #(pub #field_names: Option<#field_types>,)*
+                }
+            });
+            let read_row = quote::quote!(#row_name { #(#field_names: #field_reads?,)* });
+            if function.returns_set {
+                (
+                    quote::quote!(Vec<#row_name>),
+                    quote::quote!({
+                        let mut __rasql_output = Vec::new();
+                        for __rasql_row in #query_many? {
+                            __rasql_output.push(#read_row);
+                        }
+                        __rasql_output
+                    }),
+                )
+            } else {
+                (
+                    quote::quote!(Option<#row_name>),
+                    quote::quote!(match #query_one? {
+                        Some(__rasql_row) => Some(#read_row),
+                        None => None,
+                    }),
+                )
+            }
+        }
+        (None, FunctionReturns::Value(r#type)) => {
+            let r#type = generator.sql_datatype_to_rust_type(&catalog.qualified_type(r#type))?;
+            let read = Generator::generate_row_read_column(&row, &syn::parse_quote!("value"));
+            if function.returns_set {
+                (
+                    quote::quote!(Vec<Option<#r#type>>),
+                    quote::quote!({
+                        let mut __rasql_output = Vec::new();
+                        for __rasql_row in #query_many? {
+                            __rasql_output.push(#read?);
+                        }
+                        __rasql_output
+                    }),
+                )
+            } else {
+                (
+                    quote::quote!(Option<#r#type>),
+                    quote::quote!(match #query_one? {
+                        Some(__rasql_row) => #read?,
+                        None => None,
+                    }),
+                )
+            }
+        }
+        _ => (
+            quote::quote!(()),
+            quote::quote!({
+                #query_many?;
+            }),
+        ),
+    };
+
+    Ok(quote::quote! {
+        #row_struct
+
+        #[doc = #doc]
+        pub async fn #name(
+            client: &#client_type,
+            #(#parameter_names: &#parameter_types,)*
+        ) -> Result<#output_type, <#client_type as rasql_traits::r#async::Client>::Error> {
+            let __rasql_statement = #prepare?;
+            Ok(#output)
+        }
+    })
+}
+
+/// The name of the column a field of a generated struct holds.
+fn column_name(field: &TableStructField) -> String {
+    field
+        .db_alias
+        .clone()
+        .unwrap_or_else(|| field.name.unraw().to_string())
+}
+
+/// The name of `field`'s column, quoted so it's used exactly as given.
+fn quoted_column_name(field: &TableStructField) -> String {
+    sqlparser::ast::Ident::with_quote('"', column_name(field)).to_string()
+}
+
+/// How `column` is generated, if it's an identity or generated column.
+fn generated_as(column: &sqlparser::ast::ColumnDef) -> Option<&sqlparser::ast::GeneratedAs> {
+    column
+        .options
+        .iter()
+        .find_map(|option| match &option.option {
+            sqlparser::ast::ColumnOption::Generated { generated_as, .. } => Some(generated_as),
+            _ => None,
+        })
+}
+
+/// An `INSERT` of a row of `table` from parameters, along with the fields of `table_struct` to
+/// pass as those parameters.
+fn insert_sql<'a>(
+    table: &crate::sql::Table,
+    table_struct: &'a TableStruct,
+) -> (String, Vec<&'a TableStructField>) {
+    // Generated columns are always computed by the database, so they can't be inserted.
+    // Identity columns can be, but only if the insert says so when they're `GENERATED ALWAYS`.
+    let inserted: Vec<_> = table
+        .columns
+        .iter()
+        .zip(&table_struct.fields)
+        .filter(|(column, _)| {
+            !matches!(
+                generated_as(column),
+                Some(sqlparser::ast::GeneratedAs::ExpStored)
+            )
+        })
+        .collect();
+    let overriding = inserted.iter().any(|(column, _)| {
+        matches!(
+            generated_as(column),
+            Some(sqlparser::ast::GeneratedAs::Always)
+        )
+    });
+    let sql = format!(
+        "INSERT INTO {} ({}){} VALUES ({})",
+        quoted_name(&table.name),
+        inserted
+            .iter()
+            .map(|(_, field)| quoted_column_name(field))
+            .collect::<Vec<_>>()
+            .join(", "),
+        if overriding {
+            " OVERRIDING SYSTEM VALUE"
+        } else {
+            ""
         },
+        (1..=inserted.len())
+            .map(|index| format!("${index}"))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    (sql, inserted.into_iter().map(|(_, field)| field).collect())
+}
+
+/// The fields of `table_struct` whose columns can be updated in a row of `table` found by
+/// `key`. Generated columns can't be updated, and nor can identity columns that are
+/// `GENERATED ALWAYS`.
+fn updated_fields<'a>(
+    table: &crate::sql::Table,
+    table_struct: &'a TableStruct,
+    key: &[&TableStructField],
+) -> Vec<&'a TableStructField> {
+    table
+        .columns
+        .iter()
+        .zip(&table_struct.fields)
+        .filter(|(column, field)| {
+            !key.iter().any(|key_field| key_field.name == field.name)
+                && !matches!(
+                    generated_as(column),
+                    Some(
+                        sqlparser::ast::GeneratedAs::ExpStored
+                            | sqlparser::ast::GeneratedAs::Always
+                    )
+                )
+        })
+        .map(|(_, field)| field)
+        .collect()
+}
+
+/// The fields of `table_struct` holding `columns` of `table`, in the order of `columns`. Schema
+/// analysis keeps keys in step with the columns they're on, so every one has to exist.
+fn key_fields<'a>(
+    table: &crate::sql::Table,
+    table_struct: &'a TableStruct,
+    columns: &[sqlparser::ast::Ident],
+) -> Vec<&'a TableStructField> {
+    columns
+        .iter()
+        .map(|key_column| {
+            let index = table
+                .columns
+                .iter()
+                .position(|column| column.name == *key_column)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "key column `{key_column}` isn't a column of `{}`",
+                        table.name
+                    )
+                });
+            &table_struct.fields[index]
+        })
+        .collect()
+}
+
+/// A `WHERE` clause comparing each of `fields`' columns with a parameter, numbering the
+/// parameters from `first_parameter`.
+fn key_condition(fields: &[&TableStructField], first_parameter: usize) -> String {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            format!(
+                "{} = ${}",
+                quoted_column_name(field),
+                first_parameter + index
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+/// A `SELECT` of every column of `table_struct` from the relation `relation_name`.
+fn select_sql(relation_name: &sqlparser::ast::ObjectName, table_struct: &TableStruct) -> String {
+    format!(
+        "SELECT {} FROM {}",
+        table_struct
+            .fields
+            .iter()
+            .map(quoted_column_name)
+            .collect::<Vec<_>>()
+            .join(", "),
+        quoted_name(relation_name)
     )
 }
 
+/// An expression building `table_struct`, whose path is `struct_path`, from `row`, whose columns
+/// are named after its fields.
+fn generate_read_row<
+    Client: rasql_traits::r#async::Client,
+    ClientGen: AsyncClientCodeGenerator<Client>,
+>(
+    struct_path: &syn::Path,
+    table_struct: &TableStruct,
+    row: &syn::Expr,
+) -> proc_macro2::TokenStream {
+    let field_names = table_struct.fields.iter().map(|field| &field.name);
+    let field_reads = table_struct.fields.iter().map(|field| {
+        let column_name = column_name(field);
+        ClientGen::generate_row_read_column(row, &syn::parse_quote!(#column_name))
+    });
+    quote::quote!(#struct_path { #(#field_names: #field_reads?,)* })
+}
+
+/// `name` quoted, so it's used exactly as given rather than being folded to lower case.
+fn quoted_name(name: &sqlparser::ast::ObjectName) -> sqlparser::ast::ObjectName {
+    sqlparser::ast::ObjectName(
+        name.0
+            .iter()
+            .map(|ident| sqlparser::ast::Ident::with_quote('"', &ident.value))
+            .collect(),
+    )
+}
+
+/// Generate the implementation of [Relation](rasql_traits::r#async::Relation) for
+/// `table_struct`, which holds the rows of the table or view `relation_name`.
+fn generate_relation_impl<
+    Client: rasql_traits::r#async::Client,
+    ClientGen: AsyncClientCodeGenerator<Client>,
+>(
+    relation_name: &sqlparser::ast::ObjectName,
+    table_struct: &TableStruct,
+    _client_gen: &ClientGen,
+) -> proc_macro2::TokenStream {
+    let struct_name = &table_struct.name;
+    let schema = &relation_name.0[0].value;
+    let name = &relation_name.0[1].value;
+    let client_type = ClientGen::generate_client_type();
+    let client: syn::Expr = syn::parse_quote!(client);
+    let statement: syn::Expr = syn::parse_quote!(select_all_statement);
+    let row: syn::Expr = syn::parse_quote!((&row));
+
+    let sql = select_sql(relation_name, table_struct);
+    let prepare = ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#sql));
+    let query = ClientGen::generate_query_many_with_statement(&client, &statement, &[]);
+    let read_row =
+        generate_read_row::<Client, ClientGen>(&syn::parse_quote!(Self), table_struct, &row);
+
+    quote::quote! {
+        impl rasql_traits::r#async::Relation<#client_type> for #struct_name {
+            const SCHEMA: Option<&str> = Some(#schema);
+            const NAME: &str = #name;
+
+            type SelectAllStatement =
+                <#client_type as rasql_traits::r#async::Client>::PreparedStatement;
+
+            async fn prepare_select_all(
+                client: &#client_type,
+            ) -> Result<
+                Self::SelectAllStatement,
+                <#client_type as rasql_traits::r#async::Client>::PrepareError,
+            > {
+                #prepare
+            }
+
+            async fn select_all<Container: FromIterator<Self>>(
+                client: &#client_type,
+                select_all_statement: &Self::SelectAllStatement,
+            ) -> Result<Container, <#client_type as rasql_traits::r#async::Client>::QueryError> {
+                #query?
+                    .into_iter()
+                    .map(|row| Ok(#read_row))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Generate a `refresh` function for the struct of the materialized view `view_name`.
+fn generate_refresh<
+    Client: rasql_traits::r#async::Client,
+    ClientGen: AsyncClientCodeGenerator<Client>,
+>(
+    view_name: &sqlparser::ast::ObjectName,
+    table_struct: &TableStruct,
+    _client_gen: &ClientGen,
+) -> proc_macro2::TokenStream {
+    let struct_name = &table_struct.name;
+    let client_type = ClientGen::generate_client_type();
+    let client: syn::Expr = syn::parse_quote!(client);
+    let statement: syn::Expr = syn::parse_quote!(&statement);
+
+    let sql = format!("REFRESH MATERIALIZED VIEW {}", quoted_name(view_name));
+    let prepare = ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#sql));
+    let query = ClientGen::generate_query_many_with_statement(&client, &statement, &[]);
+
+    quote::quote! {
+        impl #struct_name {
+            /// Rerun the materialized view's query, replacing the rows it holds.
+            pub async fn refresh(
+                client: &#client_type,
+            ) -> Result<(), <#client_type as rasql_traits::r#async::Client>::Error> {
+                let statement = #prepare?;
+                #query?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Generate the implementations of [Relation](rasql_traits::r#async::Relation) and
+/// [Table](rasql_traits::r#async::Table) for `table_struct`, which holds the rows of `table`.
+fn generate_base_table_impl<
+    Client: rasql_traits::r#async::Client,
+    ClientGen: AsyncClientCodeGenerator<Client>,
+>(
+    table: &crate::sql::Table,
+    table_struct: &TableStruct,
+    client_gen: &ClientGen,
+) -> proc_macro2::TokenStream {
+    let relation_impl = generate_relation_impl(&table.name, table_struct, client_gen);
+    let struct_name = &table_struct.name;
+    let client_type = ClientGen::generate_client_type();
+    let client: syn::Expr = syn::parse_quote!(client);
+    let statement: syn::Expr = syn::parse_quote!(insert_statement);
+
+    let (sql, inserted) = insert_sql(table, table_struct);
+    let prepare = ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#sql));
+    let parameters: Vec<syn::Expr> = inserted
+        .iter()
+        .map(|field| {
+            let name = &field.name;
+            syn::parse_quote!(&self.#name)
+        })
+        .collect();
+    let insert = ClientGen::generate_insert_with_statement(
+        &client,
+        &statement,
+        &parameters.iter().collect::<Vec<_>>(),
+    );
+
+    quote::quote! {
+        #relation_impl
+
+        impl rasql_traits::r#async::Table<#client_type> for #struct_name {
+            type InsertStatement = <#client_type as rasql_traits::r#async::Client>::PreparedStatement;
+
+            async fn prepare_insert(
+                client: &#client_type,
+            ) -> Result<
+                Self::InsertStatement,
+                <#client_type as rasql_traits::r#async::Client>::PrepareError,
+            > {
+                #prepare
+            }
+
+            async fn insert(
+                &self,
+                client: &#client_type,
+                insert_statement: &Self::InsertStatement,
+            ) -> Result<
+                <#client_type as rasql_traits::r#async::Client>::InsertOutcome,
+                <#client_type as rasql_traits::r#async::Client>::InsertError,
+            > {
+                #insert
+            }
+        }
+    }
+}
+
+/// Generate the implementation of [TableWithPK](rasql_traits::r#async::TableWithPK) for
+/// `table_struct`, which holds the rows of `table`, along with the struct borrowing a row's
+/// primary key. Returns `None` if `table` has no primary key.
+fn generate_table_with_pk_impl<
+    Client: rasql_traits::r#async::Client,
+    ClientGen: AsyncClientCodeGenerator<Client>,
+>(
+    table: &crate::sql::Table,
+    table_struct: &TableStruct,
+    _client_gen: &ClientGen,
+) -> Option<proc_macro2::TokenStream> {
+    let primary_key = key_fields(table, table_struct, table.primary_key()?);
+    let struct_name = &table_struct.name;
+    let key_name = quote::format_ident!("{}PrimaryKeyRef", struct_name);
+    let client_type = ClientGen::generate_client_type();
+    let client: syn::Expr = syn::parse_quote!(client);
+    let row: syn::Expr = syn::parse_quote!((&row));
+
+    let key_ref = generate_key_ref(struct_name, &key_name, &primary_key);
+    let key_field_names: Vec<_> = primary_key.iter().map(|field| &field.name).collect();
+    let key_parameters: Vec<syn::Expr> = key_field_names
+        .iter()
+        .map(|name| syn::parse_quote!(pk.#name))
+        .collect();
+    let key_parameters: Vec<_> = key_parameters.iter().collect();
+
+    // A table whose columns other than the primary key's can't be updated still gets an
+    // update, which leaves the row as it is
+    let updated = updated_fields(table, table_struct, &primary_key);
+    let assignments = if updated.is_empty() {
+        primary_key
+            .iter()
+            .map(|field| format!("{0} = {0}", quoted_column_name(field)))
+            .collect::<Vec<_>>()
+    } else {
+        updated
+            .iter()
+            .enumerate()
+            .map(|(index, field)| format!("{} = ${}", quoted_column_name(field), index + 1))
+            .collect()
+    };
+    let update_sql = format!(
+        "UPDATE {} SET {} WHERE {}",
+        quoted_name(&table.name),
+        assignments.join(", "),
+        key_condition(&primary_key, updated.len() + 1)
+    );
+    let prepare_update =
+        ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#update_sql));
+    let update_parameters: Vec<syn::Expr> = updated
+        .iter()
+        .chain(&primary_key)
+        .map(|field| {
+            let name = &field.name;
+            syn::parse_quote!(&self.#name)
+        })
+        .collect();
+    let update = ClientGen::generate_update_with_statement(
+        &client,
+        &syn::parse_quote!(update_statement),
+        &update_parameters.iter().collect::<Vec<_>>(),
+    );
+
+    let select_sql = format!(
+        "{} WHERE {}",
+        select_sql(&table.name, table_struct),
+        key_condition(&primary_key, 1)
+    );
+    let prepare_select =
+        ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#select_sql));
+    let select = ClientGen::generate_query_one_or_none_with_statement(
+        &client,
+        &syn::parse_quote!(select_by_pk_statement),
+        &key_parameters,
+    );
+    let read_row =
+        generate_read_row::<Client, ClientGen>(&syn::parse_quote!(Self), table_struct, &row);
+
+    let delete_sql = format!(
+        "DELETE FROM {} WHERE {}",
+        quoted_name(&table.name),
+        key_condition(&primary_key, 1)
+    );
+    let prepare_delete =
+        ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#delete_sql));
+    let delete = ClientGen::generate_delete_with_statement(
+        &client,
+        &syn::parse_quote!(delete_by_pk_statement),
+        &key_parameters,
+    );
+
+    Some(quote::quote! {
+        #key_ref
+
+        impl rasql_traits::r#async::TableWithPK<#client_type> for #struct_name {
+            type PrimaryKeyRef<'a> = #key_name<'a>;
+
+            type UpdateStatement = <#client_type as rasql_traits::r#async::Client>::PreparedStatement;
+
+            async fn prepare_update(
+                client: &#client_type,
+            ) -> Result<
+                Self::UpdateStatement,
+                <#client_type as rasql_traits::r#async::Client>::PrepareError,
+            > {
+                #prepare_update
+            }
+
+            async fn update(
+                &self,
+                client: &#client_type,
+                update_statement: &Self::UpdateStatement,
+            ) -> Result<
+                <#client_type as rasql_traits::r#async::Client>::UpdateOutcome,
+                <#client_type as rasql_traits::r#async::Client>::UpdateError,
+            > {
+                #update
+            }
+
+            type SelectByPKStatement =
+                <#client_type as rasql_traits::r#async::Client>::PreparedStatement;
+
+            async fn prepare_select_by_pk(
+                client: &#client_type,
+            ) -> Result<
+                Self::SelectByPKStatement,
+                <#client_type as rasql_traits::r#async::Client>::PrepareError,
+            > {
+                #prepare_select
+            }
+
+            async fn select_by_pk(
+                client: &#client_type,
+                select_by_pk_statement: &Self::SelectByPKStatement,
+                pk: Self::PrimaryKeyRef<'_>,
+            ) -> Result<Option<Self>, <#client_type as rasql_traits::r#async::Client>::QueryError> {
+                #select?.map(|row| Ok(#read_row)).transpose()
+            }
+
+            type DeleteByPKStatememt =
+                <#client_type as rasql_traits::r#async::Client>::PreparedStatement;
+
+            async fn prepare_delete_by_pk(
+                client: &#client_type,
+            ) -> Result<
+                Self::DeleteByPKStatememt,
+                <#client_type as rasql_traits::r#async::Client>::PrepareError,
+            > {
+                #prepare_delete
+            }
+
+            async fn delete_by_pk(
+                client: &#client_type,
+                delete_by_pk_statement: &Self::DeleteByPKStatememt,
+                pk: Self::PrimaryKeyRef<'_>,
+            ) -> Result<
+                <#client_type as rasql_traits::r#async::Client>::DeleteOutcome,
+                <#client_type as rasql_traits::r#async::Client>::DeleteError,
+            > {
+                #delete
+            }
+        }
+    })
+}
+
+/// The struct `key_name` borrowing the values of `fields` from a `struct_name`, which are the
+/// columns of one of its table's keys.
+fn generate_key_ref(
+    struct_name: &syn::Ident,
+    key_name: &syn::Ident,
+    fields: &[&TableStructField],
+) -> proc_macro2::TokenStream {
+    let field_names: Vec<_> = fields.iter().map(|field| &field.name).collect();
+    let field_types = fields.iter().map(|field| &field.r#type);
+    quote::quote! {
+        #[derive(Debug, Clone, Copy)]
+        pub struct #key_name<'a> {
+            #(pub #field_names: &'a #field_types,)*
+        }
+
+        impl<'a> From<&'a #struct_name> for #key_name<'a> {
+            fn from(row: &'a #struct_name) -> Self {
+                Self {
+                    #(#field_names: &row.#field_names,)*
+                }
+            }
+        }
+    }
+}
+
+/// The columns of each of `table`'s unique constraints and the unique indexes on it, along
+/// with the predicate of those that are partial indexes. Keys with the same columns as the
+/// primary key or an earlier key are left out, as are indexes on expressions.
+fn unique_keys<'a>(
+    catalog: &'a crate::sql::Catalog,
+    table: &'a crate::sql::Table,
+) -> Vec<(Vec<sqlparser::ast::Ident>, Option<&'a sqlparser::ast::Expr>)> {
+    let constraints = table
+        .constraints
+        .iter()
+        .filter_map(|constraint| match constraint {
+            sqlparser::ast::TableConstraint::Unique { columns, .. } => {
+                Some((columns.clone(), None))
+            }
+            _ => None,
+        });
+    let indexes = catalog
+        .indexes()
+        .filter(|index| index.unique && index.table == table.name)
+        .filter_map(|index| {
+            let columns = index
+                .columns
+                .iter()
+                .map(|column| match column {
+                    sqlparser::ast::Expr::Identifier(column) => {
+                        Some(crate::sql::normalise_ident(column))
+                    }
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some((columns, index.predicate.as_ref()))
+        });
+
+    let mut keys: Vec<(Vec<_>, _)> = vec![];
+    for (columns, predicate) in constraints.chain(indexes) {
+        let mut sorted = columns.clone();
+        sorted.sort_by(|a, b| a.value.cmp(&b.value));
+        let same_columns = |other: &[sqlparser::ast::Ident]| {
+            let mut other = other.to_vec();
+            other.sort_by(|a, b| a.value.cmp(&b.value));
+            other == sorted
+        };
+        if !table.primary_key().is_some_and(same_columns)
+            && !keys.iter().any(|(key, _)| same_columns(key))
+        {
+            keys.push((columns, predicate));
+        }
+    }
+    keys
+}
+
+/// Generate the implementation of [TableWithUnique](rasql_traits::r#async::TableWithUnique) for
+/// `table_struct`, which holds the rows of `table`, for the unique key of `columns`, along with
+/// the struct borrowing a row's key. If the key comes from a partial index, `predicate` is its
+/// `WHERE` clause, which rows must also satisfy to be found.
+fn generate_table_with_unique_impl<
+    Client: rasql_traits::r#async::Client,
+    ClientGen: AsyncClientCodeGenerator<Client>,
+>(
+    table: &crate::sql::Table,
+    table_struct: &TableStruct,
+    columns: &[sqlparser::ast::Ident],
+    predicate: Option<&sqlparser::ast::Expr>,
+    _client_gen: &ClientGen,
+) -> proc_macro2::TokenStream {
+    let key = key_fields(table, table_struct, columns);
+    let struct_name = &table_struct.name;
+    let key_name = quote::format_ident!(
+        "{}{}KeyRef",
+        struct_name,
+        columns
+            .iter()
+            .map(|column| sql_ident_to_type_name(column).to_string())
+            .collect::<String>()
+    );
+    let key_ref = generate_key_ref(struct_name, &key_name, &key);
+    let client_type = ClientGen::generate_client_type();
+    let client: syn::Expr = syn::parse_quote!(client);
+    let row: syn::Expr = syn::parse_quote!((&row));
+
+    let key_parameters: Vec<syn::Expr> = key
+        .iter()
+        .map(|field| {
+            let name = &field.name;
+            syn::parse_quote!(key.#name)
+        })
+        .collect();
+    let key_parameters: Vec<_> = key_parameters.iter().collect();
+    let condition = match predicate {
+        Some(predicate) => format!("{} AND ({predicate})", key_condition(&key, 1)),
+        None => key_condition(&key, 1),
+    };
+
+    let select_sql = format!(
+        "{} WHERE {condition}",
+        select_sql(&table.name, table_struct)
+    );
+    let prepare_select =
+        ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#select_sql));
+    let select = ClientGen::generate_query_one_or_none_with_statement(
+        &client,
+        &syn::parse_quote!(select_by_unique_statement),
+        &key_parameters,
+    );
+    let read_row =
+        generate_read_row::<Client, ClientGen>(&syn::parse_quote!(Self), table_struct, &row);
+
+    let delete_sql = format!("DELETE FROM {} WHERE {condition}", quoted_name(&table.name));
+    let prepare_delete =
+        ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#delete_sql));
+    let delete = ClientGen::generate_delete_with_statement(
+        &client,
+        &syn::parse_quote!(delete_by_unique_statement),
+        &key_parameters,
+    );
+
+    quote::quote! {
+        #key_ref
+
+        impl<'k> rasql_traits::r#async::TableWithUnique<#client_type, #key_name<'k>> for #struct_name {
+            type SelectByUniqueStatement =
+                <#client_type as rasql_traits::r#async::Client>::PreparedStatement;
+
+            async fn prepare_select_by_unique(
+                client: &#client_type,
+            ) -> Result<
+                Self::SelectByUniqueStatement,
+                <#client_type as rasql_traits::r#async::Client>::PrepareError,
+            > {
+                #prepare_select
+            }
+
+            async fn select_by_unique(
+                client: &#client_type,
+                select_by_unique_statement: &Self::SelectByUniqueStatement,
+                key: #key_name<'k>,
+            ) -> Result<Option<Self>, <#client_type as rasql_traits::r#async::Client>::QueryError> {
+                #select?.map(|row| Ok(#read_row)).transpose()
+            }
+
+            type DeleteByUniqueStatement =
+                <#client_type as rasql_traits::r#async::Client>::PreparedStatement;
+
+            async fn prepare_delete_by_unique(
+                client: &#client_type,
+            ) -> Result<
+                Self::DeleteByUniqueStatement,
+                <#client_type as rasql_traits::r#async::Client>::PrepareError,
+            > {
+                #prepare_delete
+            }
+
+            async fn delete_by_unique(
+                client: &#client_type,
+                delete_by_unique_statement: &Self::DeleteByUniqueStatement,
+                key: #key_name<'k>,
+            ) -> Result<
+                <#client_type as rasql_traits::r#async::Client>::DeleteOutcome,
+                <#client_type as rasql_traits::r#async::Client>::DeleteError,
+            > {
+                #delete
+            }
+        }
+    }
+}
+
+/// Generate the implementation of [Upsert](rasql_traits::r#async::Upsert) for `table_struct`,
+/// which holds the rows of `table`. Updates happen on conflicts with the primary key, or if
+/// there isn't one, the first unique key, while conflicts with any key are ignored. Returns
+/// `None` if `table` has neither.
+fn generate_upsert_impl<
+    Client: rasql_traits::r#async::Client,
+    ClientGen: AsyncClientCodeGenerator<Client>,
+>(
+    catalog: &crate::sql::Catalog,
+    table: &crate::sql::Table,
+    table_struct: &TableStruct,
+    _client_gen: &ClientGen,
+) -> Option<proc_macro2::TokenStream> {
+    let (key, predicate) = match table.primary_key() {
+        Some(primary_key) => (primary_key.to_vec(), None),
+        None => unique_keys(catalog, table).into_iter().next()?,
+    };
+    let key = key_fields(table, table_struct, &key);
+    let struct_name = &table_struct.name;
+    let client_type = ClientGen::generate_client_type();
+    let client: syn::Expr = syn::parse_quote!(client);
+
+    let (insert_sql, inserted) = insert_sql(table, table_struct);
+    let conflict_target = format!(
+        "({}){}",
+        key.iter()
+            .map(|field| quoted_column_name(field))
+            .collect::<Vec<_>>()
+            .join(", "),
+        match predicate {
+            Some(predicate) => format!(" WHERE {predicate}"),
+            None => String::new(),
+        }
+    );
+    // As with updates by primary key, a table whose columns other than the key's can't be
+    // updated still gets an update, which leaves the row as it is. Unqualified columns would
+    // be ambiguous with `EXCLUDED`'s.
+    let updated = updated_fields(table, table_struct, &key);
+    let assignments = if updated.is_empty() {
+        key.iter()
+            .map(|field| {
+                let column = quoted_column_name(field);
+                format!("{column} = {}.{column}", quoted_name(&table.name))
+            })
+            .collect::<Vec<_>>()
+    } else {
+        updated
+            .iter()
+            .map(|field| format!("{0} = EXCLUDED.{0}", quoted_column_name(field)))
+            .collect()
+    };
+    let update_sql = format!(
+        "{insert_sql} ON CONFLICT {conflict_target} DO UPDATE SET {}",
+        assignments.join(", ")
+    );
+    // Without a target, conflicts with any of the table's keys are ignored rather than failing
+    let ignore_sql = format!("{insert_sql} ON CONFLICT DO NOTHING");
+
+    let prepare_update =
+        ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#update_sql));
+    let prepare_ignore =
+        ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#ignore_sql));
+    let parameters: Vec<syn::Expr> = inserted
+        .iter()
+        .map(|field| {
+            let name = &field.name;
+            syn::parse_quote!(&self.#name)
+        })
+        .collect();
+    let parameters: Vec<_> = parameters.iter().collect();
+    let update = ClientGen::generate_insert_with_statement(
+        &client,
+        &syn::parse_quote!(insert_or_update_statement),
+        &parameters,
+    );
+    let ignore = ClientGen::generate_insert_with_statement(
+        &client,
+        &syn::parse_quote!(insert_or_ignore_statement),
+        &parameters,
+    );
+
+    Some(quote::quote! {
+        impl rasql_traits::r#async::Upsert<#client_type> for #struct_name {
+            type InsertOrUpdateStatement =
+                <#client_type as rasql_traits::r#async::Client>::PreparedStatement;
+
+            async fn prepare_insert_or_update(
+                client: &#client_type,
+            ) -> Result<
+                Self::InsertOrUpdateStatement,
+                <#client_type as rasql_traits::r#async::Client>::PrepareError,
+            > {
+                #prepare_update
+            }
+
+            async fn insert_or_update(
+                &self,
+                client: &#client_type,
+                insert_or_update_statement: &Self::InsertOrUpdateStatement,
+            ) -> Result<
+                <#client_type as rasql_traits::r#async::Client>::InsertOutcome,
+                <#client_type as rasql_traits::r#async::Client>::InsertError,
+            > {
+                #update
+            }
+
+            type InsertOrIgnoreStatement =
+                <#client_type as rasql_traits::r#async::Client>::PreparedStatement;
+
+            async fn prepare_insert_or_ignore(
+                client: &#client_type,
+            ) -> Result<
+                Self::InsertOrIgnoreStatement,
+                <#client_type as rasql_traits::r#async::Client>::PrepareError,
+            > {
+                #prepare_ignore
+            }
+
+            async fn insert_or_ignore(
+                &self,
+                client: &#client_type,
+                insert_or_ignore_statement: &Self::InsertOrIgnoreStatement,
+            ) -> Result<
+                <#client_type as rasql_traits::r#async::Client>::InsertOutcome,
+                <#client_type as rasql_traits::r#async::Client>::InsertError,
+            > {
+                #ignore
+            }
+        }
+    })
+}
+
+/// Generate methods on `table_struct`, which holds the rows of `table`, for following foreign
+/// keys: `fetch_parent_<columns>` fetches the row a foreign key of `table` references, and
+/// `fetch_<table>` fetches the rows of another table whose foreign key references a row of
+/// `table`, with `_by_<columns>` added when that table has several such foreign keys and the
+/// table's schema added after `fetch_` when a table of the same name in another schema has one
+/// on the same columns. A name that's still taken by another of the methods gets the longer
+/// forms, then a number. Each has a `prepare_` method preparing the statement it takes.
+fn generate_foreign_key_impl<
+    Traits: rasql_traits::DbTraits,
+    TypeGen: TypeGenerator<Traits>,
+    Client: rasql_traits::r#async::Client<Traits = Traits>,
+    ClientGen: AsyncClientCodeGenerator<Client>,
+>(
+    catalog: &crate::sql::Catalog,
+    table: &crate::sql::Table,
+    table_struct: &TableStruct,
+    config: &CodeGenConfig,
+    type_gen: &TypeGen,
+    _client_gen: &ClientGen,
+) -> Result<proc_macro2::TokenStream, UnsupportedDataType> {
+    let struct_name = &table_struct.name;
+    let client_type = ClientGen::generate_client_type();
+    let client: syn::Expr = syn::parse_quote!(client);
+    let row: syn::Expr = syn::parse_quote!((&row));
+    let columns_name = |columns: &[sqlparser::ast::Ident]| {
+        columns
+            .iter()
+            .map(|column| sql_ident_to_field_name(column).unraw().to_string())
+            .collect::<Vec<_>>()
+            .join("_")
+    };
+
+    // A method on this table's struct selecting the rows of `other` whose `other_columns` are
+    // equal to `columns` of this row
+    let generate_fetch = |method_name: syn::Ident,
+                          columns: &[sqlparser::ast::Ident],
+                          other: &crate::sql::Table,
+                          other_columns: &[sqlparser::ast::Ident],
+                          many: bool|
+     -> Result<proc_macro2::TokenStream, UnsupportedDataType> {
+        let (other_struct, _) = generate_table_struct(catalog, other, config, type_gen)?;
+        let other_module = sql_ident_to_module_name(&other.name.0[0]);
+        let other_name = &other_struct.name;
+        let other_path: syn::Path = syn::parse_quote!(super::#other_module::#other_name);
+        let prepare_name = quote::format_ident!("prepare_{}", method_name);
+
+        let sql = format!(
+            "{} WHERE {}",
+            select_sql(&other.name, &other_struct),
+            key_condition(&key_fields(other, &other_struct, other_columns), 1)
+        );
+        let prepare = ClientGen::generate_prepare_statement(&client, &syn::parse_quote!(#sql));
+        let parameters: Vec<syn::Expr> = key_fields(table, table_struct, columns)
+            .iter()
+            .map(|field| {
+                let name = &field.name;
+                syn::parse_quote!(&self.#name)
+            })
+            .collect();
+        let parameters: Vec<_> = parameters.iter().collect();
+        let statement: syn::Expr = syn::parse_quote!(statement);
+        let read_row = generate_read_row::<Client, ClientGen>(&other_path, &other_struct, &row);
+        let (output, fetch) = if many {
+            let query =
+                ClientGen::generate_query_many_with_statement(&client, &statement, &parameters);
+            (
+                quote::quote!(Container),
+                quote::quote!(#query?.into_iter().map(|row| Ok(#read_row)).collect()),
+            )
+        } else {
+            let query = ClientGen::generate_query_one_or_none_with_statement(
+                &client,
+                &statement,
+                &parameters,
+            );
+            (
+                quote::quote!(Option<#other_path>),
+                quote::quote!(#query?.map(|row| Ok(#read_row)).transpose()),
+            )
+        };
+        let generics = many.then(|| quote::quote!(<Container: FromIterator<#other_path>>));
+
+        Ok(quote::quote! {
+            pub async fn #prepare_name(
+                client: &#client_type,
+            ) -> Result<
+                <#client_type as rasql_traits::r#async::Client>::PreparedStatement,
+                <#client_type as rasql_traits::r#async::Client>::PrepareError,
+            > {
+                #prepare
+            }
+
+            pub async fn #method_name #generics(
+                &self,
+                client: &#client_type,
+                statement: &<#client_type as rasql_traits::r#async::Client>::PreparedStatement,
+            ) -> Result<#output, <#client_type as rasql_traits::r#async::Client>::QueryError> {
+                #fetch
+            }
+        })
+    };
+
+    // Methods are named after tables and columns, so names can still collide, e.g. the parent
+    // of the column `a` and a child table called `parent_a`. The first of `candidates` that's
+    // free is used, numbering the last one if none are.
+    let mut method_names = HashSet::new();
+    let mut method_name = |candidates: Vec<syn::Ident>| {
+        let base_name = candidates.last().unwrap().clone();
+        if let Some(name) = candidates
+            .into_iter()
+            .find(|name| method_names.insert(name.to_string()))
+        {
+            return name;
+        }
+        let mut number = 2;
+        loop {
+            let name = quote::format_ident!("{base_name}{number}");
+            if method_names.insert(name.to_string()) {
+                return name;
+            }
+            number += 1;
+        }
+    };
+
+    let mut methods = vec![];
+    for constraint in &table.constraints {
+        let sqlparser::ast::TableConstraint::ForeignKey {
+            columns,
+            foreign_table,
+            referred_columns,
+            ..
+        } = constraint
+        else {
+            continue;
+        };
+        let Some(parent) = catalog.table(foreign_table) else {
+            continue;
+        };
+        let method_name = method_name(vec![quote::format_ident!(
+            "fetch_parent_{}",
+            columns_name(columns)
+        )]);
+        methods.push(generate_fetch(
+            method_name,
+            columns,
+            parent,
+            referred_columns,
+            false,
+        )?);
+    }
+
+    let children: Vec<_> = catalog
+        .schemas()
+        .flat_map(|schema| schema.tables.values())
+        .flat_map(|child| {
+            child
+                .constraints
+                .iter()
+                .filter_map(move |constraint| match constraint {
+                    sqlparser::ast::TableConstraint::ForeignKey {
+                        columns,
+                        foreign_table,
+                        referred_columns,
+                        ..
+                    } if catalog.table(foreign_table).map(|parent| &parent.name)
+                        == Some(&table.name) =>
+                    {
+                        Some((child, columns, referred_columns))
+                    }
+                    _ => None,
+                })
+        })
+        .collect();
+    for &(child, columns, referred_columns) in &children {
+        let child_name = sql_ident_to_field_name(&child.name.0[1]);
+        let same_name: Vec<_> = children
+            .iter()
+            .filter(|(other, _, _)| other.name.0[1] == child.name.0[1])
+            .collect();
+        let mut candidates = vec![];
+        if same_name.len() == 1 {
+            candidates.push(quote::format_ident!("fetch_{}", child_name));
+        }
+        if !same_name.iter().any(|(other, other_columns, _)| {
+            other.name.0[0] != child.name.0[0] && other_columns == &columns
+        }) {
+            candidates.push(quote::format_ident!(
+                "fetch_{}_by_{}",
+                child_name,
+                columns_name(columns)
+            ));
+        }
+        candidates.push(quote::format_ident!(
+            "fetch_{}_{}_by_{}",
+            sql_ident_to_module_name(&child.name.0[0]),
+            child_name,
+            columns_name(columns)
+        ));
+        let method_name = method_name(candidates);
+        methods.push(generate_fetch(
+            method_name,
+            referred_columns,
+            child,
+            columns,
+            true,
+        )?);
+    }
+
+    Ok(if methods.is_empty() {
+        quote::quote!()
+    } else {
+        quote::quote! {
+            impl #struct_name {
+                #(#methods)*
+            }
+        }
+    })
+}
+
+#[derive(Default)]
 pub struct CodeGenConfig {
     pub module_configs: HashMap<syn::Ident, ModuleCodeGenConfig>,
+    /// Generate an async function for calling each function the schema creates, in the module
+    /// of the function's schema.
+    pub function_wrappers: bool,
 }
 
+#[derive(Default)]
 pub struct ModuleCodeGenConfig {
     pub use_statements: Vec<syn::ItemUse>,
     pub struct_configs: HashMap<syn::Ident, StructCodeGenConfig>,
@@ -140,16 +1965,24 @@ pub struct StructCodeGenConfig {
 
 #[derive(Default)]
 pub struct StructFieldCodeGenConfig {
-    rename: Option<syn::Ident>,
-    override_type: Option<syn::Type>,
-    attrs: Vec<syn::Attribute>,
-    id_promote_mode: IdPromoteMode,
+    pub rename: Option<syn::Ident>,
+    pub override_type: Option<syn::Type>,
+    pub attrs: Vec<syn::Attribute>,
+    pub id_promote_mode: IdPromoteMode,
 }
 
+/// Whether a column's values get their own type, e.g. `UsersId` for the `id` column of
+/// `users`, which is also used for every column referencing it through a foreign key. This
+/// stops the IDs of different tables being mixed up. The ID types are `Copy` when the column's
+/// Rust type is, and only columns whose Rust type is `Eq` and `Hash` can be promoted.
 #[derive(Clone, Copy, Default)]
 pub enum IdPromoteMode {
     #[default]
     None,
+    /// Generate a [TrustedId](rasql_traits::TrustedId), for values known to identify a row,
+    /// along with an [UntrustedId](rasql_traits::UntrustedId), e.g. `UntrustedUsersId`, for
+    /// those that aren't.
     TrustedId,
+    /// Generate an [Id](rasql_traits::Id).
     Id,
 }
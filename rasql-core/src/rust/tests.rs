@@ -0,0 +1,421 @@
+//! Code generated for small schemas, checked through the names and SQL it contains.
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+
+use super::{
+    generate_catalog, sql_ident_to_field_name, sql_ident_to_type_name,
+    type_gen::{
+        TokioPostgresGenerator, UnsupportedDataType, UseDateTime, UseRustDecimal, UseSerdeJson,
+        UseUuid,
+    },
+    CodeGenConfig, IdPromoteMode, ModuleCodeGenConfig, StructCodeGenConfig,
+    StructFieldCodeGenConfig,
+};
+use crate::sql::{parse_schema_sql, parse_sql_schema};
+
+/// Generate the code for `schema` with `config`.
+fn try_generate(schema: &str, config: &CodeGenConfig) -> Result<TokenStream, UnsupportedDataType> {
+    let catalog = parse_sql_schema(parse_schema_sql(schema).unwrap()).unwrap();
+    let generator = TokioPostgresGenerator {
+        use_rust_decimal: UseRustDecimal::DontUse,
+        use_uuid: UseUuid::DontUse,
+        use_date_time: UseDateTime::DontUse,
+        use_serde_json: UseSerdeJson::DontUse,
+        generated_module_path: syn::parse_quote!(crate),
+    };
+    generate_catalog::<_, tokio_postgres::Client, _>(&catalog, config, &generator)
+}
+
+/// Generate the code for `schema` with the default config, which has to parse as Rust.
+fn generate(schema: &str) -> syn::File {
+    let config = CodeGenConfig {
+        module_configs: Default::default(),
+        function_wrappers: false,
+    };
+    let code = try_generate(schema, &config).unwrap_or_else(|error| panic!("{error}"));
+    syn::parse2(code.clone()).unwrap_or_else(|error| panic!("{error}: {code}"))
+}
+
+/// The items of every schema's module.
+fn items(code: &syn::File) -> impl Iterator<Item = &syn::Item> {
+    code.items.iter().flat_map(|item| match item {
+        syn::Item::Mod(module) => &module.content.as_ref().unwrap().1[..],
+        item => std::slice::from_ref(item),
+    })
+}
+
+/// The fields of the struct `name`, each a name and type.
+fn fields(code: &syn::File, name: &str) -> Vec<(String, String)> {
+    let item = items(code)
+        .find_map(|item| match item {
+            syn::Item::Struct(item) if item.ident == name => Some(item),
+            _ => None,
+        })
+        .unwrap_or_else(|| panic!("no struct `{name}`"));
+    item.fields
+        .iter()
+        .map(|field| {
+            (
+                field.ident.as_ref().unwrap().to_string(),
+                field.ty.to_token_stream().to_string().replace(' ', ""),
+            )
+        })
+        .collect()
+}
+
+/// The `impl` blocks for the type `name` of the trait `r#trait`, or its inherent ones if it's
+/// `None`.
+fn impls<'c>(code: &'c syn::File, name: &str, r#trait: Option<&str>) -> Vec<&'c syn::ItemImpl> {
+    items(code)
+        .filter_map(|item| match item {
+            syn::Item::Impl(item)
+                if item.self_ty.to_token_stream().to_string() == name
+                    && item
+                        .trait_
+                        .as_ref()
+                        .map(|(_, path, _)| path.segments.last().unwrap().ident.to_string())
+                        == r#trait.map(str::to_owned) =>
+            {
+                Some(item)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The names of the inherent methods of `name`, leaving out the `prepare_` methods preparing
+/// their statements.
+fn methods(code: &syn::File, name: &str) -> Vec<String> {
+    impls(code, name, None)
+        .into_iter()
+        .flat_map(|item| &item.items)
+        .filter_map(|item| match item {
+            syn::ImplItem::Fn(method) => Some(method.sig.ident.to_string()),
+            _ => None,
+        })
+        .filter(|method| !method.starts_with("prepare_"))
+        .collect()
+}
+
+/// The SQL statements the implementations of `r#trait` for `name` prepare, or its inherent
+/// methods if it's `None`.
+fn statements(code: &syn::File, name: &str, r#trait: Option<&str>) -> Vec<String> {
+    fn string_literals(tokens: TokenStream, literals: &mut Vec<String>) {
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => string_literals(group.stream(), literals),
+                TokenTree::Literal(literal) => {
+                    if let Ok(literal) = syn::parse_str::<syn::LitStr>(&literal.to_string()) {
+                        literals.push(literal.value());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut literals = vec![];
+    for item in impls(code, name, r#trait) {
+        string_literals(item.to_token_stream(), &mut literals);
+    }
+    literals.retain(|literal| {
+        ["SELECT ", "INSERT ", "UPDATE ", "DELETE "]
+            .iter()
+            .any(|keyword| literal.starts_with(keyword))
+    });
+    literals
+}
+
+#[test]
+fn identifiers() {
+    let cases = [
+        ("user_id", "user_id", "UserId"),
+        ("userId", "user_id", "UserId"),
+        ("type", "r#type", "Type"),
+        ("self", "self_", "Self_"),
+        ("2fa", "_2_fa", "_2Fa"),
+        ("?column?", "column", "Column"),
+        ("count(*)", "count", "Count"),
+        ("first name", "first_name", "FirstName"),
+        ("?", "__", "__"),
+        ("", "__", "__"),
+    ];
+    for (sql, field_name, type_name) in cases {
+        let ident = sqlparser::ast::Ident::new(sql);
+        assert_eq!(
+            sql_ident_to_field_name(&ident).to_string(),
+            field_name,
+            "{sql}"
+        );
+        assert_eq!(
+            sql_ident_to_type_name(&ident).to_string(),
+            type_name,
+            "{sql}"
+        );
+    }
+}
+
+#[test]
+fn views_with_unaliased_columns() {
+    let code = generate(
+        "CREATE TABLE t (id int PRIMARY KEY);
+        CREATE VIEW v AS SELECT id + 1, id FROM t;",
+    );
+    assert_eq!(
+        fields(&code, "V"),
+        [("column".into(), "i32".into()), ("id".into(), "i32".into())]
+    );
+    assert_eq!(
+        statements(&code, "V", Some("Relation")),
+        [r#"SELECT "?column?", "id" FROM "public"."v""#]
+    );
+}
+
+#[test]
+fn unique_keys() {
+    let code = generate(
+        "CREATE TABLE t (
+            id int PRIMARY KEY,
+            a int NOT NULL,
+            b text,
+            c int,
+            d int,
+            UNIQUE (c, d),
+            UNIQUE (id)
+        );
+        CREATE UNIQUE INDEX t_a ON t (a);
+        CREATE UNIQUE INDEX t_b ON t (lower(b));
+        CREATE UNIQUE INDEX t_c ON t (c) WHERE c > 0;
+        ALTER TABLE t RENAME COLUMN a TO renamed;",
+    );
+    let select = r#"SELECT "id", "renamed", "b", "c", "d" FROM "public"."t""#;
+    assert_eq!(
+        statements(&code, "T", Some("TableWithUnique")),
+        [
+            format!(r#"{select} WHERE "c" = $1 AND "d" = $2"#),
+            r#"DELETE FROM "public"."t" WHERE "c" = $1 AND "d" = $2"#.into(),
+            format!(r#"{select} WHERE "renamed" = $1"#),
+            r#"DELETE FROM "public"."t" WHERE "renamed" = $1"#.into(),
+            format!(r#"{select} WHERE "c" = $1 AND (c > 0)"#),
+            r#"DELETE FROM "public"."t" WHERE "c" = $1 AND (c > 0)"#.into(),
+        ]
+    );
+    assert_eq!(
+        fields(&code, "TCDKeyRef"),
+        [
+            ("c".into(), "&'aOption<i32>".into()),
+            ("d".into(), "&'aOption<i32>".into())
+        ]
+    );
+    assert_eq!(
+        fields(&code, "TRenamedKeyRef"),
+        [("renamed".into(), "&'ai32".into())]
+    );
+    assert_eq!(
+        fields(&code, "TCKeyRef"),
+        [("c".into(), "&'aOption<i32>".into())]
+    );
+}
+
+#[test]
+fn foreign_keys() {
+    let code = generate(
+        "CREATE TABLE users (id int PRIMARY KEY);
+        CREATE TABLE posts (
+            id int PRIMARY KEY,
+            author int NOT NULL REFERENCES users (id),
+            editor int REFERENCES users (id)
+        );
+        CREATE TABLE comments (id int PRIMARY KEY, post int NOT NULL REFERENCES posts (id));",
+    );
+    assert_eq!(
+        methods(&code, "Users"),
+        ["fetch_posts_by_author", "fetch_posts_by_editor"]
+    );
+    assert_eq!(
+        methods(&code, "Posts"),
+        [
+            "fetch_parent_author",
+            "fetch_parent_editor",
+            "fetch_comments"
+        ]
+    );
+    assert_eq!(
+        statements(&code, "Posts", None),
+        [
+            r#"SELECT "id" FROM "public"."users" WHERE "id" = $1"#,
+            r#"SELECT "id" FROM "public"."users" WHERE "id" = $1"#,
+            r#"SELECT "id", "post" FROM "public"."comments" WHERE "post" = $1"#,
+        ]
+    );
+    assert_eq!(methods(&code, "Comments"), ["fetch_parent_post"]);
+}
+
+#[test]
+fn foreign_key_method_names_collide() {
+    let code = generate(
+        "CREATE SCHEMA other;
+        CREATE TABLE things (id int PRIMARY KEY, a int);
+        CREATE TABLE parent_a (id int PRIMARY KEY, thing int REFERENCES things (id));
+        ALTER TABLE things ADD FOREIGN KEY (a) REFERENCES parent_a (id);
+        CREATE TABLE items (id int PRIMARY KEY, thing int REFERENCES things (id));
+        CREATE TABLE other.items (id int PRIMARY KEY, thing int REFERENCES things (id), a int);
+        ALTER TABLE other.items ADD FOREIGN KEY (a) REFERENCES things (id);",
+    );
+    assert_eq!(
+        methods(&code, "Things"),
+        [
+            "fetch_parent_a",
+            "fetch_parent_a_by_thing",
+            "fetch_public_items_by_thing",
+            "fetch_other_items_by_thing",
+            "fetch_items_by_a",
+        ]
+    );
+}
+
+#[test]
+fn tables() {
+    let code = generate(
+        r#"CREATE TABLE "Events" (id int8 PRIMARY KEY, type text NOT NULL, "startsAt" int4);"#,
+    );
+    assert_eq!(
+        fields(&code, "Events"),
+        [
+            ("id".into(), "i64".into()),
+            ("r#type".into(), "String".into()),
+            ("starts_at".into(), "Option<i32>".into()),
+        ]
+    );
+    assert_eq!(
+        statements(&code, "Events", Some("Relation")),
+        [r#"SELECT "id", "type", "startsAt" FROM "public"."Events""#]
+    );
+    assert_eq!(
+        statements(&code, "Events", Some("Table")),
+        [r#"INSERT INTO "public"."Events" ("id", "type", "startsAt") VALUES ($1, $2, $3)"#]
+    );
+}
+
+#[test]
+fn primary_keys() {
+    let code = generate(
+        "CREATE TABLE memberships (
+            org int,
+            member int,
+            role text NOT NULL,
+            PRIMARY KEY (member, org)
+        );
+        CREATE TABLE tags (post int, tag text, PRIMARY KEY (post, tag));
+        CREATE TABLE log (message text);",
+    );
+    let select = r#"SELECT "org", "member", "role" FROM "public"."memberships""#;
+    assert_eq!(
+        statements(&code, "Memberships", Some("TableWithPK")),
+        [
+            r#"UPDATE "public"."memberships" SET "role" = $1 WHERE "member" = $2 AND "org" = $3"#
+                .into(),
+            format!(r#"{select} WHERE "member" = $1 AND "org" = $2"#),
+            r#"DELETE FROM "public"."memberships" WHERE "member" = $1 AND "org" = $2"#.into(),
+        ]
+    );
+    assert_eq!(
+        fields(&code, "MembershipsPrimaryKeyRef"),
+        [
+            ("member".into(), "&'ai32".into()),
+            ("org".into(), "&'ai32".into())
+        ]
+    );
+    // Updating a row that's all key still has to set something
+    assert_eq!(
+        statements(&code, "Tags", Some("TableWithPK"))[0],
+        r#"UPDATE "public"."tags" SET "post" = "post", "tag" = "tag" WHERE "post" = $1 AND "tag" = $2"#
+    );
+    assert!(impls(&code, "Log", Some("TableWithPK")).is_empty());
+}
+
+#[test]
+fn upserts() {
+    let code = generate(
+        "CREATE TABLE a (id int PRIMARY KEY, b int UNIQUE, c text);
+        CREATE TABLE u (x int NOT NULL, y text, UNIQUE (x));
+        CREATE TABLE p (x int, y text);
+        CREATE UNIQUE INDEX p_x ON p (x) WHERE x > 0;
+        CREATE TABLE n (x int);",
+    );
+    // Updates on the primary key if there is one, otherwise the first unique key
+    assert_eq!(
+        statements(&code, "A", Some("Upsert")),
+        [
+            r#"INSERT INTO "public"."a" ("id", "b", "c") VALUES ($1, $2, $3) ON CONFLICT ("id") DO UPDATE SET "b" = EXCLUDED."b", "c" = EXCLUDED."c""#,
+            r#"INSERT INTO "public"."a" ("id", "b", "c") VALUES ($1, $2, $3) ON CONFLICT DO NOTHING"#,
+        ]
+    );
+    assert_eq!(
+        statements(&code, "U", Some("Upsert")),
+        [
+            r#"INSERT INTO "public"."u" ("x", "y") VALUES ($1, $2) ON CONFLICT ("x") DO UPDATE SET "y" = EXCLUDED."y""#,
+            r#"INSERT INTO "public"."u" ("x", "y") VALUES ($1, $2) ON CONFLICT DO NOTHING"#,
+        ]
+    );
+    // A partial index is only inferred as the arbiter with its predicate
+    assert_eq!(
+        statements(&code, "P", Some("Upsert"))[0],
+        r#"INSERT INTO "public"."p" ("x", "y") VALUES ($1, $2) ON CONFLICT ("x") WHERE x > 0 DO UPDATE SET "y" = EXCLUDED."y""#
+    );
+    assert!(impls(&code, "N", Some("Upsert")).is_empty());
+}
+
+/// Generate the code for `schema` with the column `column` of `public.t` promoted to an ID.
+fn generate_with_id(schema: &str, column: &str) -> Result<TokenStream, UnsupportedDataType> {
+    let field_config = StructFieldCodeGenConfig {
+        id_promote_mode: IdPromoteMode::Id,
+        ..Default::default()
+    };
+    let struct_config = StructCodeGenConfig {
+        field_configs: [(sqlparser::ast::Ident::new(column), field_config)].into(),
+        deny_extra_fields: false,
+    };
+    let module_config = ModuleCodeGenConfig {
+        struct_configs: [(quote::format_ident!("T"), struct_config)].into(),
+        ..Default::default()
+    };
+    let config = CodeGenConfig {
+        module_configs: [(quote::format_ident!("public"), module_config)].into(),
+        function_wrappers: false,
+    };
+    try_generate(schema, &config)
+}
+
+#[test]
+fn ids() {
+    let code = generate_with_id("CREATE TABLE t (id int8 PRIMARY KEY, name text);", "id").unwrap();
+    let code: syn::File = syn::parse2(code).unwrap();
+    assert_eq!(fields(&code, "T")[0], ("id".into(), "TId".into()));
+    let id = items(&code)
+        .find_map(|item| match item {
+            syn::Item::Struct(item) if item.ident == "TId" => Some(item),
+            _ => None,
+        })
+        .unwrap();
+    let derives = id.attrs[0].to_token_stream().to_string();
+    for derive in ["Copy", "Eq", "Hash"] {
+        assert!(derives.contains(derive), "{derives}");
+    }
+
+    for r#type in ["real", "double precision", "float8[]"] {
+        let error = generate_with_id(
+            &format!("CREATE TABLE t (id {type} PRIMARY KEY, name text);"),
+            "id",
+        )
+        .unwrap_err();
+        assert!(error.as_id, "{type}: {error}");
+    }
+    let error = generate_with_id("CREATE TABLE t (id real PRIMARY KEY);", "id").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Type generator does not support the following SQL datatype as an ID: REAL \
+        (of column `public.t.id`)"
+    );
+}
@@ -1,8 +1,12 @@
 use thiserror::Error;
 
-use crate::rust::TableStructField;
+#[cfg(feature = "tokio-postgres")]
+use crate::{
+    rust::{sql_ident_to_module_name, sql_ident_to_type_name, EnumTypeVariant, TableStructField},
+    sql::types::canonical_type,
+};
 
-use super::TableStruct;
+use super::{DomainType, EnumType, IdType, TableStruct};
 
 pub trait TypeGenerator<Traits: rasql_traits::DbTraits> {
     fn sql_datatype_to_rust_type(
@@ -10,17 +14,101 @@ pub trait TypeGenerator<Traits: rasql_traits::DbTraits> {
         datatype: &sqlparser::ast::DataType,
     ) -> Result<syn::Type, UnsupportedDataType>;
 
+    /// The type that query parameters of type `datatype` are borrowed as. This defaults to the
+    /// same type as [TypeGenerator::sql_datatype_to_rust_type], but can be unsized so that
+    /// borrowed values are accepted, e.g. `str` rather than `String`.
+    fn sql_datatype_to_rust_param_type(
+        &self,
+        datatype: &sqlparser::ast::DataType,
+    ) -> Result<syn::Type, UnsupportedDataType> {
+        self.sql_datatype_to_rust_type(datatype)
+    }
+
+    /// Whether the Rust type of `datatype` is `Copy`. This defaults to `false`, which is always
+    /// safe, it just means types wrapping it can't be `Copy` either.
+    fn sql_datatype_is_copy(&self, datatype: &sqlparser::ast::DataType) -> bool {
+        let _ = datatype;
+        false
+    }
+
+    /// Whether the Rust type of `datatype` is `Eq` and `Hash`, which ID types have to be. This
+    /// defaults to `true`, leaving any type that isn't to fail to compile once it's promoted.
+    fn sql_datatype_is_eq_and_hash(&self, datatype: &sqlparser::ast::DataType) -> bool {
+        let _ = datatype;
+        true
+    }
+
     fn generate_table_struct(&self, table_struct: &TableStruct) -> proc_macro2::TokenStream;
+
+    fn generate_enum(&self, enum_type: &EnumType) -> proc_macro2::TokenStream;
+
+    /// Generate the newtype for a domain, which has to convert to and from the database the
+    /// same way as its base type.
+    fn generate_domain(&self, domain_type: &DomainType) -> proc_macro2::TokenStream;
+
+    /// Generate the newtype for an ID, and its untrusted counterpart if it has one, which have
+    /// to convert to and from the database the same way as the underlying type. The trait
+    /// implementations are generated separately.
+    fn generate_id(&self, id_type: &IdType) -> proc_macro2::TokenStream;
 }
 
 #[derive(Debug, Error)]
-#[error("Type generator does not support the following SQL datatype: {0}")]
-pub struct UnsupportedDataType(pub sqlparser::ast::DataType);
+#[error(
+    "Type generator does not support the following SQL datatype{}: {data_type}{}",
+    if *as_id { " as an ID" } else { "" },
+    column.as_ref().map(|column| format!(" (of column `{column}`)")).unwrap_or_default()
+)]
+pub struct UnsupportedDataType {
+    pub data_type: sqlparser::ast::DataType,
+    /// The column that has the type, `[schema, relation, column]`, if it's known
+    pub column: Option<sqlparser::ast::ObjectName>,
+    /// Whether the type is only unsupported as the underlying type of an ID
+    pub as_id: bool,
+}
+
+impl UnsupportedDataType {
+    pub fn new(data_type: sqlparser::ast::DataType) -> Self {
+        Self {
+            data_type,
+            column: None,
+            as_id: false,
+        }
+    }
+
+    /// Record that the type is only unsupported as the underlying type of an ID.
+    pub fn as_id(self) -> Self {
+        Self {
+            as_id: true,
+            ..self
+        }
+    }
+
+    /// Record that the type is that of `column` of the table, view or composite type
+    /// `relation`.
+    pub fn in_column(
+        self,
+        relation: &sqlparser::ast::ObjectName,
+        column: &sqlparser::ast::Ident,
+    ) -> Self {
+        let mut name = relation.clone();
+        name.0.push(column.clone());
+        Self {
+            column: Some(name),
+            ..self
+        }
+    }
+}
 
 #[cfg(feature = "tokio-postgres")]
 pub struct TokioPostgresGenerator {
     pub use_rust_decimal: UseRustDecimal,
     pub use_uuid: UseUuid,
+    pub use_date_time: UseDateTime,
+    pub use_serde_json: UseSerdeJson,
+    /// The path the code [generate_catalog](super::generate_catalog) generates can be found at,
+    /// which the types it generates for the schema's custom types are referred to through.
+    /// From inside the generated modules themselves this is `super`.
+    pub generated_module_path: syn::Path,
 }
 
 #[cfg(feature = "tokio-postgres")]
@@ -36,6 +124,67 @@ pub enum UseUuid {
     Version1,
 }
 
+/// Which crate's types `DATE`, `TIME` and `TIMESTAMP` columns are mapped to. Postgres'
+/// `INTERVAL` and `TIMETZ` aren't supported by tokio-postgres with either.
+#[cfg(feature = "tokio-postgres")]
+pub enum UseDateTime {
+    DontUse,
+    Chrono0_4,
+    Time0_3,
+}
+
+#[cfg(feature = "tokio-postgres")]
+pub enum UseSerdeJson {
+    DontUse,
+    Version1,
+}
+
+/// Implement `name` and `from_name` for each of the generator's options from the names of their
+/// variants.
+#[cfg(feature = "tokio-postgres")]
+macro_rules! option_names {
+    ($($option:ident { $($variant:ident => $name:literal,)* })*) => {$(
+        impl $option {
+            /// The name used for this option when passing it between crates in an environment
+            /// variable, see [super::build_env].
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($option::$variant => $name,)*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($name => Some($option::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    )*};
+}
+
+#[cfg(feature = "tokio-postgres")]
+option_names! {
+    UseRustDecimal {
+        DontUse => "none",
+        Version1 => "1",
+    }
+    UseUuid {
+        DontUse => "none",
+        Version0_8 => "0.8",
+        Version1 => "1",
+    }
+    UseDateTime {
+        DontUse => "none",
+        Chrono0_4 => "chrono-0.4",
+        Time0_3 => "time-0.3",
+    }
+    UseSerdeJson {
+        DontUse => "none",
+        Version1 => "1",
+    }
+}
+
 #[cfg(feature = "tokio-postgres")]
 impl TokioPostgresGenerator {
     pub(super) fn generate_execute(
@@ -53,7 +202,9 @@ impl TypeGenerator<rasql_traits::PostgresTypesTraits> for TokioPostgresGenerator
         &self,
         datatype: &sqlparser::ast::DataType,
     ) -> Result<syn::Type, UnsupportedDataType> {
-        Ok(match datatype {
+        // Postgres spells many types several ways, e.g. `timestamptz` and `TIMESTAMP WITH TIME
+        // ZONE`, or `serial` for `INT4` with a default
+        Ok(match canonical_type(datatype) {
             sqlparser::ast::DataType::Character(..)
             | sqlparser::ast::DataType::Char(..)
             | sqlparser::ast::DataType::CharacterVarying(..)
@@ -90,27 +241,13 @@ impl TypeGenerator<rasql_traits::PostgresTypesTraits> for TokioPostgresGenerator
             }
             sqlparser::ast::DataType::Int2(_) => syn::Type::Verbatim(quote::quote! {i16}),
             sqlparser::ast::DataType::UnsignedInt2(_) => syn::Type::Verbatim(quote::quote! {u16}),
-            sqlparser::ast::DataType::Int16 => todo!(),
-            sqlparser::ast::DataType::Int128 => todo!(),
-            sqlparser::ast::DataType::Int256 => todo!(),
             sqlparser::ast::DataType::Int(_)
             | sqlparser::ast::DataType::Int32
             | sqlparser::ast::DataType::Int4(_)
             | sqlparser::ast::DataType::Integer(_) => syn::Type::Verbatim(quote::quote! {i32}),
-            sqlparser::ast::DataType::UnsignedInt(_) => todo!(),
-            sqlparser::ast::DataType::UnsignedInt4(_) => todo!(),
-            sqlparser::ast::DataType::UnsignedInteger(_) => todo!(),
-            sqlparser::ast::DataType::UInt8 => todo!(),
-            sqlparser::ast::DataType::UInt16 => todo!(),
-            sqlparser::ast::DataType::UInt32 => todo!(),
-            sqlparser::ast::DataType::UInt64 => todo!(),
-            sqlparser::ast::DataType::UInt128 => todo!(),
-            sqlparser::ast::DataType::UInt256 => todo!(),
             sqlparser::ast::DataType::Int8(_)
             | sqlparser::ast::DataType::Int64
             | sqlparser::ast::DataType::BigInt(_) => syn::Type::Verbatim(quote::quote! {i64}),
-            sqlparser::ast::DataType::UnsignedBigInt(_) => todo!(),
-            sqlparser::ast::DataType::UnsignedInt8(_) => todo!(),
             sqlparser::ast::DataType::Float(_)
             | sqlparser::ast::DataType::Float4
             | sqlparser::ast::DataType::Real
@@ -119,46 +256,144 @@ impl TypeGenerator<rasql_traits::PostgresTypesTraits> for TokioPostgresGenerator
             | sqlparser::ast::DataType::Float8
             | sqlparser::ast::DataType::Double(..)
             | sqlparser::ast::DataType::DoublePrecision => syn::Type::Verbatim(quote::quote! {f64}),
-            sqlparser::ast::DataType::Bool => todo!(),
-            sqlparser::ast::DataType::Boolean => todo!(),
-            sqlparser::ast::DataType::Date => todo!(),
-            sqlparser::ast::DataType::Date32 => todo!(),
-            sqlparser::ast::DataType::Time(_, timezone_info) => todo!(),
-            sqlparser::ast::DataType::Datetime(_) => todo!(),
-            sqlparser::ast::DataType::Datetime64(_, _) => todo!(),
-            sqlparser::ast::DataType::Timestamp(_, timezone_info) => todo!(),
-            sqlparser::ast::DataType::Interval => todo!(),
-            sqlparser::ast::DataType::JSON => todo!(),
-            sqlparser::ast::DataType::JSONB => todo!(),
-            sqlparser::ast::DataType::Regclass => todo!(),
-            sqlparser::ast::DataType::Bit(_) => todo!(),
-            sqlparser::ast::DataType::BitVarying(_) => todo!(),
-            sqlparser::ast::DataType::Custom(object_name, vec) => todo!(),
+            sqlparser::ast::DataType::Bool | sqlparser::ast::DataType::Boolean => {
+                syn::Type::Verbatim(quote::quote! {bool})
+            }
+            sqlparser::ast::DataType::Timestamp(_, timezone) => {
+                let with_time_zone = timezone == sqlparser::ast::TimezoneInfo::WithTimeZone;
+                match (&self.use_date_time, with_time_zone) {
+                    (UseDateTime::Chrono0_4, false) => {
+                        syn::Type::Verbatim(quote::quote! {chrono::NaiveDateTime})
+                    }
+                    (UseDateTime::Chrono0_4, true) => {
+                        syn::Type::Verbatim(quote::quote! {chrono::DateTime<chrono::Utc>})
+                    }
+                    (UseDateTime::Time0_3, false) => {
+                        syn::Type::Verbatim(quote::quote! {time::PrimitiveDateTime})
+                    }
+                    (UseDateTime::Time0_3, true) => {
+                        syn::Type::Verbatim(quote::quote! {time::OffsetDateTime})
+                    }
+                    (UseDateTime::DontUse, _) => {
+                        return Err(UnsupportedDataType::new(datatype.clone()))
+                    }
+                }
+            }
+            sqlparser::ast::DataType::Date => match self.use_date_time {
+                UseDateTime::Chrono0_4 => syn::Type::Verbatim(quote::quote! {chrono::NaiveDate}),
+                UseDateTime::Time0_3 => syn::Type::Verbatim(quote::quote! {time::Date}),
+                UseDateTime::DontUse => return Err(UnsupportedDataType::new(datatype.clone())),
+            },
+            // tokio-postgres doesn't support `timetz`
+            sqlparser::ast::DataType::Time(_, sqlparser::ast::TimezoneInfo::None) => {
+                match self.use_date_time {
+                    UseDateTime::Chrono0_4 => {
+                        syn::Type::Verbatim(quote::quote! {chrono::NaiveTime})
+                    }
+                    UseDateTime::Time0_3 => syn::Type::Verbatim(quote::quote! {time::Time}),
+                    UseDateTime::DontUse => return Err(UnsupportedDataType::new(datatype.clone())),
+                }
+            }
+            sqlparser::ast::DataType::JSON | sqlparser::ast::DataType::JSONB
+                if matches!(self.use_serde_json, UseSerdeJson::Version1) =>
+            {
+                syn::Type::Verbatim(quote::quote! {serde_json::Value})
+            }
+            sqlparser::ast::DataType::Custom(sqlparser::ast::ObjectName(name), modifiers) => {
+                // Types the schema creates, which are qualified by Catalog::qualified_type
+                let ([schema, name], []) = (name.as_slice(), modifiers.as_slice()) else {
+                    return Err(UnsupportedDataType::new(datatype.clone()));
+                };
+                let path = &self.generated_module_path;
+                let module_name = sql_ident_to_module_name(schema);
+                let type_name = sql_ident_to_type_name(name);
+                syn::Type::Verbatim(quote::quote! {#path::#module_name::#type_name})
+            }
             sqlparser::ast::DataType::Array(array_elem_type_def) => match array_elem_type_def {
                 sqlparser::ast::ArrayElemTypeDef::None => {
-                    return Err(UnsupportedDataType(datatype.clone()))
+                    return Err(UnsupportedDataType::new(datatype.clone()))
                 }
                 sqlparser::ast::ArrayElemTypeDef::AngleBracket(data_type)
                 | sqlparser::ast::ArrayElemTypeDef::SquareBracket(data_type, _)
                 | sqlparser::ast::ArrayElemTypeDef::Parenthesis(data_type) => {
-                    let inner_type = self.sql_datatype_to_rust_type(&datatype)?;
+                    let inner_type = self.sql_datatype_to_rust_type(&data_type)?;
                     syn::Type::Verbatim(quote::quote! {Vec<#inner_type>})
                 }
             },
-            sqlparser::ast::DataType::Map(data_type, data_type1) => todo!(),
-            sqlparser::ast::DataType::Tuple(vec) => todo!(),
-            sqlparser::ast::DataType::Nested(vec) => todo!(),
-            sqlparser::ast::DataType::Enum(vec, _) => todo!(),
-            sqlparser::ast::DataType::Set(vec) => todo!(),
-            sqlparser::ast::DataType::Struct(vec, struct_bracket_kind) => todo!(),
-            sqlparser::ast::DataType::Union(vec) => todo!(),
-            sqlparser::ast::DataType::Nullable(data_type) => todo!(),
-            sqlparser::ast::DataType::LowCardinality(data_type) => todo!(),
-            sqlparser::ast::DataType::Trigger => todo!(),
-            _ => return Err(UnsupportedDataType(datatype.clone())),
+            _ => return Err(UnsupportedDataType::new(datatype.clone())),
         })
     }
 
+    fn sql_datatype_to_rust_param_type(
+        &self,
+        datatype: &sqlparser::ast::DataType,
+    ) -> Result<syn::Type, UnsupportedDataType> {
+        Ok(match datatype {
+            sqlparser::ast::DataType::Character(..)
+            | sqlparser::ast::DataType::Char(..)
+            | sqlparser::ast::DataType::CharacterVarying(..)
+            | sqlparser::ast::DataType::CharVarying(..)
+            | sqlparser::ast::DataType::Varchar(..)
+            | sqlparser::ast::DataType::Nvarchar(..)
+            | sqlparser::ast::DataType::Text
+            | sqlparser::ast::DataType::TinyText
+            | sqlparser::ast::DataType::MediumText
+            | sqlparser::ast::DataType::LongText
+            | sqlparser::ast::DataType::String(_)
+            | sqlparser::ast::DataType::FixedString(_) => syn::Type::Verbatim(quote::quote! {str}),
+            sqlparser::ast::DataType::Varbinary(_)
+            | sqlparser::ast::DataType::Blob(_)
+            | sqlparser::ast::DataType::TinyBlob
+            | sqlparser::ast::DataType::MediumBlob
+            | sqlparser::ast::DataType::LongBlob
+            | sqlparser::ast::DataType::Bytes(_)
+            | sqlparser::ast::DataType::Bytea
+            | sqlparser::ast::DataType::Binary(_) => syn::Type::Verbatim(quote::quote! {[u8]}),
+            sqlparser::ast::DataType::Array(
+                sqlparser::ast::ArrayElemTypeDef::AngleBracket(data_type)
+                | sqlparser::ast::ArrayElemTypeDef::SquareBracket(data_type, _)
+                | sqlparser::ast::ArrayElemTypeDef::Parenthesis(data_type),
+            ) => {
+                let inner_type = self.sql_datatype_to_rust_type(data_type)?;
+                syn::Type::Verbatim(quote::quote! {[#inner_type]})
+            }
+            datatype => self.sql_datatype_to_rust_type(datatype)?,
+        })
+    }
+
+    fn sql_datatype_is_copy(&self, datatype: &sqlparser::ast::DataType) -> bool {
+        // Everything that maps to a type that owns its value, and types the schema creates,
+        // which aren't all Copy, are left out
+        self.sql_datatype_to_rust_type(datatype).is_ok()
+            && !matches!(
+                canonical_type(datatype),
+                sqlparser::ast::DataType::Text
+                    | sqlparser::ast::DataType::Bytea
+                    | sqlparser::ast::DataType::JSON
+                    | sqlparser::ast::DataType::JSONB
+                    | sqlparser::ast::DataType::Custom(..)
+                    | sqlparser::ast::DataType::Array(_)
+            )
+    }
+
+    fn sql_datatype_is_eq_and_hash(&self, datatype: &sqlparser::ast::DataType) -> bool {
+        // Types the schema creates are left out as only enums are `Eq` and `Hash`
+        self.sql_datatype_to_rust_type(datatype).is_ok()
+            && match canonical_type(datatype) {
+                sqlparser::ast::DataType::Float4
+                | sqlparser::ast::DataType::Float8
+                | sqlparser::ast::DataType::JSON
+                | sqlparser::ast::DataType::JSONB
+                | sqlparser::ast::DataType::Custom(..) => false,
+                sqlparser::ast::DataType::Array(
+                    sqlparser::ast::ArrayElemTypeDef::AngleBracket(data_type)
+                    | sqlparser::ast::ArrayElemTypeDef::SquareBracket(data_type, _)
+                    | sqlparser::ast::ArrayElemTypeDef::Parenthesis(data_type),
+                ) => self.sql_datatype_is_eq_and_hash(&data_type),
+                _ => true,
+            }
+    }
+
     fn generate_table_struct(&self, table_struct: &TableStruct) -> proc_macro2::TokenStream {
         let TableStruct {
             name,
@@ -174,19 +409,156 @@ impl TypeGenerator<rasql_traits::PostgresTypesTraits> for TokioPostgresGenerator
                  name,
                  r#type,
                  db_alias,
+                 ..
              }| {
                 let db_alias = db_alias
                     .as_deref()
                     .map(|db_alias| quote::quote!(#[postgres(name = #db_alias)]));
-                quote::quote!(#db_alias #name : #r#type)
+                quote::quote!(#db_alias pub #name : #r#type)
             },
         );
         quote::quote!(
-            #[derive(ToSql, FromSql)]
+            #[derive(Debug, Clone, postgres_types::ToSql, postgres_types::FromSql)]
             #db_alias
-            struct #name {
+            pub struct #name {
                 #(#fields,)*
             }
         )
     }
+
+    fn generate_enum(&self, enum_type: &EnumType) -> proc_macro2::TokenStream {
+        let EnumType {
+            name,
+            variants,
+            db_alias,
+        } = enum_type;
+        let db_alias = db_alias
+            .as_deref()
+            .map(|db_alias| quote::quote!(#[postgres(name = #db_alias)]));
+
+        let variants = variants.iter().map(|EnumTypeVariant { name, db_alias }| {
+            let db_alias = db_alias
+                .as_deref()
+                .map(|db_alias| quote::quote!(#[postgres(name = #db_alias)]));
+            quote::quote!(#db_alias #name)
+        });
+        quote::quote!(
+            #[derive(
+                Debug,
+                Clone,
+                Copy,
+                PartialEq,
+                Eq,
+                Hash,
+                postgres_types::ToSql,
+                postgres_types::FromSql,
+            )]
+            #db_alias
+            pub enum #name {
+                #(#variants,)*
+            }
+        )
+    }
+
+    fn generate_domain(&self, domain_type: &DomainType) -> proc_macro2::TokenStream {
+        let DomainType {
+            name,
+            base_type,
+            db_name,
+        } = domain_type;
+        // Postgres describes values of a domain as the domain itself when they're parameters
+        // but as the base type when they're columns, so both are accepted. postgres-types'
+        // own domain support only accepts the domain.
+        quote::quote!(
+            #[derive(Debug, Clone, PartialEq)]
+            #[repr(transparent)]
+            pub struct #name(#base_type);
+
+            impl<'a> postgres_types::FromSql<'a> for #name {
+                fn from_sql(
+                    ty: &postgres_types::Type,
+                    raw: &'a [u8],
+                ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+                    let ty = match ty.kind() {
+                        postgres_types::Kind::Domain(base) => base,
+                        _ => ty,
+                    };
+                    <#base_type as postgres_types::FromSql>::from_sql(ty, raw).map(Self)
+                }
+
+                fn accepts(ty: &postgres_types::Type) -> bool {
+                    match ty.kind() {
+                        postgres_types::Kind::Domain(base) => {
+                            ty.name() == #db_name
+                                && <#base_type as postgres_types::FromSql>::accepts(base)
+                        }
+                        _ => <#base_type as postgres_types::FromSql>::accepts(ty),
+                    }
+                }
+            }
+
+            impl postgres_types::ToSql for #name {
+                fn to_sql(
+                    &self,
+                    ty: &postgres_types::Type,
+                    out: &mut postgres_types::private::BytesMut,
+                ) -> Result<postgres_types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+                    let ty = match ty.kind() {
+                        postgres_types::Kind::Domain(base) => base,
+                        _ => ty,
+                    };
+                    postgres_types::ToSql::to_sql(&self.0, ty, out)
+                }
+
+                fn accepts(ty: &postgres_types::Type) -> bool {
+                    match ty.kind() {
+                        postgres_types::Kind::Domain(base) => {
+                            ty.name() == #db_name
+                                && <#base_type as postgres_types::ToSql>::accepts(base)
+                        }
+                        _ => <#base_type as postgres_types::ToSql>::accepts(ty),
+                    }
+                }
+
+                postgres_types::to_sql_checked!();
+            }
+        )
+    }
+
+    fn generate_id(&self, id_type: &IdType) -> proc_macro2::TokenStream {
+        let IdType {
+            name,
+            underlying,
+            untrusted_name,
+            copy,
+        } = id_type;
+        let copy = copy.then(|| quote::quote!(Copy,));
+        let derives = quote::quote!(
+            #[derive(
+                Debug,
+                Clone,
+                #copy
+                PartialEq,
+                Eq,
+                Hash,
+                postgres_types::ToSql,
+                postgres_types::FromSql,
+            )]
+            #[postgres(transparent)]
+        );
+        match untrusted_name {
+            None => quote::quote!(
+                #derives
+                pub struct #name(pub #underlying);
+            ),
+            // Only the generated code can vouch for a value being trusted, so its field is private
+            Some(untrusted_name) => quote::quote!(
+                #derives
+                pub struct #name(#underlying);
+
+                #derives
+                pub struct #untrusted_name(pub #underlying);
+            ),
+        }
+    }
 }
@@ -0,0 +1,559 @@
+use std::ops::ControlFlow;
+
+use sqlparser::ast::{
+    AlterColumnOperation, AlterTableOperation, ColumnDef, ColumnOption, ColumnOptionDef,
+    DropBehavior, Expr, Ident, ObjectName, Spanned, TableConstraint, Visit,
+};
+
+use super::{
+    constraint_columns, constraint_name, normalise_ident, take_column_constraints, ObjectKind,
+    SchemaAnalyser, SchemaError, Table,
+};
+
+impl SchemaAnalyser {
+    pub(super) fn alter_table(
+        &mut self,
+        name: ObjectName,
+        if_exists: bool,
+        operations: Vec<AlterTableOperation>,
+    ) -> Result<(), SchemaError> {
+        let Some(table) = self.resolve_table(&name, None)? else {
+            return if if_exists {
+                Ok(())
+            } else {
+                Err(SchemaError::UnknownTable {
+                    location: self.location(name.span()),
+                    name,
+                })
+            };
+        };
+        let mut table_name = table.name.clone();
+        for operation in operations {
+            if let Err(error) = self.alter_table_operation(&mut table_name, operation) {
+                self.errors.push(error);
+            }
+        }
+        self.catalog.sync_table_type(&table_name);
+        Ok(())
+    }
+
+    /// Apply a single `ALTER TABLE` operation to the table called `table_name`, updating
+    /// `table_name` if the table gets renamed.
+    fn alter_table_operation(
+        &mut self,
+        table_name: &mut ObjectName,
+        operation: AlterTableOperation,
+    ) -> Result<(), SchemaError> {
+        match operation {
+            AlterTableOperation::AddConstraint(constraint) => {
+                self.add_constraint(table_name, constraint, None)
+            }
+            AlterTableOperation::AddColumn {
+                if_not_exists,
+                column_def,
+                ..
+            } => {
+                let mut column = ColumnDef {
+                    name: normalise_ident(&column_def.name),
+                    data_type: self.resolve_type(&column_def.data_type)?,
+                    ..column_def
+                };
+                if self.table(table_name).column(&column.name).is_some() {
+                    return if if_not_exists {
+                        Ok(())
+                    } else {
+                        Err(SchemaError::DuplicateColumn {
+                            location: self.location(column.name.span),
+                            table: table_name.clone(),
+                            column: column.name,
+                        })
+                    };
+                }
+                let constraints = take_column_constraints(&mut column);
+                let column_name = column.name.clone();
+                self.table_mut(table_name).columns.push(column);
+                for constraint in constraints {
+                    self.add_constraint(table_name, constraint, Some(&column_name))?;
+                }
+                Ok(())
+            }
+            AlterTableOperation::DropColumn {
+                column_name,
+                if_exists,
+                drop_behavior,
+            } => self.drop_column(table_name, column_name, if_exists, drop_behavior),
+            AlterTableOperation::RenameColumn {
+                old_column_name,
+                new_column_name,
+            } => self.rename_column(table_name, old_column_name, new_column_name),
+            AlterTableOperation::RenameTable {
+                table_name: new_name,
+            } => self.rename_table(table_name, new_name),
+            AlterTableOperation::AlterColumn { column_name, op } => {
+                let op = match op {
+                    AlterColumnOperation::SetDataType { data_type, using } => {
+                        AlterColumnOperation::SetDataType {
+                            data_type: self.resolve_type(&data_type)?,
+                            using,
+                        }
+                    }
+                    op => op,
+                };
+                self.check_alter_column(table_name, &column_name, &op)?;
+                let column = self.column_mut(table_name, &column_name)?;
+                match op {
+                    AlterColumnOperation::SetNotNull => {
+                        column
+                            .options
+                            .retain(|option| !matches!(option.option, ColumnOption::Null));
+                        if !column
+                            .options
+                            .iter()
+                            .any(|option| matches!(option.option, ColumnOption::NotNull))
+                        {
+                            column.options.push(ColumnOptionDef {
+                                name: None,
+                                option: ColumnOption::NotNull,
+                            });
+                        }
+                    }
+                    AlterColumnOperation::DropNotNull => column
+                        .options
+                        .retain(|option| !matches!(option.option, ColumnOption::NotNull)),
+                    AlterColumnOperation::SetDefault { value } => {
+                        column
+                            .options
+                            .retain(|option| !matches!(option.option, ColumnOption::Default(_)));
+                        column.options.push(ColumnOptionDef {
+                            name: None,
+                            option: ColumnOption::Default(value),
+                        });
+                    }
+                    AlterColumnOperation::DropDefault => column
+                        .options
+                        .retain(|option| !matches!(option.option, ColumnOption::Default(_))),
+                    AlterColumnOperation::SetDataType { data_type, .. } => {
+                        column.data_type = data_type;
+                    }
+                    AlterColumnOperation::AddGenerated {
+                        generated_as,
+                        sequence_options,
+                    } => column.options.push(ColumnOptionDef {
+                        name: None,
+                        option: ColumnOption::Generated {
+                            generated_as: generated_as
+                                .unwrap_or(sqlparser::ast::GeneratedAs::Always),
+                            sequence_options,
+                            generation_expr: None,
+                            generation_expr_mode: None,
+                            generated_keyword: true,
+                        },
+                    }),
+                }
+                Ok(())
+            }
+            AlterTableOperation::DropConstraint {
+                if_exists,
+                name,
+                drop_behavior,
+            } => self.drop_constraint(table_name, name, if_exists, drop_behavior),
+            AlterTableOperation::DropPrimaryKey => {
+                let Some(name) =
+                    self.table(table_name).constraints.iter().find_map(
+                        |constraint| match constraint {
+                            TableConstraint::PrimaryKey { name, .. } => name.clone(),
+                            _ => None,
+                        },
+                    )
+                else {
+                    return Ok(());
+                };
+                self.drop_constraint(table_name, name, false, None)
+            }
+            AlterTableOperation::RenameConstraint { old_name, new_name } => {
+                let old_name = normalise_ident(&old_name);
+                let new_name = normalise_ident(&new_name);
+                if self.table(table_name).constraint(&new_name).is_some() {
+                    return Err(SchemaError::DuplicateObject {
+                        location: self.location(new_name.span),
+                        kind: ObjectKind::Constraint,
+                        name: ObjectName(vec![new_name]),
+                    });
+                }
+                let Some(name) = self
+                    .table_mut(table_name)
+                    .constraints
+                    .iter_mut()
+                    .map(super::constraint_name_mut)
+                    .find(|name| name.as_ref() == Some(&old_name))
+                else {
+                    return Err(SchemaError::UnknownConstraint {
+                        location: self.location(old_name.span),
+                        table: table_name.clone(),
+                        constraint: old_name,
+                    });
+                };
+                *name = Some(new_name);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Add a constraint to an existing table. `column` is the column the constraint was
+    /// declared on, if it was declared inline.
+    fn add_constraint(
+        &mut self,
+        table_name: &ObjectName,
+        constraint: TableConstraint,
+        column: Option<&Ident>,
+    ) -> Result<(), SchemaError> {
+        let table = self.table(table_name);
+        let mut constraint = self.resolve_constraint(table, None, constraint)?;
+        if let Some(name) = constraint_name(&constraint) {
+            if table.constraint(name).is_some() {
+                return Err(SchemaError::DuplicateObject {
+                    location: self.location(name.span),
+                    kind: ObjectKind::Constraint,
+                    name: ObjectName(vec![normalise_ident(name)]),
+                });
+            }
+        }
+        table.name_constraint(&mut constraint, column);
+        self.table_mut(table_name).constraints.push(constraint);
+        Ok(())
+    }
+
+    pub(super) fn drop_column(
+        &mut self,
+        table_name: &ObjectName,
+        column_name: Ident,
+        if_exists: bool,
+        drop_behavior: Option<DropBehavior>,
+    ) -> Result<(), SchemaError> {
+        let column_name = normalise_ident(&column_name);
+        if self.table(table_name).column(&column_name).is_none() {
+            return if if_exists {
+                Ok(())
+            } else {
+                Err(SchemaError::UnknownColumn {
+                    location: self.location(column_name.span),
+                    table: table_name.clone(),
+                    column: column_name,
+                })
+            };
+        }
+
+        let mut dependents = self.foreign_keys_referencing(table_name, |referred_columns| {
+            referred_columns.contains(&column_name)
+        });
+        // Views only record which relations they read from, so assume any view mentioning a
+        // column with this name is using it
+        dependents.extend(self.views_depending_on(table_name, |view| {
+            references_column(&view.query, &column_name)
+        }));
+        self.drop_dependents(
+            ObjectKind::Column,
+            ObjectName(table_name.0.iter().chain([&column_name]).cloned().collect()),
+            column_name.span,
+            dependents,
+            drop_behavior,
+        )?;
+
+        // Constraints on the table that involve the column go along with it
+        let table = self.table_mut(table_name);
+        table.columns.retain(|column| column.name != column_name);
+        table.constraints.retain(|constraint| match constraint {
+            TableConstraint::Check { expr, .. } => !references_column(expr, &column_name),
+            constraint => !constraint_columns(constraint).contains(&column_name),
+        });
+        self.catalog
+            .schema_entry(&table_name.0[0])
+            .indexes
+            .retain(|_, index| {
+                index.table != *table_name
+                    || !index
+                        .columns
+                        .iter()
+                        .chain(&index.predicate)
+                        .any(|expr| references_column(expr, &column_name))
+            });
+        Ok(())
+    }
+
+    fn rename_column(
+        &mut self,
+        table_name: &ObjectName,
+        old_column_name: Ident,
+        new_column_name: Ident,
+    ) -> Result<(), SchemaError> {
+        let old_column_name = normalise_ident(&old_column_name);
+        let new_column_name = normalise_ident(&new_column_name);
+        if self.table(table_name).column(&new_column_name).is_some() {
+            return Err(SchemaError::DuplicateColumn {
+                location: self.location(new_column_name.span),
+                table: table_name.clone(),
+                column: new_column_name,
+            });
+        }
+        self.column_mut(table_name, &old_column_name)?.name = new_column_name.clone();
+
+        let rename = |column: &mut Ident| {
+            if *column == old_column_name {
+                *column = new_column_name.clone();
+            }
+        };
+        for table in self.catalog.tables_mut() {
+            let is_renamed_table = table.name == *table_name;
+            for constraint in &mut table.constraints {
+                match constraint {
+                    TableConstraint::ForeignKey {
+                        columns,
+                        foreign_table,
+                        referred_columns,
+                        ..
+                    } => {
+                        if is_renamed_table {
+                            columns.iter_mut().for_each(rename);
+                        }
+                        if foreign_table == table_name {
+                            referred_columns.iter_mut().for_each(rename);
+                        }
+                    }
+                    TableConstraint::Unique { columns, .. }
+                    | TableConstraint::PrimaryKey { columns, .. }
+                    | TableConstraint::Index { columns, .. }
+                    | TableConstraint::FulltextOrSpatial { columns, .. }
+                        if is_renamed_table =>
+                    {
+                        columns.iter_mut().for_each(rename)
+                    }
+                    TableConstraint::Check { expr, .. } if is_renamed_table => {
+                        rename_column_in_expr(expr, &old_column_name, &new_column_name)
+                    }
+                    _ => (),
+                }
+            }
+        }
+        for index in self
+            .catalog
+            .schema_entry(&table_name.0[0])
+            .indexes
+            .values_mut()
+            .filter(|index| index.table == *table_name)
+        {
+            for expr in index.columns.iter_mut().chain(&mut index.predicate) {
+                rename_column_in_expr(expr, &old_column_name, &new_column_name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rename a table, updating `table_name` to its new fully qualified name.
+    fn rename_table(
+        &mut self,
+        table_name: &mut ObjectName,
+        new_name: ObjectName,
+    ) -> Result<(), SchemaError> {
+        let location = self.location(new_name.span());
+        // Renaming can't move a table to another schema, so only the last part matters
+        let new_name = normalise_ident(new_name.0.last().unwrap());
+        let old_name = table_name.0[1].clone();
+        let new_table_name = ObjectName(vec![table_name.0[0].clone(), new_name.clone()]);
+        let schema = self.catalog.schema_entry(&table_name.0[0]);
+        let existing = schema.relation_kind(&new_name).or(schema
+            .types
+            .contains_key(&new_name)
+            .then_some(ObjectKind::Type));
+        if let Some(kind) = existing {
+            return Err(SchemaError::DuplicateObject {
+                location,
+                kind,
+                name: new_table_name,
+            });
+        }
+
+        let (index, _, mut table) = schema.tables.shift_remove_full(&old_name).unwrap();
+        table.name = new_table_name.clone();
+        schema.tables.shift_insert(index, new_name.clone(), table);
+        let (index, _, r#type) = schema.types.shift_remove_full(&old_name).unwrap();
+        schema.types.shift_insert(index, new_name, r#type);
+        for index in schema.indexes.values_mut() {
+            if index.table == *table_name {
+                index.table = new_table_name.clone();
+            }
+        }
+
+        for table in self.catalog.tables_mut() {
+            for constraint in &mut table.constraints {
+                if let TableConstraint::ForeignKey { foreign_table, .. } = constraint {
+                    if foreign_table == table_name {
+                        *foreign_table = new_table_name.clone();
+                    }
+                }
+            }
+        }
+        for schema in self.catalog.schemas_mut() {
+            for view in schema.views.values_mut() {
+                for dependency in &mut view.dependencies {
+                    if dependency == table_name {
+                        *dependency = new_table_name.clone();
+                    }
+                }
+            }
+        }
+        *table_name = new_table_name;
+        Ok(())
+    }
+
+    fn drop_constraint(
+        &mut self,
+        table_name: &ObjectName,
+        name: Ident,
+        if_exists: bool,
+        drop_behavior: Option<DropBehavior>,
+    ) -> Result<(), SchemaError> {
+        let name = normalise_ident(&name);
+        let Some(constraint) = self.table(table_name).constraint(&name) else {
+            return if if_exists {
+                Ok(())
+            } else {
+                Err(SchemaError::UnknownConstraint {
+                    location: self.location(name.span),
+                    table: table_name.clone(),
+                    constraint: name,
+                })
+            };
+        };
+
+        // Foreign keys rely on the unique index backing the columns they refer to
+        if let TableConstraint::PrimaryKey { columns, .. }
+        | TableConstraint::Unique { columns, .. } = constraint
+        {
+            let columns = columns.clone();
+            let dependents = self.foreign_keys_referencing(table_name, |referred_columns| {
+                referred_columns.len() == columns.len()
+                    && referred_columns
+                        .iter()
+                        .all(|column| columns.contains(column))
+            });
+            self.drop_dependents(
+                ObjectKind::Constraint,
+                ObjectName(vec![name.clone()]),
+                name.span,
+                dependents,
+                drop_behavior,
+            )?;
+        }
+
+        self.table_mut(table_name)
+            .constraints
+            .retain(|constraint| constraint_name(constraint) != Some(&name));
+        Ok(())
+    }
+
+    /// Look up a table already known to exist.
+    fn table(&self, table_name: &ObjectName) -> &Table {
+        self.catalog.table(table_name).unwrap()
+    }
+
+    /// Look up a table already known to exist.
+    fn table_mut(&mut self, table_name: &ObjectName) -> &mut Table {
+        self.catalog.table_mut(table_name).unwrap()
+    }
+
+    /// Check `op` can be applied to the column `column_name`. Like Postgres, a column can't
+    /// change type while a view uses it, and primary key columns can't be made nullable.
+    fn check_alter_column(
+        &self,
+        table_name: &ObjectName,
+        column_name: &Ident,
+        op: &AlterColumnOperation,
+    ) -> Result<(), SchemaError> {
+        let column_name = normalise_ident(column_name);
+        match op {
+            AlterColumnOperation::SetDataType { .. } => {
+                // As when dropping a column, any view mentioning a column with this name is
+                // assumed to use it
+                let dependents = self.views_depending_on(table_name, |view| {
+                    references_column(&view.query, &column_name)
+                });
+                if dependents.is_empty() {
+                    return Ok(());
+                }
+                Err(SchemaError::ColumnTypeInUse {
+                    location: self.location(column_name.span),
+                    column: ObjectName(
+                        table_name.0.iter().chain([&column_name]).cloned().collect(),
+                    ),
+                    dependents: dependents
+                        .iter()
+                        .map(|dependent| dependent.to_string())
+                        .collect(),
+                })
+            }
+            AlterColumnOperation::DropNotNull
+                if self
+                    .table(table_name)
+                    .primary_key()
+                    .is_some_and(|primary_key| primary_key.contains(&column_name)) =>
+            {
+                Err(SchemaError::PrimaryKeyColumnNullable {
+                    location: self.location(column_name.span),
+                    table: table_name.clone(),
+                    column: column_name,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn column_mut(
+        &mut self,
+        table_name: &ObjectName,
+        column_name: &Ident,
+    ) -> Result<&mut ColumnDef, SchemaError> {
+        let column_name = normalise_ident(column_name);
+        let location = self.location(column_name.span);
+        self.table_mut(table_name)
+            .columns
+            .iter_mut()
+            .find(|column| column.name == column_name)
+            .ok_or_else(|| SchemaError::UnknownColumn {
+                location,
+                table: table_name.clone(),
+                column: column_name,
+            })
+    }
+}
+
+/// Whether `node` refers to the column `column` anywhere within it.
+fn references_column<V: Visit>(node: &V, column: &Ident) -> bool {
+    sqlparser::ast::visit_expressions(node, |expr| match expr {
+        Expr::Identifier(ident) if normalise_ident(ident) == *column => ControlFlow::Break(()),
+        Expr::CompoundIdentifier(idents)
+            if idents
+                .last()
+                .is_some_and(|ident| normalise_ident(ident) == *column) =>
+        {
+            ControlFlow::Break(())
+        }
+        _ => ControlFlow::Continue(()),
+    })
+    .is_break()
+}
+
+/// Rename every reference to the column `old_column_name` in `expr` to `new_column_name`.
+fn rename_column_in_expr(expr: &mut Expr, old_column_name: &Ident, new_column_name: &Ident) {
+    let _ = sqlparser::ast::visit_expressions_mut(expr, |expr| {
+        let ident = match expr {
+            Expr::Identifier(ident) => Some(ident),
+            Expr::CompoundIdentifier(idents) => idents.last_mut(),
+            _ => None,
+        };
+        if let Some(ident) = ident.filter(|ident| normalise_ident(ident) == *old_column_name) {
+            *ident = new_column_name.clone();
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
@@ -0,0 +1,140 @@
+//! `ALTER TYPE`, which sqlparser can't parse at all, so [parse_alter_type] parses the forms
+//! that change an enum's labels.
+
+use sqlparser::{
+    ast::{Ident, ObjectName, Spanned},
+    keywords::Keyword,
+    parser::{Parser, ParserError},
+};
+
+use super::{ObjectKind, SchemaAnalyser, SchemaError, Type};
+
+/// An `ALTER TYPE` statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlterType {
+    pub name: ObjectName,
+    pub operation: AlterTypeOperation,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AlterTypeOperation {
+    /// `ADD VALUE [ IF NOT EXISTS ] 'label' [ { BEFORE | AFTER } 'label' ]`
+    AddValue {
+        if_not_exists: bool,
+        label: Ident,
+        position: Option<LabelPosition>,
+    },
+    /// `RENAME VALUE 'label' TO 'label'`
+    RenameValue { from: Ident, to: Ident },
+}
+
+/// Where a label added to an enum goes, relative to an existing label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LabelPosition {
+    Before(Ident),
+    After(Ident),
+}
+
+/// Parse the rest of an `ALTER TYPE` statement, after `ALTER TYPE` itself.
+pub(super) fn parse_alter_type(parser: &mut Parser) -> Result<AlterType, ParserError> {
+    let name = parser.parse_object_name(false)?;
+    let operation = if parser.parse_keywords(&[Keyword::ADD, Keyword::VALUE]) {
+        let if_not_exists = parser.parse_keywords(&[Keyword::IF, Keyword::NOT, Keyword::EXISTS]);
+        let label = parser.parse_identifier()?;
+        let position = if parser.parse_keyword(Keyword::BEFORE) {
+            Some(LabelPosition::Before(parser.parse_identifier()?))
+        } else if parser.parse_keyword(Keyword::AFTER) {
+            Some(LabelPosition::After(parser.parse_identifier()?))
+        } else {
+            None
+        };
+        AlterTypeOperation::AddValue {
+            if_not_exists,
+            label,
+            position,
+        }
+    } else if parser.parse_keywords(&[Keyword::RENAME, Keyword::VALUE]) {
+        let from = parser.parse_identifier()?;
+        parser.expect_keyword_is(Keyword::TO)?;
+        let to = parser.parse_identifier()?;
+        AlterTypeOperation::RenameValue { from, to }
+    } else {
+        return parser.expected("ADD VALUE or RENAME VALUE", parser.peek_token());
+    };
+    Ok(AlterType { name, operation })
+}
+
+impl SchemaAnalyser {
+    pub(super) fn alter_type(
+        &mut self,
+        AlterType { name, operation }: AlterType,
+    ) -> Result<(), SchemaError> {
+        let location = self.location(name.span());
+        let Some(r#type) = self.catalog.type_mut(&name) else {
+            return Err(SchemaError::UnknownObject {
+                location,
+                kind: ObjectKind::Type,
+                name,
+            });
+        };
+        let Type::Enum {
+            name: enum_name,
+            variants,
+        } = r#type
+        else {
+            return Err(SchemaError::NotAnEnum {
+                location,
+                name: r#type.name().clone(),
+            });
+        };
+        let position = |label: &Ident| {
+            variants
+                .iter()
+                .position(|variant| variant.value == label.value)
+        };
+        let unknown_label = |label: Ident| SchemaError::UnknownEnumLabel {
+            location: location.clone(),
+            name: enum_name.clone(),
+            label,
+        };
+        let duplicate_label = |label: Ident| SchemaError::DuplicateEnumLabel {
+            location: location.clone(),
+            name: enum_name.clone(),
+            label,
+        };
+
+        match operation {
+            AlterTypeOperation::AddValue {
+                if_not_exists,
+                label,
+                position: label_position,
+            } => {
+                if position(&label).is_some() {
+                    return if if_not_exists {
+                        Ok(())
+                    } else {
+                        Err(duplicate_label(label))
+                    };
+                }
+                let index = match label_position {
+                    None => variants.len(),
+                    Some(LabelPosition::Before(existing)) => {
+                        position(&existing).ok_or_else(|| unknown_label(existing))?
+                    }
+                    Some(LabelPosition::After(existing)) => {
+                        position(&existing).ok_or_else(|| unknown_label(existing))? + 1
+                    }
+                };
+                variants.insert(index, label);
+            }
+            AlterTypeOperation::RenameValue { from, to } => {
+                let index = position(&from).ok_or_else(|| unknown_label(from))?;
+                if position(&to).is_some() {
+                    return Err(duplicate_label(to));
+                }
+                variants[index] = to;
+            }
+        }
+        Ok(())
+    }
+}
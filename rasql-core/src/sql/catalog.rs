@@ -0,0 +1,265 @@
+use indexmap::IndexMap;
+use sqlparser::ast::{ArrayElemTypeDef, ColumnDef, DataType, Ident, ObjectName};
+
+use super::{
+    normalise_ident, types::canonical_type, Function, FunctionCatalog, Index, Schema, Table, Type,
+    View,
+};
+
+/// Every [Schema] produced by analysing a set of SQL statements.
+///
+/// Object names are looked up the way Postgres would look them up: qualified names only
+/// look in the named schema, while unqualified names try each schema in the search path
+/// in turn.
+pub struct Catalog {
+    schemas: IndexMap<Ident, Schema>,
+    search_path: Vec<Ident>,
+    functions: FunctionCatalog,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Catalog {
+    /// Create a catalog holding just the `public` schema, which is also the only schema in
+    /// the search path, along with every built-in function.
+    pub fn new() -> Self {
+        let public = Ident::new("public");
+        let mut schemas = IndexMap::new();
+        schemas.insert(public.clone(), Schema::new(public.clone()));
+        Self {
+            schemas,
+            search_path: vec![public],
+            functions: FunctionCatalog::builtin(),
+        }
+    }
+
+    /// Iterate over every schema in the order they were declared.
+    pub fn schemas(&self) -> impl Iterator<Item = &Schema> {
+        self.schemas.values()
+    }
+
+    pub fn schema(&self, name: &Ident) -> Option<&Schema> {
+        self.schemas.get(&normalise_ident(name))
+    }
+
+    pub fn search_path(&self) -> &[Ident] {
+        &self.search_path
+    }
+
+    /// Replace the search path used for resolving unqualified names. The first schema in
+    /// the search path is also where unqualified objects get created.
+    pub fn set_search_path(&mut self, search_path: impl IntoIterator<Item = Ident>) {
+        self.search_path = search_path
+            .into_iter()
+            .map(|ident| normalise_ident(&ident))
+            .collect();
+    }
+
+    /// The functions, operators and casts queries can use.
+    pub fn functions(&self) -> &FunctionCatalog {
+        &self.functions
+    }
+
+    /// Add functions, operators or casts, such as those from an extension the catalog doesn't
+    /// know about.
+    pub fn functions_mut(&mut self) -> &mut FunctionCatalog {
+        &mut self.functions
+    }
+
+    /// Iterate over every table in every schema, in declaration order.
+    pub fn tables(&self) -> impl Iterator<Item = &Table> {
+        self.schemas().flat_map(|schema| schema.tables.values())
+    }
+
+    /// Iterate over every type in every schema, in declaration order.
+    pub fn types(&self) -> impl Iterator<Item = &Type> {
+        self.schemas().flat_map(|schema| schema.types.values())
+    }
+
+    pub fn table(&self, name: &ObjectName) -> Option<&Table> {
+        let (schema, name) = self.resolve(name, |schema, name| schema.tables.contains_key(name))?;
+        self.schemas[&schema].tables.get(&name)
+    }
+
+    /// Iterate over every view in every schema, in declaration order.
+    pub fn views(&self) -> impl Iterator<Item = &View> {
+        self.schemas().flat_map(|schema| schema.views.values())
+    }
+
+    /// Iterate over every index in every schema, in declaration order.
+    pub fn indexes(&self) -> impl Iterator<Item = &Index> {
+        self.schemas().flat_map(|schema| schema.indexes.values())
+    }
+
+    pub fn view(&self, name: &ObjectName) -> Option<&View> {
+        let (schema, name) = self.resolve(name, |schema, name| schema.views.contains_key(name))?;
+        self.schemas[&schema].views.get(&name)
+    }
+
+    pub fn index(&self, name: &ObjectName) -> Option<&Index> {
+        let (schema, name) =
+            self.resolve(name, |schema, name| schema.indexes.contains_key(name))?;
+        self.schemas[&schema].indexes.get(&name)
+    }
+
+    /// Resolve the name of a table or view to its fully qualified name.
+    pub fn relation(&self, name: &ObjectName) -> Option<ObjectName> {
+        let (schema, name) = self.resolve(name, |schema, name| {
+            schema.tables.contains_key(name) || schema.views.contains_key(name)
+        })?;
+        Some(ObjectName(vec![schema, name]))
+    }
+
+    pub fn r#type(&self, name: &ObjectName) -> Option<&Type> {
+        let (schema, name) = self.resolve(name, |schema, name| schema.types.contains_key(name))?;
+        self.schemas[&schema].types.get(&name)
+    }
+
+    /// `data_type` with any types the schema creates given their fully qualified name,
+    /// `[schema, type]`, so it means the same thing whatever the search path. Names that don't
+    /// resolve are left as they are, which the schema analyser only allows for built-in types.
+    pub fn qualified_type(&self, data_type: &DataType) -> DataType {
+        match data_type {
+            DataType::Custom(name, modifiers) => {
+                match self.resolve(name, |schema, name| schema.types.contains_key(name)) {
+                    Some((schema, name)) => {
+                        DataType::Custom(ObjectName(vec![schema, name]), modifiers.clone())
+                    }
+                    None => data_type.clone(),
+                }
+            }
+            DataType::Array(
+                ArrayElemTypeDef::AngleBracket(element)
+                | ArrayElemTypeDef::SquareBracket(element, _)
+                | ArrayElemTypeDef::Parenthesis(element),
+            ) => DataType::Array(ArrayElemTypeDef::SquareBracket(
+                Box::new(self.qualified_type(element)),
+                None,
+            )),
+            data_type => data_type.clone(),
+        }
+    }
+
+    /// The [canonical_type] of `data_type`, with types the schema creates qualified as
+    /// [Catalog::qualified_type] does. Queries are analysed in terms of these, so a domain
+    /// gets the casts [FunctionCatalog::add_domain] gave it whatever the search path.
+    pub fn canonical_type(&self, data_type: &DataType) -> DataType {
+        canonical_type(&self.qualified_type(data_type))
+    }
+
+    /// Every overload of the user defined function `name`, which is empty if there isn't one.
+    /// Built-in functions are in [Catalog::functions] instead.
+    pub fn function(&self, name: &ObjectName) -> &[Function] {
+        self.resolve(name, |schema, name| schema.functions.contains_key(name))
+            .and_then(|(schema, name)| self.schemas[&schema].functions.get(&name))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    pub fn column(&self, table: &ObjectName, column: &Ident) -> Option<&ColumnDef> {
+        self.table(table)?.column(column)
+    }
+
+    /// Work out which schema an object lives in, returning the normalised schema and object
+    /// names. `exists` is used to decide whether an unqualified name can be found in each
+    /// schema in the search path.
+    pub fn resolve(
+        &self,
+        name: &ObjectName,
+        exists: impl Fn(&Schema, &Ident) -> bool,
+    ) -> Option<(Ident, Ident)> {
+        match name.0.as_slice() {
+            [object_name] => {
+                let object_name = normalise_ident(object_name);
+                self.search_path
+                    .iter()
+                    .filter_map(|schema_name| self.schemas.get(schema_name))
+                    .find(|schema| exists(schema, &object_name))
+                    .map(|schema| (schema.name.clone(), object_name))
+            }
+            [schema_name, object_name] | [_, schema_name, object_name] => {
+                let schema_name = normalise_ident(schema_name);
+                let object_name = normalise_ident(object_name);
+                let schema = self.schemas.get(&schema_name)?;
+                exists(schema, &object_name).then_some((schema_name, object_name))
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn table_mut(&mut self, name: &ObjectName) -> Option<&mut Table> {
+        let (schema, name) = self.resolve(name, |schema, name| schema.tables.contains_key(name))?;
+        self.schemas.get_mut(&schema)?.tables.get_mut(&name)
+    }
+
+    pub(crate) fn type_mut(&mut self, name: &ObjectName) -> Option<&mut Type> {
+        let (schema, name) = self.resolve(name, |schema, name| schema.types.contains_key(name))?;
+        self.schemas.get_mut(&schema)?.types.get_mut(&name)
+    }
+
+    pub(crate) fn schemas_mut(&mut self) -> impl Iterator<Item = &mut Schema> {
+        self.schemas.values_mut()
+    }
+
+    pub(crate) fn schema_mut(&mut self, name: &Ident) -> Option<&mut Schema> {
+        self.schemas.get_mut(&normalise_ident(name))
+    }
+
+    pub(crate) fn remove_schema(&mut self, name: &Ident) -> Option<Schema> {
+        self.schemas.shift_remove(&normalise_ident(name))
+    }
+
+    pub(crate) fn tables_mut(&mut self) -> impl Iterator<Item = &mut Table> {
+        self.schemas
+            .values_mut()
+            .flat_map(|schema| schema.tables.values_mut())
+    }
+
+    /// Regenerate the composite type for a table after the table has changed.
+    pub(crate) fn sync_table_type(&mut self, table_name: &ObjectName) {
+        let Some(table) = self.table(table_name) else {
+            return;
+        };
+        let r#type = table.composite_type();
+        let [schema_name, name] = table.name.0.as_slice() else {
+            unreachable!("tables in the catalog are fully qualified")
+        };
+        let name = name.clone();
+        self.schema_entry(&schema_name.clone())
+            .types
+            .insert(name, r#type);
+    }
+
+    pub(crate) fn schema_entry(&mut self, name: &Ident) -> &mut Schema {
+        let name = normalise_ident(name);
+        self.schemas
+            .entry(name.clone())
+            .or_insert_with(|| Schema::new(name))
+    }
+
+    /// Find the schema a new object called `object_name` should be created in, along with
+    /// the normalised, unqualified name of the object. Returns `None` if `object_name` has
+    /// more qualifiers than Postgres allows.
+    pub(crate) fn schema_for_object(
+        &mut self,
+        object_name: &ObjectName,
+    ) -> Option<(&mut Schema, Ident)> {
+        match object_name.0.as_slice() {
+            [name] => {
+                let schema_name = self
+                    .search_path
+                    .first()
+                    .cloned()
+                    .unwrap_or_else(|| Ident::new("public"));
+                Some((self.schema_entry(&schema_name), normalise_ident(name)))
+            }
+            [schema_name, name] | [_, schema_name, name] => {
+                Some((self.schema_entry(schema_name), normalise_ident(name)))
+            }
+            _ => None,
+        }
+    }
+}
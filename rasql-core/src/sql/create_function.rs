@@ -0,0 +1,408 @@
+//! `CREATE FUNCTION` and `DROP FUNCTION`.
+//!
+//! sqlparser can't parse `RETURNS SETOF` at all, and only parses `RETURNS TABLE` when each
+//! column's type is a single word, so [rewrite_function_returns] turns both into something it
+//! can parse: `SETOF('type')` and `TABLE('column', 'type', ...)`. These come out as custom types,
+//! the same shape sqlparser gives the `RETURNS TABLE` clauses it can parse.
+
+use sqlparser::{
+    ast::{
+        ArgMode, CreateFunction, DataType, FunctionCalledOnNull, FunctionDesc, Ident, ObjectName,
+        OperateFunctionArg, Spanned,
+    },
+    dialect::PostgreSqlDialect,
+    parser::{Parser, ParserError},
+    tokenizer::{Span, Token, TokenWithSpan},
+};
+
+use super::{
+    normalise_ident, types::canonical_type, Field, Function, FunctionParameter, FunctionReturns,
+    ObjectKind, SchemaAnalyser, SchemaError,
+};
+
+/// Keywords that can follow the return type of a function, ending it.
+const FUNCTION_OPTIONS: &[&str] = &[
+    "AS",
+    "BEGIN",
+    "CALLED",
+    "COST",
+    "EXTERNAL",
+    "IMMUTABLE",
+    "LANGUAGE",
+    "LEAKPROOF",
+    "NOT",
+    "PARALLEL",
+    "RETURN",
+    "RETURNS",
+    "ROWS",
+    "SECURITY",
+    "SET",
+    "STABLE",
+    "STRICT",
+    "SUPPORT",
+    "TRANSFORM",
+    "VOLATILE",
+    "WINDOW",
+];
+
+impl SchemaAnalyser {
+    pub(super) fn create_function(
+        &mut self,
+        CreateFunction {
+            or_replace,
+            name,
+            args,
+            return_type,
+            called_on_null,
+            ..
+        }: CreateFunction,
+    ) -> Result<(), SchemaError> {
+        let location = self.location(name.span());
+        let mut parameters = vec![];
+        let mut outputs = vec![];
+        for arg in args.unwrap_or_default() {
+            let mode = arg.mode.unwrap_or(ArgMode::In);
+            if matches!(mode, ArgMode::Out | ArgMode::InOut) {
+                outputs.push(Field {
+                    // Postgres names unnamed output columns the same way
+                    name: arg.name.as_ref().map_or_else(
+                        || Ident::new(format!("column{}", outputs.len() + 1)),
+                        normalise_ident,
+                    ),
+                    r#type: self.catalog.qualified_type(&arg.data_type),
+                    nullable: true,
+                });
+            }
+            if matches!(mode, ArgMode::In | ArgMode::InOut) {
+                parameters.push(FunctionParameter {
+                    name: arg.name.as_ref().map(normalise_ident),
+                    r#type: self.catalog.qualified_type(&arg.data_type),
+                    has_default: arg.default_expr.is_some(),
+                });
+            }
+        }
+
+        let (returns, returns_set) = match return_type {
+            Some(return_type) => {
+                function_returns(return_type).map_err(|error| SchemaError::Parse {
+                    location: location.clone(),
+                    message: error.to_string(),
+                })?
+            }
+            None => (FunctionReturns::Void, false),
+        };
+        // Types the schema creates are qualified, so calls match whatever the search path is
+        // when the query is analysed
+        let returns = match returns {
+            FunctionReturns::Value(r#type) => {
+                FunctionReturns::Value(self.catalog.qualified_type(&r#type))
+            }
+            FunctionReturns::Columns(columns) => FunctionReturns::Columns(
+                columns
+                    .into_iter()
+                    .map(|column| Field {
+                        r#type: self.catalog.qualified_type(&column.r#type),
+                        ..column
+                    })
+                    .collect(),
+            ),
+            FunctionReturns::Void => FunctionReturns::Void,
+        };
+        // The return type is only `record` or a single output's type when there are outputs,
+        // so the outputs say more about the rows
+        let returns = if outputs.is_empty() {
+            returns
+        } else {
+            FunctionReturns::Columns(outputs)
+        };
+
+        let Some((schema, function_name)) = self.catalog.schema_for_object(&name) else {
+            return Err(SchemaError::OverQualifiedName { location, name });
+        };
+        let qualified_name = ObjectName(vec![schema.name.clone(), function_name.clone()]);
+        let function = Function {
+            name: qualified_name.clone(),
+            parameters,
+            returns,
+            returns_set,
+            strict: matches!(
+                called_on_null,
+                Some(FunctionCalledOnNull::Strict | FunctionCalledOnNull::ReturnsNullOnNullInput)
+            ),
+        };
+
+        // Overloads are told apart by their parameter types alone
+        let parameter_types = function_parameter_types(&function);
+        let overloads = schema.functions.entry(function_name).or_default();
+        match overloads
+            .iter()
+            .position(|overload| function_parameter_types(overload) == parameter_types)
+        {
+            Some(index) if or_replace => overloads[index] = function,
+            Some(_) => {
+                return Err(SchemaError::DuplicateObject {
+                    location,
+                    kind: ObjectKind::Function,
+                    name: qualified_name,
+                })
+            }
+            None => overloads.push(function),
+        }
+        Ok(())
+    }
+
+    /// Drop each function in `functions`. A function given without a parameter list drops
+    /// every overload with that name.
+    pub(super) fn drop_function(
+        &mut self,
+        if_exists: bool,
+        functions: Vec<FunctionDesc>,
+    ) -> Result<(), SchemaError> {
+        for FunctionDesc { name, args } in functions {
+            let location = self.location(name.span());
+            let parameter_types = args.map(|args| {
+                args.iter()
+                    .filter(|arg| is_input(arg))
+                    .map(|arg| self.catalog.canonical_type(&arg.data_type))
+                    .collect::<Vec<_>>()
+            });
+            let resolved = self
+                .catalog
+                .resolve(&name, |schema, name| schema.functions.contains_key(name));
+            let mut dropped = false;
+            if let Some((schema_name, function_name)) = resolved {
+                let schema = self.catalog.schema_mut(&schema_name).unwrap();
+                let overloads = schema.functions.get_mut(&function_name).unwrap();
+                let count = overloads.len();
+                overloads.retain(|overload| {
+                    parameter_types
+                        .as_ref()
+                        .is_some_and(|types| function_parameter_types(overload) != *types)
+                });
+                dropped = overloads.len() < count;
+                if overloads.is_empty() {
+                    schema.functions.shift_remove(&function_name);
+                }
+            }
+            if !dropped && !if_exists {
+                self.errors.push(SchemaError::UnknownObject {
+                    location,
+                    kind: ObjectKind::Function,
+                    name,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `arg` is passed to the function, rather than being one of its outputs.
+fn is_input(arg: &OperateFunctionArg) -> bool {
+    !matches!(arg.mode, Some(ArgMode::Out))
+}
+
+fn function_parameter_types(function: &Function) -> Vec<DataType> {
+    function
+        .parameters
+        .iter()
+        .map(|parameter| canonical_type(&parameter.r#type))
+        .collect()
+}
+
+/// Work out what a function declared `RETURNS return_type` returns, and whether it returns a
+/// set.
+fn function_returns(return_type: DataType) -> Result<(FunctionReturns, bool), ParserError> {
+    let DataType::Custom(ObjectName(name), modifiers) = &return_type else {
+        return Ok((FunctionReturns::Value(return_type), false));
+    };
+    let name = match name.as_slice() {
+        [name] if name.quote_style.is_none() => name.value.to_uppercase(),
+        _ => String::new(),
+    };
+    match (name.as_str(), modifiers.as_slice()) {
+        ("SETOF", [r#type]) => {
+            let r#type = parse(r#type, |parser| parser.parse_data_type())?;
+            Ok((value_returns(r#type), true))
+        }
+        ("TABLE", columns) if columns.len() % 2 == 0 => {
+            let columns = columns
+                .chunks(2)
+                .map(|column| {
+                    Ok(Field {
+                        name: normalise_ident(&parse(&column[0], |parser| {
+                            parser.parse_identifier()
+                        })?),
+                        r#type: parse(&column[1], |parser| parser.parse_data_type())?,
+                        nullable: true,
+                    })
+                })
+                .collect::<Result<_, ParserError>>()?;
+            Ok((FunctionReturns::Columns(columns), true))
+        }
+        _ => Ok((value_returns(return_type), false)),
+    }
+}
+
+fn value_returns(r#type: DataType) -> FunctionReturns {
+    match &r#type {
+        DataType::Custom(ObjectName(name), modifiers)
+            if modifiers.is_empty()
+                && matches!(name.as_slice(), [name] if normalise_ident(name).value == "void") =>
+        {
+            FunctionReturns::Void
+        }
+        _ => FunctionReturns::Value(r#type),
+    }
+}
+
+/// Parse a piece of SQL that [rewrite_function_returns] put in a string.
+fn parse<T>(
+    sql: &str,
+    parse: impl FnOnce(&mut Parser) -> Result<T, ParserError>,
+) -> Result<T, ParserError> {
+    parse(&mut Parser::new(&PostgreSqlDialect {}).try_with_sql(sql)?)
+}
+
+/// Rewrite `RETURNS SETOF type` and `RETURNS TABLE (column type, ...)` in `tokens` into forms
+/// sqlparser can parse, as described in the [module docs](self). Anything that doesn't look
+/// like it can be rewritten is left for the parser to report.
+pub(super) fn rewrite_function_returns(tokens: Vec<TokenWithSpan>) -> Vec<TokenWithSpan> {
+    let mut rewritten = Vec::with_capacity(tokens.len());
+    let mut index = 0;
+    while index < tokens.len() {
+        rewritten.push(tokens[index].clone());
+        index += 1;
+        if !is_word(tokens.get(index - 1), "RETURNS") {
+            continue;
+        }
+
+        let start = skip_whitespace(&tokens, index);
+        if is_word(tokens.get(start), "SETOF") {
+            let end = end_of_type(&tokens, start + 1);
+            let r#type = tokens_to_sql(&tokens[start + 1..end]);
+            rewritten.extend(type_call(
+                "SETOF",
+                vec![r#type],
+                span_of(&tokens[start..end]),
+            ));
+            index = end;
+        } else if is_word(tokens.get(start), "TABLE") {
+            if let Some((columns, end)) = table_columns(&tokens, start + 1) {
+                rewritten.extend(type_call("TABLE", columns, span_of(&tokens[start..end])));
+                index = end;
+            }
+        }
+    }
+    rewritten
+}
+
+/// Read the parenthesised column list of a `RETURNS TABLE` that starts at `start`, returning
+/// the name and type of each column and the index just after the list.
+fn table_columns(tokens: &[TokenWithSpan], start: usize) -> Option<(Vec<String>, usize)> {
+    let open = skip_whitespace(tokens, start);
+    if tokens.get(open)?.token != Token::LParen {
+        return None;
+    }
+    let mut columns = vec![];
+    let mut column_start = open + 1;
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(open + 1) {
+        match token.token {
+            Token::LParen | Token::LBracket => depth += 1,
+            Token::RParen | Token::RBracket if depth > 0 => depth -= 1,
+            Token::Comma | Token::RParen if depth == 0 => {
+                let name = skip_whitespace(tokens, column_start);
+                if name >= index {
+                    return None;
+                }
+                columns.push(tokens_to_sql(&tokens[name..name + 1]));
+                columns.push(tokens_to_sql(&tokens[name + 1..index]));
+                if token.token == Token::RParen {
+                    return Some((columns, index + 1));
+                }
+                column_start = index + 1;
+            }
+            Token::SemiColon => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Find where a type starting at `start` ends, which is at the first function option or the
+/// end of the statement.
+fn end_of_type(tokens: &[TokenWithSpan], start: usize) -> usize {
+    let mut depth = 0;
+    for (index, token) in tokens.iter().enumerate().skip(start) {
+        match &token.token {
+            Token::LParen | Token::LBracket => depth += 1,
+            Token::RParen | Token::RBracket => depth -= 1,
+            Token::SemiColon => return index,
+            Token::Word(word)
+                if depth == 0
+                    && word.quote_style.is_none()
+                    && FUNCTION_OPTIONS.contains(&word.value.to_uppercase().as_str()) =>
+            {
+                return index
+            }
+            _ => {}
+        }
+    }
+    tokens.len()
+}
+
+fn is_word(token: Option<&TokenWithSpan>, keyword: &str) -> bool {
+    matches!(
+        token.map(|token| &token.token),
+        Some(Token::Word(word)) if word.quote_style.is_none() && word.value.eq_ignore_ascii_case(keyword)
+    )
+}
+
+fn skip_whitespace(tokens: &[TokenWithSpan], mut index: usize) -> usize {
+    while matches!(
+        tokens.get(index),
+        Some(TokenWithSpan {
+            token: Token::Whitespace(_),
+            ..
+        })
+    ) {
+        index += 1;
+    }
+    index
+}
+
+/// Turn tokens back into SQL, with any whitespace or comments between them as a single space.
+fn tokens_to_sql(tokens: &[TokenWithSpan]) -> String {
+    let mut sql = String::new();
+    for token in tokens {
+        match &token.token {
+            Token::Whitespace(_) => {
+                if !sql.ends_with(' ') {
+                    sql.push(' ');
+                }
+            }
+            token => sql.push_str(&token.to_string()),
+        }
+    }
+    sql.trim().to_owned()
+}
+
+fn span_of(tokens: &[TokenWithSpan]) -> Span {
+    Span::union_iter(tokens.iter().map(|token| token.span))
+}
+
+/// The tokens of `name('arg', ...)`, all spanning `span`.
+fn type_call(name: &str, args: Vec<String>, span: Span) -> Vec<TokenWithSpan> {
+    let mut tokens = vec![Token::make_word(name, None), Token::LParen];
+    for (index, arg) in args.into_iter().enumerate() {
+        if index > 0 {
+            tokens.push(Token::Comma);
+        }
+        tokens.push(Token::SingleQuotedString(arg));
+    }
+    tokens.push(Token::RParen);
+    tokens
+        .into_iter()
+        .map(|token| TokenWithSpan { token, span })
+        .collect()
+}
@@ -0,0 +1,158 @@
+//! `CREATE DOMAIN` and `DROP DOMAIN`, which sqlparser can't parse at all, so
+//! [parse_create_domain] and [parse_drop_domain] parse them instead.
+
+use sqlparser::{
+    ast::{DataType, Expr, Ident, ObjectName, ObjectType},
+    keywords::Keyword,
+    parser::{Parser, ParserError},
+    tokenizer::Token,
+};
+
+use super::{SchemaAnalyser, SchemaError, Type};
+
+/// A `CREATE DOMAIN` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateDomain {
+    pub name: ObjectName,
+    pub data_type: DataType,
+    pub default: Option<Expr>,
+    pub constraints: Vec<DomainConstraint>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomainConstraint {
+    NotNull {
+        name: Option<Ident>,
+    },
+    Null {
+        name: Option<Ident>,
+    },
+    Check {
+        name: Option<Ident>,
+        expr: Box<Expr>,
+    },
+}
+
+/// A `DROP DOMAIN` statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropDomain {
+    pub if_exists: bool,
+    pub names: Vec<ObjectName>,
+    pub cascade: bool,
+}
+
+/// Consume `keyword DOMAIN` if that's what comes next. `DOMAIN` isn't one of sqlparser's
+/// keywords, so it's matched as a plain word.
+pub(super) fn parse_domain_keyword(parser: &mut Parser, keyword: Keyword) -> bool {
+    let is_domain = matches!(
+        &parser.peek_nth_token(1).token,
+        Token::Word(word) if word.quote_style.is_none() && word.value.eq_ignore_ascii_case("DOMAIN")
+    );
+    if is_domain && parser.parse_keyword(keyword) {
+        parser.next_token();
+        true
+    } else {
+        false
+    }
+}
+
+/// Parse the rest of a `CREATE DOMAIN` statement, after `CREATE DOMAIN` itself.
+pub(super) fn parse_create_domain(parser: &mut Parser) -> Result<CreateDomain, ParserError> {
+    let name = parser.parse_object_name(false)?;
+    // `AS` is optional
+    let _ = parser.parse_keyword(Keyword::AS);
+    let data_type = parser.parse_data_type()?;
+    let mut default = None;
+    let mut constraints = vec![];
+    loop {
+        if parser.parse_keyword(Keyword::COLLATE) {
+            parser.parse_object_name(false)?;
+        } else if parser.parse_keyword(Keyword::DEFAULT) {
+            default = Some(parser.parse_expr()?);
+        } else {
+            let name = if parser.parse_keyword(Keyword::CONSTRAINT) {
+                Some(parser.parse_identifier()?)
+            } else {
+                None
+            };
+            let constraint = if parser.parse_keywords(&[Keyword::NOT, Keyword::NULL]) {
+                DomainConstraint::NotNull { name }
+            } else if parser.parse_keyword(Keyword::NULL) {
+                DomainConstraint::Null { name }
+            } else if parser.parse_keyword(Keyword::CHECK) {
+                parser.expect_token(&Token::LParen)?;
+                let expr = Box::new(parser.parse_expr()?);
+                parser.expect_token(&Token::RParen)?;
+                DomainConstraint::Check { name, expr }
+            } else if name.is_some() {
+                return parser.expected("NOT NULL, NULL or CHECK", parser.peek_token());
+            } else {
+                break;
+            };
+            constraints.push(constraint);
+        }
+    }
+    Ok(CreateDomain {
+        name,
+        data_type,
+        default,
+        constraints,
+    })
+}
+
+/// Parse the rest of a `DROP DOMAIN` statement, after `DROP DOMAIN` itself.
+pub(super) fn parse_drop_domain(parser: &mut Parser) -> Result<DropDomain, ParserError> {
+    let if_exists = parser.parse_keywords(&[Keyword::IF, Keyword::EXISTS]);
+    let names = parser.parse_comma_separated(|parser| parser.parse_object_name(false))?;
+    let cascade = parser.parse_one_of_keywords(&[Keyword::CASCADE, Keyword::RESTRICT])
+        == Some(Keyword::CASCADE);
+    Ok(DropDomain {
+        if_exists,
+        names,
+        cascade,
+    })
+}
+
+impl SchemaAnalyser {
+    pub(super) fn create_domain(
+        &mut self,
+        CreateDomain {
+            name,
+            data_type,
+            constraints,
+            ..
+        }: CreateDomain,
+    ) -> Result<(), SchemaError> {
+        let base = self.resolve_type(&data_type)?;
+        let (schema, name, qualified_name) = self.create_type(&name)?;
+        schema.types.insert(
+            name,
+            Type::Domain {
+                name: qualified_name.clone(),
+                base: base.clone(),
+                checks: constraints
+                    .into_iter()
+                    .filter_map(|constraint| match constraint {
+                        DomainConstraint::Check { expr, .. } => Some(*expr),
+                        _ => None,
+                    })
+                    .collect(),
+            },
+        );
+        self.catalog
+            .functions_mut()
+            .add_domain(DataType::Custom(qualified_name, vec![]), base);
+        Ok(())
+    }
+
+    pub(super) fn drop_domain(
+        &mut self,
+        DropDomain {
+            if_exists,
+            names,
+            cascade,
+        }: DropDomain,
+    ) -> Result<(), SchemaError> {
+        self.drop(ObjectType::Type, if_exists, names, cascade)
+    }
+}
@@ -0,0 +1,417 @@
+use std::fmt::Display;
+
+use sqlparser::{
+    ast::{ArrayElemTypeDef, DataType, DropBehavior, Ident, ObjectName, ObjectType, Spanned},
+    tokenizer::Span,
+};
+
+use super::{
+    constraint_name, normalise_ident, ObjectKind, SchemaAnalyser, SchemaError, TableConstraint,
+    Type, View,
+};
+
+/// Something that has to be dropped along with the object it depends on.
+pub(super) enum Dependent {
+    /// A table, view, index or type
+    Object {
+        kind: ObjectKind,
+        name: ObjectName,
+    },
+    Constraint {
+        table: ObjectName,
+        name: Ident,
+    },
+    Column {
+        table: ObjectName,
+        column: Ident,
+    },
+    /// An attribute of a composite type
+    Attribute {
+        r#type: ObjectName,
+        name: Ident,
+    },
+}
+
+impl Dependent {
+    /// Whether this dependent is, or belongs to, one of the objects in `dropped`.
+    fn is_within(&self, dropped: &[ObjectName]) -> bool {
+        match self {
+            Dependent::Object { name, .. } => dropped.contains(name),
+            Dependent::Constraint { table, .. } | Dependent::Column { table, .. } => {
+                dropped.contains(table)
+            }
+            Dependent::Attribute { r#type, .. } => dropped.contains(r#type),
+        }
+    }
+}
+
+impl Display for Dependent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dependent::Object { kind, name } => write!(f, "{kind} `{name}`"),
+            Dependent::Constraint { table, name } => {
+                write!(f, "constraint `{name}` on table `{table}`")
+            }
+            Dependent::Column { table, column } => {
+                write!(f, "column `{column}` of table `{table}`")
+            }
+            Dependent::Attribute { r#type, name } => {
+                write!(f, "column `{name}` of composite type `{type}`")
+            }
+        }
+    }
+}
+
+impl SchemaAnalyser {
+    pub(super) fn drop(
+        &mut self,
+        object_type: ObjectType,
+        if_exists: bool,
+        names: Vec<ObjectName>,
+        cascade: bool,
+    ) -> Result<(), SchemaError> {
+        let drop_behavior = cascade.then_some(DropBehavior::Cascade);
+        let kind = match object_type {
+            ObjectType::Table => ObjectKind::Table,
+            ObjectType::View => ObjectKind::View,
+            ObjectType::Index => ObjectKind::Index,
+            ObjectType::Type => ObjectKind::Type,
+            ObjectType::Schema => {
+                for name in names {
+                    if let Err(error) = self.drop_schema(name, if_exists, drop_behavior) {
+                        self.errors.push(error);
+                    }
+                }
+                return Ok(());
+            }
+            // Nothing else affects the shape of the schema
+            _ => return Ok(()),
+        };
+
+        // Resolve every name up front, so objects dropped together aren't treated as
+        // depending on each other
+        let mut dropped = vec![];
+        for name in names {
+            let resolved = self.catalog.resolve(&name, |schema, name| match kind {
+                ObjectKind::Table => schema.tables.contains_key(name),
+                ObjectKind::View => schema.views.contains_key(name),
+                ObjectKind::Index => schema.indexes.contains_key(name),
+                _ => schema.types.contains_key(name),
+            });
+            match resolved {
+                Some((schema, object)) => {
+                    dropped.push((name.span(), ObjectName(vec![schema, object])))
+                }
+                None if if_exists => (),
+                None => self.errors.push(SchemaError::UnknownObject {
+                    location: self.location(name.span()),
+                    kind,
+                    name,
+                }),
+            }
+        }
+        let names: Vec<_> = dropped.iter().map(|(_, name)| name.clone()).collect();
+        for (span, name) in dropped {
+            if let Err(error) = self.drop_object(kind, &name, span, &names, drop_behavior) {
+                self.errors.push(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop the table, view, index, type or function `name`, which must be fully qualified.
+    /// Dependents that are part of `dropped` are about to be dropped anyway, so they don't stop
+    /// the drop. Objects that have already gone are skipped, as a cascade can reach an object
+    /// more than once.
+    fn drop_object(
+        &mut self,
+        kind: ObjectKind,
+        name: &ObjectName,
+        span: Span,
+        dropped: &[ObjectName],
+        drop_behavior: Option<DropBehavior>,
+    ) -> Result<(), SchemaError> {
+        let mut dependents = match kind {
+            ObjectKind::Table => {
+                if self.catalog.table(name).is_none() {
+                    return Ok(());
+                }
+                let mut dependents = self.foreign_keys_referencing(name, |_| true);
+                dependents.extend(self.views_depending_on(name, |_| true));
+                dependents.extend(self.columns_of_type(name));
+                dependents
+            }
+            ObjectKind::View => {
+                if self.catalog.view(name).is_none() {
+                    return Ok(());
+                }
+                self.views_depending_on(name, |_| true)
+            }
+            ObjectKind::Type => {
+                if self.catalog.r#type(name).is_none() {
+                    return Ok(());
+                }
+                if self.catalog.table(name).is_some() {
+                    return Err(SchemaError::DependentObjects {
+                        location: self.location(span),
+                        kind,
+                        name: name.clone(),
+                        dependents: vec![format!("table `{name}`")],
+                    });
+                }
+                self.columns_of_type(name)
+            }
+            _ => vec![],
+        };
+        dependents.retain(|dependent| !dependent.is_within(dropped));
+        self.drop_dependents(kind, name.clone(), span, dependents, drop_behavior)?;
+
+        let Some(schema) = self.catalog.schema_mut(&name.0[0]) else {
+            return Ok(());
+        };
+        let object_name = &name.0[1];
+        match kind {
+            ObjectKind::Table => {
+                schema.tables.shift_remove(object_name);
+                schema.types.shift_remove(object_name);
+                schema.indexes.retain(|_, index| index.table != *name);
+            }
+            ObjectKind::View => {
+                schema.views.shift_remove(object_name);
+            }
+            ObjectKind::Index => {
+                schema.indexes.shift_remove(object_name);
+            }
+            ObjectKind::Type => {
+                schema.types.shift_remove(object_name);
+            }
+            ObjectKind::Function => {
+                schema.functions.shift_remove(object_name);
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn drop_schema(
+        &mut self,
+        name: ObjectName,
+        if_exists: bool,
+        drop_behavior: Option<DropBehavior>,
+    ) -> Result<(), SchemaError> {
+        let span = name.span();
+        let [schema_name] = name.0.as_slice() else {
+            return Err(SchemaError::OverQualifiedName {
+                location: self.location(span),
+                name,
+            });
+        };
+        let Some(schema) = self.catalog.schema(schema_name) else {
+            return if if_exists {
+                Ok(())
+            } else {
+                Err(SchemaError::UnknownObject {
+                    location: self.location(span),
+                    kind: ObjectKind::Schema,
+                    name,
+                })
+            };
+        };
+
+        let qualify = |object: &Ident| ObjectName(vec![schema.name.clone(), object.clone()]);
+        let tables: Vec<_> = schema.tables.keys().map(qualify).collect();
+        let views: Vec<_> = schema.views.keys().map(qualify).collect();
+        // Row types of tables go along with their table
+        let types: Vec<_> = schema
+            .types
+            .keys()
+            .filter(|name| !schema.tables.contains_key(*name))
+            .map(qualify)
+            .collect();
+        let functions: Vec<_> = schema.functions.keys().map(qualify).collect();
+        let schema_name = schema.name.clone();
+
+        let contents: Vec<_> = tables
+            .into_iter()
+            .map(|name| (ObjectKind::Table, name))
+            .chain(views.into_iter().map(|name| (ObjectKind::View, name)))
+            .chain(types.into_iter().map(|name| (ObjectKind::Type, name)))
+            .chain(
+                functions
+                    .into_iter()
+                    .map(|name| (ObjectKind::Function, name)),
+            )
+            .collect();
+        if !contents.is_empty() && !matches!(drop_behavior, Some(DropBehavior::Cascade)) {
+            return Err(SchemaError::DependentObjects {
+                location: self.location(span),
+                kind: ObjectKind::Schema,
+                name,
+                dependents: contents
+                    .into_iter()
+                    .map(|(kind, name)| Dependent::Object { kind, name }.to_string())
+                    .collect(),
+            });
+        }
+
+        // Anything outside the schema that depends on what's inside still has to be dropped
+        let dropped: Vec<_> = contents.iter().map(|(_, name)| name.clone()).collect();
+        for (kind, name) in &contents {
+            self.drop_object(*kind, name, span, &dropped, drop_behavior)?;
+        }
+        self.catalog.remove_schema(&schema_name);
+        Ok(())
+    }
+
+    /// Find every foreign key constraint referring to `table_name` whose referred columns
+    /// satisfy `refers_to`.
+    pub(super) fn foreign_keys_referencing(
+        &self,
+        table_name: &ObjectName,
+        refers_to: impl Fn(&[Ident]) -> bool,
+    ) -> Vec<Dependent> {
+        self.catalog
+            .tables()
+            .flat_map(|table| {
+                table
+                    .constraints
+                    .iter()
+                    .filter_map(|constraint| match constraint {
+                        TableConstraint::ForeignKey {
+                            name,
+                            foreign_table,
+                            referred_columns,
+                            ..
+                        } if foreign_table == table_name && refers_to(referred_columns) => {
+                            Some(Dependent::Constraint {
+                                table: table.name.clone(),
+                                name: name.clone()?,
+                            })
+                        }
+                        _ => None,
+                    })
+            })
+            .collect()
+    }
+
+    /// Find every view that reads from the table or view `name` and satisfies `filter`.
+    pub(super) fn views_depending_on(
+        &self,
+        name: &ObjectName,
+        filter: impl Fn(&View) -> bool,
+    ) -> Vec<Dependent> {
+        self.catalog
+            .views()
+            .filter(|view| view.dependencies.contains(name) && filter(view))
+            .map(|view| Dependent::Object {
+                kind: ObjectKind::View,
+                name: view.name.clone(),
+            })
+            .collect()
+    }
+
+    /// Find every table column, composite type attribute and domain whose type is, or is an
+    /// array of, the type `type_name`.
+    fn columns_of_type(&self, type_name: &ObjectName) -> Vec<Dependent> {
+        let columns = self.catalog.tables().flat_map(|table| {
+            table
+                .columns
+                .iter()
+                .filter(|column| self.uses_type(&column.data_type, type_name))
+                .map(|column| Dependent::Column {
+                    table: table.name.clone(),
+                    column: column.name.clone(),
+                })
+        });
+        // Row types of tables are kept in sync with their table, so only standalone composite
+        // types need checking here
+        let attributes = self.catalog.types().flat_map(|r#type| match r#type {
+            Type::Composite { name, fields } if self.catalog.table(name).is_none() => fields
+                .iter()
+                .filter(|field| self.uses_type(&field.r#type, type_name))
+                .map(|field| Dependent::Attribute {
+                    r#type: name.clone(),
+                    name: field.name.clone(),
+                })
+                .collect(),
+            Type::Domain { name, base, .. } if self.uses_type(base, type_name) => {
+                vec![Dependent::Object {
+                    kind: ObjectKind::Type,
+                    name: name.clone(),
+                }]
+            }
+            _ => vec![],
+        });
+        columns.chain(attributes).collect()
+    }
+
+    fn uses_type(&self, data_type: &DataType, type_name: &ObjectName) -> bool {
+        match data_type {
+            DataType::Custom(name, _) => self
+                .catalog
+                .resolve(name, |schema, name| schema.types.contains_key(name))
+                .is_some_and(|(schema, name)| type_name.0 == [schema, name]),
+            DataType::Array(
+                ArrayElemTypeDef::AngleBracket(data_type)
+                | ArrayElemTypeDef::SquareBracket(data_type, _)
+                | ArrayElemTypeDef::Parenthesis(data_type),
+            ) => self.uses_type(data_type, type_name),
+            _ => false,
+        }
+    }
+
+    /// Drop the objects that depend on an object that is about to be dropped, which is only
+    /// allowed if the drop cascades.
+    pub(super) fn drop_dependents(
+        &mut self,
+        kind: ObjectKind,
+        name: ObjectName,
+        span: Span,
+        dependents: Vec<Dependent>,
+        drop_behavior: Option<DropBehavior>,
+    ) -> Result<(), SchemaError> {
+        if dependents.is_empty() {
+            return Ok(());
+        }
+        if !matches!(drop_behavior, Some(DropBehavior::Cascade)) {
+            return Err(SchemaError::DependentObjects {
+                location: self.location(span),
+                kind,
+                name,
+                dependents: dependents
+                    .iter()
+                    .map(|dependent| dependent.to_string())
+                    .collect(),
+            });
+        }
+        for dependent in dependents {
+            match dependent {
+                Dependent::Object { kind, name } => {
+                    self.drop_object(kind, &name, span, &[], drop_behavior)?
+                }
+                Dependent::Constraint {
+                    table,
+                    name: dependent_name,
+                } => {
+                    if let Some(table) = self.catalog.table_mut(&table) {
+                        table.constraints.retain(|constraint| {
+                            constraint_name(constraint) != Some(&dependent_name)
+                        });
+                    }
+                }
+                Dependent::Column { table, column } => {
+                    if self.catalog.table(&table).is_some() {
+                        self.drop_column(&table, normalise_ident(&column), true, drop_behavior)?;
+                        self.catalog.sync_table_type(&table);
+                    }
+                }
+                Dependent::Attribute { r#type, name } => {
+                    if let Some(Type::Composite { fields, .. }) = self.catalog.type_mut(&r#type) {
+                        fields.retain(|field| field.name != name);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
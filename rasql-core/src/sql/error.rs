@@ -0,0 +1,379 @@
+use std::{fmt::Display, path::PathBuf};
+
+use sqlparser::{
+    ast::{DataType, Ident, ObjectName},
+    tokenizer::Span,
+};
+use thiserror::Error;
+
+/// Where in the analysed SQL something was found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The file the SQL was read from, if it was read from a file at all.
+    pub file: Option<PathBuf>,
+    pub span: Span,
+}
+
+impl Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let start = self.span.start;
+        match (&self.file, start.line) {
+            (Some(file), 0) => write!(f, "{}", file.display()),
+            (Some(file), line) => write!(f, "{}:{line}:{}", file.display(), start.column),
+            (None, 0) => write!(f, "<unknown location>"),
+            (None, line) => write!(f, "line {line}, column {}", start.column),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Schema,
+    Table,
+    Column,
+    Constraint,
+    View,
+    Index,
+    Type,
+    Function,
+}
+
+impl Display for ObjectKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ObjectKind::Schema => "schema",
+            ObjectKind::Table => "table",
+            ObjectKind::Column => "column",
+            ObjectKind::Constraint => "constraint",
+            ObjectKind::View => "view",
+            ObjectKind::Index => "index",
+            ObjectKind::Type => "type",
+            ObjectKind::Function => "function",
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum SchemaError {
+    #[error("{location}: failed to read SQL: {source}")]
+    Io {
+        location: SourceLocation,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(
+        "{location}: cannot tell where this migration belongs, expected a name like \
+        `0001_name.sql`, `V1__name.sql` or `R__name.sql`"
+    )]
+    UnversionedMigration { location: SourceLocation },
+    #[error("{location}: this migration has the same version as `{}`", other.display())]
+    DuplicateMigrationVersion {
+        location: SourceLocation,
+        /// The migration the version was first seen on
+        other: PathBuf,
+    },
+    #[error("{location}: failed to parse SQL: {message}")]
+    Parse {
+        location: SourceLocation,
+        message: String,
+    },
+    #[error("{location}: {kind} `{name}` already exists")]
+    DuplicateObject {
+        location: SourceLocation,
+        kind: ObjectKind,
+        name: ObjectName,
+    },
+    #[error("{location}: column `{column}` specified more than once for table `{table}`")]
+    DuplicateColumn {
+        location: SourceLocation,
+        table: ObjectName,
+        column: Ident,
+    },
+    #[error("{location}: multiple primary keys for table `{table}` are not allowed")]
+    MultiplePrimaryKeys {
+        location: SourceLocation,
+        table: ObjectName,
+    },
+    #[error("{location}: table `{name}` does not exist")]
+    UnknownTable {
+        location: SourceLocation,
+        name: ObjectName,
+    },
+    #[error("{location}: {kind} `{name}` does not exist")]
+    UnknownObject {
+        location: SourceLocation,
+        kind: ObjectKind,
+        name: ObjectName,
+    },
+    #[error("{location}: column `{column}` of table `{table}` does not exist")]
+    UnknownColumn {
+        location: SourceLocation,
+        table: ObjectName,
+        column: Ident,
+    },
+    #[error("{location}: constraint `{constraint}` of table `{table}` does not exist")]
+    UnknownConstraint {
+        location: SourceLocation,
+        table: ObjectName,
+        constraint: Ident,
+    },
+    #[error(
+        "{location}: cannot drop {kind} `{name}` because other objects depend on it: {}",
+        dependents.join(", ")
+    )]
+    DependentObjects {
+        location: SourceLocation,
+        kind: ObjectKind,
+        name: ObjectName,
+        /// Descriptions of each dependent object, e.g. "constraint `orders_user_fkey` on table
+        /// `public.orders`"
+        dependents: Vec<String>,
+    },
+    #[error(
+        "{location}: cannot alter type of column `{column}` because other objects depend on it: {}",
+        dependents.join(", ")
+    )]
+    ColumnTypeInUse {
+        location: SourceLocation,
+        /// The fully qualified name of the column, `[schema, table, column]`
+        column: ObjectName,
+        dependents: Vec<String>,
+    },
+    #[error("{location}: column `{column}` of table `{table}` is in a primary key")]
+    PrimaryKeyColumnNullable {
+        location: SourceLocation,
+        table: ObjectName,
+        column: Ident,
+    },
+    #[error(
+        "{location}: `{name}` has too many qualifiers, expected at most `database.schema.object`"
+    )]
+    OverQualifiedName {
+        location: SourceLocation,
+        name: ObjectName,
+    },
+    #[error("{location}: type `{name}` is not an enum")]
+    NotAnEnum {
+        location: SourceLocation,
+        name: ObjectName,
+    },
+    #[error("{location}: enum `{name}` already has the label `{}`", label.value)]
+    DuplicateEnumLabel {
+        location: SourceLocation,
+        name: ObjectName,
+        label: Ident,
+    },
+    #[error("{location}: enum `{name}` has no label `{}`", label.value)]
+    UnknownEnumLabel {
+        location: SourceLocation,
+        name: ObjectName,
+        label: Ident,
+    },
+    #[error("{location}: the query of view `{name}` is invalid: {source}")]
+    InvalidView {
+        location: SourceLocation,
+        name: ObjectName,
+        #[source]
+        source: QueryError,
+    },
+}
+
+impl SchemaError {
+    pub fn location(&self) -> &SourceLocation {
+        match self {
+            SchemaError::Io { location, .. }
+            | SchemaError::UnversionedMigration { location }
+            | SchemaError::DuplicateMigrationVersion { location, .. }
+            | SchemaError::Parse { location, .. }
+            | SchemaError::DuplicateObject { location, .. }
+            | SchemaError::DuplicateColumn { location, .. }
+            | SchemaError::MultiplePrimaryKeys { location, .. }
+            | SchemaError::UnknownTable { location, .. }
+            | SchemaError::UnknownObject { location, .. }
+            | SchemaError::UnknownColumn { location, .. }
+            | SchemaError::UnknownConstraint { location, .. }
+            | SchemaError::DependentObjects { location, .. }
+            | SchemaError::ColumnTypeInUse { location, .. }
+            | SchemaError::PrimaryKeyColumnNullable { location, .. }
+            | SchemaError::OverQualifiedName { location, .. }
+            | SchemaError::NotAnEnum { location, .. }
+            | SchemaError::DuplicateEnumLabel { location, .. }
+            | SchemaError::UnknownEnumLabel { location, .. }
+            | SchemaError::InvalidView { location, .. } => location,
+        }
+    }
+}
+
+/// A problem found while analysing a query against a [Catalog](super::Catalog).
+#[derive(Debug, Error)]
+pub enum QueryError {
+    #[error("{location}: failed to parse SQL: {message}")]
+    Parse {
+        location: SourceLocation,
+        message: String,
+    },
+    #[error("{location}: {description} is not supported in queries yet")]
+    Unsupported {
+        location: SourceLocation,
+        description: String,
+    },
+    #[error("{location}: table `{name}` does not exist")]
+    UnknownTable {
+        location: SourceLocation,
+        name: ObjectName,
+    },
+    #[error("{location}: missing FROM-clause entry for table `{name}`")]
+    UnknownRelation {
+        location: SourceLocation,
+        name: ObjectName,
+    },
+    #[error("{location}: table name `{name}` specified more than once")]
+    DuplicateRelation {
+        location: SourceLocation,
+        name: Ident,
+    },
+    #[error("{location}: table `{relation}` has {available} columns available but {given} columns specified")]
+    ColumnAliasCount {
+        location: SourceLocation,
+        relation: Ident,
+        available: usize,
+        given: usize,
+    },
+    #[error("{location}: column `{column}` does not exist")]
+    UnknownColumn {
+        location: SourceLocation,
+        column: ObjectName,
+    },
+    #[error("{location}: column reference `{column}` is ambiguous")]
+    AmbiguousColumn {
+        location: SourceLocation,
+        column: ObjectName,
+    },
+    #[error("{location}: position {position} is not in the select list")]
+    UnknownPosition {
+        location: SourceLocation,
+        position: String,
+    },
+    #[error("{location}: operator does not exist: {left} {operator} {right}")]
+    UndefinedOperator {
+        location: SourceLocation,
+        operator: String,
+        left: DataType,
+        right: DataType,
+    },
+    #[error("{location}: operator does not exist: {operator} {operand}")]
+    UndefinedPrefixOperator {
+        location: SourceLocation,
+        operator: String,
+        operand: DataType,
+    },
+    #[error("{location}: expected an expression of type {expected}, found {found}")]
+    UnexpectedType {
+        location: SourceLocation,
+        expected: DataType,
+        found: DataType,
+    },
+    #[error("{location}: function {signature} does not exist")]
+    UndefinedFunction {
+        location: SourceLocation,
+        /// The function's name and the types of the arguments it was called with
+        signature: String,
+    },
+    #[error("{location}: window function {name} requires an OVER clause")]
+    WindowFunctionWithoutOver {
+        location: SourceLocation,
+        name: ObjectName,
+    },
+    #[error(
+        "{location}: OVER specified, but {name} is not a window function nor an aggregate function"
+    )]
+    OverOnScalarFunction {
+        location: SourceLocation,
+        name: ObjectName,
+    },
+    #[error(
+        "{location}: column `{column}` is of type {expected} but expression is of type {found}"
+    )]
+    AssignmentType {
+        location: SourceLocation,
+        column: Ident,
+        expected: DataType,
+        found: DataType,
+    },
+    #[error("{location}: INSERT has {values} expressions but {columns} target columns")]
+    InsertColumnCount {
+        location: SourceLocation,
+        columns: usize,
+        values: usize,
+    },
+    #[error("{location}: types {left} and {right} cannot be matched")]
+    IncompatibleTypes {
+        location: SourceLocation,
+        left: DataType,
+        right: DataType,
+    },
+    #[error("{location}: each {operator} query must have the same number of columns, found {left} and {right}")]
+    SetOperationColumnCount {
+        location: SourceLocation,
+        /// `UNION`, `INTERSECT` or `EXCEPT`
+        operator: String,
+        left: usize,
+        right: usize,
+    },
+    #[error("{location}: subquery must return only one column, found {columns}")]
+    SubqueryColumnCount {
+        location: SourceLocation,
+        columns: usize,
+    },
+    #[error("{location}: could not determine the type of parameter ${index}")]
+    UnknownParameterType {
+        location: SourceLocation,
+        index: usize,
+    },
+    #[error(
+        "{location}: inconsistent types deduced for parameter ${index}: {first} versus {second}"
+    )]
+    ConflictingParameterTypes {
+        location: SourceLocation,
+        index: usize,
+        first: DataType,
+        second: DataType,
+    },
+}
+
+impl QueryError {
+    pub fn location(&self) -> &SourceLocation {
+        match self {
+            QueryError::Parse { location, .. }
+            | QueryError::Unsupported { location, .. }
+            | QueryError::UnknownTable { location, .. }
+            | QueryError::UnknownRelation { location, .. }
+            | QueryError::DuplicateRelation { location, .. }
+            | QueryError::ColumnAliasCount { location, .. }
+            | QueryError::UnknownColumn { location, .. }
+            | QueryError::AmbiguousColumn { location, .. }
+            | QueryError::UnknownPosition { location, .. }
+            | QueryError::UndefinedOperator { location, .. }
+            | QueryError::UndefinedPrefixOperator { location, .. }
+            | QueryError::UnexpectedType { location, .. }
+            | QueryError::UndefinedFunction { location, .. }
+            | QueryError::WindowFunctionWithoutOver { location, .. }
+            | QueryError::OverOnScalarFunction { location, .. }
+            | QueryError::AssignmentType { location, .. }
+            | QueryError::InsertColumnCount { location, .. }
+            | QueryError::IncompatibleTypes { location, .. }
+            | QueryError::SetOperationColumnCount { location, .. }
+            | QueryError::SubqueryColumnCount { location, .. }
+            | QueryError::UnknownParameterType { location, .. }
+            | QueryError::ConflictingParameterTypes { location, .. } => location,
+        }
+    }
+}
+
+/// A problem with a line of a file of function, operator and cast signatures.
+#[derive(Debug, Error)]
+#[error("line {line}: {message}")]
+pub struct SignatureError {
+    pub line: usize,
+    pub message: String,
+}
@@ -0,0 +1,817 @@
+//! Signatures of the functions, operators and implicit casts queries can use, which decide the
+//! types of expressions using them.
+//!
+//! Signatures are written one per line, in the same format whether they're built in or added
+//! for an extension:
+//!
+//! ```text
+//! # Functions, optionally followed by their kind and nullability
+//! lower(text) -> text
+//! count() -> int8 aggregate non-null
+//! row_number() -> int8 window non-null
+//! concat(variadic any) -> text non-null
+//! # Binary and prefix operators
+//! text || text -> text
+//! - int4 -> int4
+//! # Casts that happen implicitly, or only when storing a value in a column
+//! cast int4 -> int8
+//! cast int8 -> int4 assignment
+//! ```
+//!
+//! Types are written as they are in SQL but without spaces, so `timestamptz` rather than
+//! `timestamp with time zone`. `anyelement` stands for whatever type the arguments given for
+//! it have in common, `anynonarray` for the same but never an array, `anyarray` for an array of
+//! that type, and `any` accepts anything.
+//!
+//! Unless their nullability says otherwise, functions and operators return NULL exactly when
+//! one of their arguments is NULL, as Postgres's `STRICT` functions do, while aggregates and
+//! window functions can return NULL whatever their arguments are.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use sqlparser::{
+    ast::{ArrayElemTypeDef, DataType, Ident, ObjectName},
+    dialect::PostgreSqlDialect,
+    parser::Parser,
+};
+
+use super::{error::SignatureError, types::canonical_type};
+
+const POSTGRES: &str = include_str!("postgres.sigs");
+
+/// Signatures for the extensions that come with Postgres, keyed by the name they're created
+/// with.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("pgcrypto", include_str!("pgcrypto.sigs")),
+    ("uuid-ossp", include_str!("uuid-ossp.sigs")),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FunctionKind {
+    Scalar,
+    Aggregate,
+    Window,
+}
+
+/// When a function or operator returns NULL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nullability {
+    /// Only when one of its arguments is NULL.
+    Strict,
+    /// Never.
+    NonNull,
+    /// Possibly, whatever its arguments are.
+    Nullable,
+}
+
+/// One overload of a function or operator.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    /// The type of each parameter, in canonical form.
+    pub parameters: Vec<DataType>,
+    /// Whether the last parameter can be given any number of times, including none.
+    pub variadic: bool,
+    pub return_type: DataType,
+    pub kind: FunctionKind,
+    pub nullability: Nullability,
+}
+
+/// Where Postgres will convert a value to another type without being asked to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastContext {
+    /// Anywhere, such as in function arguments and operands.
+    Implicit,
+    /// Only when storing a value in a column.
+    Assignment,
+}
+
+/// A call to a function or operator, resolved to one of its overloads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedCall {
+    /// The type each argument is converted to, or `None` for a polymorphic parameter whose
+    /// type couldn't be worked out because no argument for it had a known type.
+    pub parameter_types: Vec<Option<DataType>>,
+    /// The return type, which is `None` if it's polymorphic and couldn't be worked out.
+    pub return_type: Option<DataType>,
+    pub kind: FunctionKind,
+    pub nullability: Nullability,
+}
+
+/// The functions, operators and implicit casts queries can use, along with the overloads of
+/// each.
+#[derive(Debug, Clone, Default)]
+pub struct FunctionCatalog {
+    functions: HashMap<String, Vec<Signature>>,
+    binary_operators: HashMap<String, Vec<Signature>>,
+    prefix_operators: HashMap<String, Vec<Signature>>,
+    casts: HashMap<(DataType, DataType), CastContext>,
+    /// The base type of each domain.
+    domains: HashMap<DataType, DataType>,
+}
+
+impl FunctionCatalog {
+    /// Create a catalog without any functions, operators or casts.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a catalog of everything built into Postgres.
+    pub fn builtin() -> Self {
+        static BUILTIN: OnceLock<FunctionCatalog> = OnceLock::new();
+        BUILTIN
+            .get_or_init(|| {
+                let mut catalog = FunctionCatalog::new();
+                catalog
+                    .add_signatures(POSTGRES)
+                    .expect("built-in signatures are valid");
+                catalog
+            })
+            .clone()
+    }
+
+    /// Add the signatures of a Postgres extension that rasql knows about, returning whether it
+    /// did. Other extensions' signatures can be added with [FunctionCatalog::add_signatures].
+    pub fn add_extension(&mut self, name: &str) -> bool {
+        let Some((_, signatures)) = EXTENSIONS
+            .iter()
+            .find(|(extension, _)| extension.eq_ignore_ascii_case(name))
+        else {
+            return false;
+        };
+        self.add_signatures(signatures)
+            .expect("extension signatures are valid");
+        true
+    }
+
+    /// Add every signature in `text`, which is in the format described in the
+    /// [module docs](self).
+    pub fn add_signatures(&mut self, text: &str) -> Result<(), SignatureError> {
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.add_signature_line(line)
+                .map_err(|message| SignatureError {
+                    line: index + 1,
+                    message,
+                })?;
+        }
+        Ok(())
+    }
+
+    pub fn add_function(&mut self, name: &str, signature: Signature) {
+        self.functions
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(signature);
+    }
+
+    /// Add a binary operator, whose signature has two parameters, or a prefix operator, whose
+    /// signature has one.
+    pub fn add_operator(&mut self, operator: &str, signature: Signature) {
+        let operators = match signature.parameters.len() {
+            1 => &mut self.prefix_operators,
+            _ => &mut self.binary_operators,
+        };
+        operators
+            .entry(operator.to_uppercase())
+            .or_default()
+            .push(signature);
+    }
+
+    pub fn add_cast(&mut self, from: DataType, to: DataType, context: CastContext) {
+        self.casts
+            .insert((canonical_type(&from), canonical_type(&to)), context);
+    }
+
+    /// Add a domain over `base`. Like Postgres, functions, operators and casts treat values of
+    /// the domain as values of `base`, and anything that can be stored in `base` can be stored
+    /// in the domain.
+    pub fn add_domain(&mut self, domain: DataType, base: DataType) {
+        self.domains
+            .insert(canonical_type(&domain), canonical_type(&base));
+    }
+
+    /// The type values of canonical type `data_type` are treated as by functions, operators and
+    /// casts. This is the base type of a domain, following domains over other domains, and
+    /// `data_type` itself for anything else.
+    pub fn base_type<'a>(&'a self, mut data_type: &'a DataType) -> &'a DataType {
+        while let Some(base) = self.domains.get(data_type) {
+            data_type = base;
+        }
+        data_type
+    }
+
+    /// Whether any function, operator or cast uses the type called `name` that isn't built into
+    /// sqlparser, which is how types that extensions create are known about.
+    pub fn mentions_type(&self, name: &str) -> bool {
+        let is_named = |data_type: &DataType| {
+            matches!(
+                data_type,
+                DataType::Custom(ObjectName(type_name), _)
+                    if matches!(type_name.as_slice(), [type_name] if type_name.value == name)
+            )
+        };
+        let signatures = self
+            .functions
+            .values()
+            .chain(self.binary_operators.values())
+            .chain(self.prefix_operators.values())
+            .flatten();
+        let mut types = signatures
+            .flat_map(|signature| signature.parameters.iter().chain([&signature.return_type]))
+            .chain(self.casts.keys().flat_map(|(from, to)| [from, to]));
+        types.any(is_named)
+    }
+
+    /// Every overload of the function `name`.
+    pub fn function(&self, name: &str) -> &[Signature] {
+        self.functions
+            .get(&name.to_lowercase())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether a value of canonical type `from` can be used where `to` is expected without an
+    /// explicit cast.
+    pub fn can_cast_implicitly(&self, from: &DataType, to: &DataType) -> bool {
+        if from == to {
+            return true;
+        }
+        let from = self.base_type(from);
+        from == to || self.casts.get(&(from.clone(), to.clone())) == Some(&CastContext::Implicit)
+    }
+
+    /// Whether a value of canonical type `from` can be stored in a column of type `to`. This
+    /// allows more than [FunctionCatalog::can_cast_implicitly], such as storing an `INT8` in an
+    /// `INT4` column, or anything in a text column.
+    pub fn can_assign(&self, from: &DataType, to: &DataType) -> bool {
+        if from == to {
+            return true;
+        }
+        let (from, to) = (self.base_type(from), self.base_type(to));
+        from == to || *to == DataType::Text || self.casts.contains_key(&(from.clone(), to.clone()))
+    }
+
+    /// The type values of canonical types `left` and `right` can both be implicitly cast to,
+    /// as happens for each branch of a `CASE`.
+    pub fn common_type(&self, left: &DataType, right: &DataType) -> Option<DataType> {
+        if self.can_cast_implicitly(left, right) {
+            Some(right.clone())
+        } else if self.can_cast_implicitly(right, left) {
+            Some(left.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Pick the overload of the function `name` that a call with arguments of canonical types
+    /// `args` uses, where `None` is an argument of unknown type.
+    pub fn resolve_function(&self, name: &str, args: &[Option<DataType>]) -> Option<ResolvedCall> {
+        self.resolve_overload(self.function(name), args)
+    }
+
+    /// Pick the overload of a binary operator used with operands of canonical types `left` and
+    /// `right`, where `None` is an operand of unknown type.
+    pub fn resolve_binary_operator(
+        &self,
+        operator: &str,
+        left: Option<&DataType>,
+        right: Option<&DataType>,
+    ) -> Option<ResolvedCall> {
+        let candidates = self
+            .binary_operators
+            .get(&operator.to_uppercase())
+            .map_or(&[][..], Vec::as_slice);
+        // Like Postgres, first try assuming an operand of unknown type is the same type as the
+        // other operand, or its base type if it's a domain
+        if let (Some(known), None) | (None, Some(known)) = (left, right) {
+            let known = Some(self.base_type(known).clone());
+            if let Some(call) = self.resolve_overload(candidates, &[known.clone(), known]) {
+                return Some(call);
+            }
+        }
+        self.resolve_overload(candidates, &[left.cloned(), right.cloned()])
+    }
+
+    /// Pick the overload of a prefix operator used with an operand of canonical type
+    /// `operand`, where `None` is an operand of unknown type.
+    pub fn resolve_prefix_operator(
+        &self,
+        operator: &str,
+        operand: Option<&DataType>,
+    ) -> Option<ResolvedCall> {
+        let candidates = self
+            .prefix_operators
+            .get(&operator.to_uppercase())
+            .map_or(&[][..], Vec::as_slice);
+        self.resolve_overload(candidates, &[operand.cloned()])
+    }
+
+    /// Pick the overload from `candidates` a call with arguments of canonical types `args` uses,
+    /// roughly following the rules Postgres has. Overloads whose parameters exactly match the
+    /// most arguments are preferred, then those that convert the most arguments to a preferred
+    /// type like `float8` or `text`. If that still leaves several, the call is ambiguous.
+    ///
+    /// This is for resolving calls against overloads from elsewhere too, like the functions
+    /// created by a schema.
+    pub fn resolve_overload(
+        &self,
+        candidates: &[Signature],
+        args: &[Option<DataType>],
+    ) -> Option<ResolvedCall> {
+        let mut best: Option<((usize, usize), ResolvedCall)> = None;
+        let mut ambiguous = false;
+        for signature in candidates {
+            let Some((call, score)) = self.match_signature(signature, args) else {
+                continue;
+            };
+            match &best {
+                Some((best_score, best_call)) if score == *best_score => {
+                    ambiguous |= call != *best_call;
+                }
+                Some((best_score, _)) if score < *best_score => {}
+                _ => {
+                    best = Some((score, call));
+                    ambiguous = false;
+                }
+            }
+        }
+        best.filter(|_| !ambiguous).map(|(_, call)| call)
+    }
+
+    /// Check a call with arguments of types `args` can use `signature`, returning what it
+    /// resolves to and how good a match it is.
+    fn match_signature(
+        &self,
+        signature: &Signature,
+        args: &[Option<DataType>],
+    ) -> Option<(ResolvedCall, (usize, usize))> {
+        let parameters = signature.parameters_for(args.len())?;
+
+        // Every argument for a polymorphic parameter has to agree on one type
+        let mut element_type: Option<DataType> = None;
+        for (parameter, arg) in parameters.iter().zip(args) {
+            let Some(arg) = arg else {
+                continue;
+            };
+            let arg = self.base_type(arg);
+            let arg_element_type = match Polymorphic::of(parameter) {
+                Some(Polymorphic::NonArray) if matches!(arg, DataType::Array(_)) => return None,
+                Some(Polymorphic::Element | Polymorphic::NonArray) => arg.clone(),
+                Some(Polymorphic::Array) => match arg {
+                    DataType::Array(ArrayElemTypeDef::SquareBracket(element, _)) => {
+                        element.as_ref().clone()
+                    }
+                    _ => return None,
+                },
+                Some(Polymorphic::Any) | None => continue,
+            };
+            element_type = Some(match element_type {
+                Some(element_type) => self.common_type(&element_type, &arg_element_type)?,
+                None => arg_element_type,
+            });
+        }
+        let substitute = |r#type: &DataType, arg: Option<&DataType>| match Polymorphic::of(r#type) {
+            Some(Polymorphic::Element | Polymorphic::NonArray) => element_type.clone(),
+            Some(Polymorphic::Array) => element_type.clone().map(array_of),
+            Some(Polymorphic::Any) => arg.cloned(),
+            None => Some(r#type.clone()),
+        };
+
+        let mut exact = 0;
+        let mut preferred = 0;
+        let mut parameter_types = vec![];
+        for (parameter, arg) in parameters.iter().zip(args) {
+            let parameter_type = substitute(parameter, arg.as_ref());
+            match (arg, &parameter_type) {
+                (Some(arg), Some(parameter_type)) if arg == parameter_type => exact += 1,
+                (Some(arg), Some(parameter_type))
+                    if !self.can_cast_implicitly(arg, parameter_type) =>
+                {
+                    return None
+                }
+                (_, Some(parameter_type)) if is_preferred(parameter_type) => preferred += 1,
+                _ => {}
+            }
+            parameter_types.push(parameter_type);
+        }
+        Some((
+            ResolvedCall {
+                parameter_types,
+                return_type: substitute(&signature.return_type, None),
+                kind: signature.kind,
+                nullability: signature.nullability,
+            },
+            (exact, preferred),
+        ))
+    }
+
+    fn add_signature_line(&mut self, line: &str) -> Result<(), String> {
+        let (left, right) = line
+            .rsplit_once(" -> ")
+            .ok_or("expected ` -> ` before the return type")?;
+        let mut right_words = right.split_whitespace();
+        let return_type = parse_type(right_words.next().ok_or("expected a return type")?)?;
+
+        if let Some(from) = left.strip_prefix("cast ") {
+            let context = match right_words.next() {
+                None => CastContext::Implicit,
+                Some("assignment") => CastContext::Assignment,
+                Some(word) => return Err(format!("unexpected `{word}` after a cast")),
+            };
+            self.add_cast(parse_type(from.trim())?, return_type, context);
+            return Ok(());
+        }
+
+        let mut kind = FunctionKind::Scalar;
+        let mut nullability = None;
+        for word in right_words {
+            match word {
+                "aggregate" => kind = FunctionKind::Aggregate,
+                "window" => kind = FunctionKind::Window,
+                "non-null" => nullability = Some(Nullability::NonNull),
+                "nullable" => nullability = Some(Nullability::Nullable),
+                word => return Err(format!("unexpected `{word}` after the return type")),
+            }
+        }
+        let nullability = nullability.unwrap_or(match kind {
+            FunctionKind::Scalar => Nullability::Strict,
+            FunctionKind::Aggregate | FunctionKind::Window => Nullability::Nullable,
+        });
+
+        let is_function = left
+            .split_once('(')
+            .is_some_and(|(name, _)| is_function_name(name));
+        if is_function {
+            let (name, parameters) = left
+                .strip_suffix(')')
+                .and_then(|left| left.split_once('('))
+                .ok_or("expected `)` after the parameters")?;
+            let mut variadic = false;
+            let parameters = parameters
+                .split(',')
+                .map(str::trim)
+                .filter(|parameter| !parameter.is_empty())
+                .map(|parameter| match parameter.strip_prefix("variadic ") {
+                    Some(parameter) => {
+                        variadic = true;
+                        parse_type(parameter.trim())
+                    }
+                    None if variadic => Err("only the last parameter can be variadic".to_owned()),
+                    None => parse_type(parameter),
+                })
+                .collect::<Result<_, _>>()?;
+            self.add_function(
+                name,
+                Signature {
+                    parameters,
+                    variadic,
+                    return_type,
+                    kind,
+                    nullability,
+                },
+            );
+            return Ok(());
+        }
+
+        let words: Vec<_> = left.split_whitespace().collect();
+        let (operator, parameters) = match words.as_slice() {
+            [operator, operand] => (*operator, vec![parse_type(operand)?]),
+            [left, operator, right] => (*operator, vec![parse_type(left)?, parse_type(right)?]),
+            _ => return Err("expected a function, operator or cast".to_owned()),
+        };
+        self.add_operator(
+            operator,
+            Signature {
+                parameters,
+                variadic: false,
+                return_type,
+                kind,
+                nullability,
+            },
+        );
+        Ok(())
+    }
+}
+
+impl Signature {
+    /// The parameters a call with `arity` arguments fills, repeating a variadic parameter as
+    /// many times as needed.
+    fn parameters_for(&self, arity: usize) -> Option<Vec<&DataType>> {
+        if self.variadic {
+            let (variadic, fixed) = self.parameters.split_last()?;
+            (arity >= fixed.len()).then(|| {
+                fixed
+                    .iter()
+                    .chain(std::iter::repeat_n(variadic, arity - fixed.len()))
+                    .collect()
+            })
+        } else {
+            (arity == self.parameters.len()).then(|| self.parameters.iter().collect())
+        }
+    }
+}
+
+enum Polymorphic {
+    Element,
+    NonArray,
+    Array,
+    Any,
+}
+
+impl Polymorphic {
+    fn of(data_type: &DataType) -> Option<Self> {
+        let DataType::Custom(ObjectName(name), modifiers) = data_type else {
+            return None;
+        };
+        match (name.as_slice(), modifiers.as_slice()) {
+            ([name], []) => match name.value.as_str() {
+                "anyelement" => Some(Self::Element),
+                "anynonarray" => Some(Self::NonArray),
+                "anyarray" => Some(Self::Array),
+                "any" => Some(Self::Any),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Whether Postgres prefers converting values to `data_type` over other types in its category.
+fn is_preferred(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Float8 | DataType::Text | DataType::Boolean | DataType::Interval
+    ) || *data_type
+        == canonical_type(&DataType::Custom(
+            ObjectName(vec![Ident::new("timestamptz")]),
+            vec![],
+        ))
+}
+
+fn is_function_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_' || char == '.')
+}
+
+/// The canonical type of an array of `element_type`, which must itself be canonical.
+pub(crate) fn array_of(element_type: DataType) -> DataType {
+    DataType::Array(ArrayElemTypeDef::SquareBracket(
+        Box::new(element_type),
+        None,
+    ))
+}
+
+/// Parse a type in a signature, returning it in canonical form.
+fn parse_type(text: &str) -> Result<DataType, String> {
+    // These are keywords, or would be mistaken for a type with modifiers
+    if matches!(text, "any" | "anyelement" | "anynonarray" | "anyarray") {
+        return Ok(DataType::Custom(ObjectName(vec![Ident::new(text)]), vec![]));
+    }
+    let data_type = Parser::new(&PostgreSqlDialect {})
+        .try_with_sql(text)
+        .and_then(|mut parser| parser.parse_data_type())
+        .map_err(|error| format!("invalid type `{text}`: {error}"))?;
+    Ok(canonical_type(&data_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog(text: &str) -> FunctionCatalog {
+        let mut catalog = FunctionCatalog::new();
+        catalog.add_signatures(text).unwrap();
+        catalog
+    }
+
+    fn error(text: &str) -> SignatureError {
+        FunctionCatalog::new().add_signatures(text).unwrap_err()
+    }
+
+    fn int4_array() -> DataType {
+        array_of(DataType::Int4(None))
+    }
+
+    #[test]
+    fn functions() {
+        let catalog = catalog(
+            "lower(text) -> text\n\
+            count() -> int8 aggregate non-null\n\
+            row_number() -> int8 window non-null\n\
+            nullif(int4, int4) -> int4 nullable\n\
+            pg_catalog.upper(text) -> text",
+        );
+        let [lower] = catalog.function("LOWER") else {
+            panic!("expected one overload of lower");
+        };
+        assert_eq!(lower.parameters, [DataType::Text]);
+        assert_eq!(lower.return_type, DataType::Text);
+        assert!(!lower.variadic);
+        assert_eq!(lower.kind, FunctionKind::Scalar);
+        assert_eq!(lower.nullability, Nullability::Strict);
+
+        let [count] = catalog.function("count") else {
+            panic!("expected one overload of count");
+        };
+        assert!(count.parameters.is_empty());
+        assert_eq!(count.kind, FunctionKind::Aggregate);
+        assert_eq!(count.nullability, Nullability::NonNull);
+
+        let [row_number] = catalog.function("row_number") else {
+            panic!("expected one overload of row_number");
+        };
+        assert_eq!(row_number.kind, FunctionKind::Window);
+
+        let [nullif] = catalog.function("nullif") else {
+            panic!("expected one overload of nullif");
+        };
+        assert_eq!(
+            nullif.parameters,
+            [DataType::Int4(None), DataType::Int4(None)]
+        );
+        assert_eq!(nullif.nullability, Nullability::Nullable);
+
+        assert_eq!(catalog.function("pg_catalog.upper").len(), 1);
+    }
+
+    #[test]
+    fn types_are_canonical() {
+        let catalog = catalog("f(integer, varchar(10), timestamptz) -> float");
+        let [f] = catalog.function("f") else {
+            panic!("expected one overload of f");
+        };
+        assert_eq!(
+            f.parameters,
+            [
+                DataType::Int4(None),
+                DataType::Text,
+                DataType::Timestamp(None, sqlparser::ast::TimezoneInfo::WithTimeZone),
+            ]
+        );
+        assert_eq!(f.return_type, DataType::Float8);
+    }
+
+    #[test]
+    fn variadic_functions() {
+        let catalog = catalog(
+            "concat(variadic any) -> text non-null\n\
+            format(text, variadic any) -> text",
+        );
+        let [format] = catalog.function("format") else {
+            panic!("expected one overload of format");
+        };
+        assert!(format.variadic);
+        assert_eq!(format.parameters_for(0), None);
+        assert_eq!(format.parameters_for(1).unwrap().len(), 1);
+        assert_eq!(format.parameters_for(4).unwrap().len(), 4);
+
+        let call = catalog
+            .resolve_function(
+                "concat",
+                &[Some(DataType::Text), Some(DataType::Int4(None)), None],
+            )
+            .unwrap();
+        assert_eq!(call.parameter_types.len(), 3);
+        assert_eq!(call.return_type, Some(DataType::Text));
+        assert_eq!(call.nullability, Nullability::NonNull);
+    }
+
+    #[test]
+    fn polymorphic_functions() {
+        let catalog = catalog(
+            "array_append(anyarray, anyelement) -> anyarray\n\
+            coalesce(variadic anyelement) -> anyelement nullable",
+        );
+        let call = catalog
+            .resolve_function("array_append", &[Some(int4_array()), None])
+            .unwrap();
+        assert_eq!(
+            call.parameter_types,
+            [Some(int4_array()), Some(DataType::Int4(None))]
+        );
+        assert_eq!(call.return_type, Some(int4_array()));
+
+        let call = catalog
+            .resolve_function("coalesce", &[None, Some(DataType::Int8(None))])
+            .unwrap();
+        assert_eq!(call.return_type, Some(DataType::Int8(None)));
+
+        // Without any argument of a known type there's nothing to decide the type from
+        let call = catalog.resolve_function("coalesce", &[None]).unwrap();
+        assert_eq!(call.return_type, None);
+    }
+
+    #[test]
+    fn operators() {
+        let catalog = catalog(
+            "text || text -> text\n\
+            int4 + int4 -> int4\n\
+            - int4 -> int4\n\
+            text like text -> bool",
+        );
+        let call = catalog
+            .resolve_binary_operator("||", Some(&DataType::Text), None)
+            .unwrap();
+        assert_eq!(
+            call.parameter_types,
+            [Some(DataType::Text), Some(DataType::Text)]
+        );
+        let call = catalog
+            .resolve_prefix_operator("-", Some(&DataType::Int4(None)))
+            .unwrap();
+        assert_eq!(call.return_type, Some(DataType::Int4(None)));
+        assert!(catalog
+            .resolve_binary_operator("LIKE", Some(&DataType::Text), Some(&DataType::Text))
+            .is_some());
+        assert!(catalog
+            .resolve_prefix_operator("+", Some(&DataType::Int4(None)))
+            .is_none());
+    }
+
+    #[test]
+    fn casts() {
+        let catalog = catalog(
+            "cast int4 -> int8\n\
+            cast int8 -> int4 assignment",
+        );
+        let (int4, int8) = (DataType::Int4(None), DataType::Int8(None));
+        assert!(catalog.can_cast_implicitly(&int4, &int8));
+        assert!(!catalog.can_cast_implicitly(&int8, &int4));
+        assert!(catalog.can_assign(&int8, &int4));
+        assert_eq!(catalog.common_type(&int4, &int8), Some(int8));
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let catalog = catalog("# lower(text) -> text\n\n   \n  upper(text) -> text  \n");
+        assert!(catalog.function("lower").is_empty());
+        assert_eq!(catalog.function("upper").len(), 1);
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let cases = [
+            ("lower(text)", 1, "expected ` -> ` before the return type"),
+            (
+                "# comment\n\nlower(text) ->",
+                3,
+                "expected ` -> ` before the return type",
+            ),
+            ("f(text -> text", 1, "expected `)` after the parameters"),
+            (
+                "f(text) -> text sometimes",
+                1,
+                "unexpected `sometimes` after the return type",
+            ),
+            (
+                "cast int4 -> int8 explicit",
+                1,
+                "unexpected `explicit` after a cast",
+            ),
+            (
+                "f(variadic text, int4) -> text",
+                1,
+                "only the last parameter can be variadic",
+            ),
+            (
+                "a b c d -> text",
+                1,
+                "expected a function, operator or cast",
+            ),
+        ];
+        for (text, line, message) in cases {
+            let error = error(text);
+            assert_eq!(
+                (error.line, error.message.as_str()),
+                (line, message),
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_types() {
+        let error = error("lower(text) -> text\nf(text) -> 'nope'");
+        assert_eq!(error.line, 2);
+        assert!(
+            error.message.starts_with("invalid type `'nope'`"),
+            "{}",
+            error.message
+        );
+        assert_eq!(error.to_string().split(':').next(), Some("line 2"));
+    }
+
+    #[test]
+    fn builtin_and_extension_signatures_parse() {
+        let mut catalog = FunctionCatalog::builtin();
+        assert!(!catalog.function("lower").is_empty());
+        for (extension, _) in EXTENSIONS {
+            assert!(catalog.add_extension(extension), "{extension}");
+        }
+        assert!(!catalog.add_extension("not_an_extension"));
+    }
+}
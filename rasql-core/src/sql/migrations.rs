@@ -0,0 +1,411 @@
+//! Loading a schema from a directory of migration files.
+//!
+//! The layouts understood are:
+//! - Numbered files, e.g. `0001_init.sql`, `0002_users.sql`. sqlx style `0001_init.up.sql` files
+//!   also work, with the matching `.down.sql` files ignored.
+//! - Flyway style files, e.g. `V1__init.sql`, `V1.1__users.sql`. Repeatable `R__views.sql`
+//!   migrations are applied after every versioned migration, and undo migrations are ignored.
+//! - Diesel style folders, e.g. `2024-01-01-000000_init/up.sql`.
+
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+};
+
+use sqlparser::tokenizer::{Location, Span};
+
+use super::{parse_schema_sql, Catalog, SchemaAnalyser, SchemaError, SourceLocation};
+
+/// A migration file found by [discover_migrations].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    pub version: MigrationVersion,
+    /// The name the version was read from, which is the file name without its extension, or
+    /// the folder name for Diesel style migrations.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl PartialOrd for Migration {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Migration {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.version
+            .cmp(&other.version)
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+/// Where a migration belongs in the order migrations are applied.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MigrationVersion {
+    /// Each part of a version number, so `V1.10__x` becomes `[1, 10]` and sorts after
+    /// `V1.9__x`. Trailing zeros are dropped, as Flyway treats `V1.0` as `V1`. Numbered and
+    /// Diesel style migrations only have one part.
+    Versioned(Vec<u128>),
+    /// A Flyway repeatable migration, which is applied after all versioned migrations.
+    Repeatable,
+}
+
+impl MigrationVersion {
+    /// Work out the version of a migration from its name, returning `None` if the name doesn't
+    /// follow any of the supported layouts.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.starts_with("R__") {
+            return Some(MigrationVersion::Repeatable);
+        }
+        if let Some((version, _)) = name
+            .strip_prefix('V')
+            .and_then(|name| name.split_once("__"))
+        {
+            let mut parts: Vec<_> = version
+                .split(['.', '_'])
+                .map(|part| part.parse().ok())
+                .collect::<Option<_>>()?;
+            while parts.len() > 1 && parts.last() == Some(&0) {
+                parts.pop();
+            }
+            return Some(MigrationVersion::Versioned(parts));
+        }
+        // Diesel puts dashes in its timestamps, which aren't significant
+        let version: String = name
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == '-')
+            .filter(|c| *c != '-')
+            .collect();
+        version
+            .parse()
+            .ok()
+            .map(|version| MigrationVersion::Versioned(vec![version]))
+    }
+}
+
+/// Find every migration in `dir`, in the order they should be applied.
+pub fn discover_migrations(dir: &Path) -> Result<Vec<Migration>, Vec<SchemaError>> {
+    let io_error = |path: &Path, source| SchemaError::Io {
+        location: SourceLocation {
+            file: Some(path.to_path_buf()),
+            span: Span::empty(),
+        },
+        source,
+    };
+
+    let mut migrations = vec![];
+    let mut errors = vec![];
+    let entries = std::fs::read_dir(dir).map_err(|error| vec![io_error(dir, error)])?;
+    for entry in entries {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(error) => {
+                errors.push(io_error(dir, error));
+                continue;
+            }
+        };
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if file_name.starts_with('.') {
+            continue;
+        }
+
+        let (name, path) = if path.is_dir() {
+            let up = path.join("up.sql");
+            if !up.is_file() {
+                continue;
+            }
+            (file_name.to_owned(), up)
+        } else if let Some(name) = file_name.strip_suffix(".sql") {
+            if name.ends_with(".down") || (name.starts_with('U') && name.contains("__")) {
+                continue;
+            }
+            let name = name.strip_suffix(".up").unwrap_or(name);
+            (name.to_owned(), path.clone())
+        } else {
+            continue;
+        };
+
+        match MigrationVersion::from_name(&name) {
+            Some(version) => migrations.push(Migration {
+                version,
+                name,
+                path,
+            }),
+            None => errors.push(SchemaError::UnversionedMigration {
+                location: SourceLocation {
+                    file: Some(path),
+                    span: Span::empty(),
+                },
+            }),
+        }
+    }
+
+    // Versions are compared as numbers, so `0001_a.sql` and `1_b.sql` clash, and there'd be
+    // no telling which to apply first
+    migrations.sort();
+    for pair in migrations.windows(2) {
+        if let [first, second @ Migration {
+            version: MigrationVersion::Versioned(_),
+            ..
+        }] = pair
+        {
+            if first.version == second.version {
+                errors.push(SchemaError::DuplicateMigrationVersion {
+                    location: SourceLocation {
+                        file: Some(second.path.clone()),
+                        span: Span::empty(),
+                    },
+                    other: first.path.clone(),
+                });
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(migrations)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Analyse every migration in `dir` in order, building up the [Catalog] of the schema they
+/// produce.
+pub fn load_migrations(dir: &Path) -> Result<Catalog, Vec<SchemaError>> {
+    let mut analyser = SchemaAnalyser::new();
+    for migration in discover_migrations(dir)? {
+        analyser.analyse_file(&migration.path);
+    }
+    analyser.finish()
+}
+
+impl SchemaAnalyser {
+    /// Parse the Postgres SQL file at `path` and analyse its statements.
+    pub fn analyse_file(&mut self, path: &Path) {
+        let location = SourceLocation {
+            file: Some(path.to_path_buf()),
+            span: Span::empty(),
+        };
+        let sql = match std::fs::read_to_string(path) {
+            Ok(sql) => sql,
+            Err(source) => return self.push_error(SchemaError::Io { location, source }),
+        };
+        match parse_schema_sql(&sql) {
+            Ok(statements) => self.analyse_statements(Some(path), statements),
+            Err(error) => {
+                let message = error.to_string();
+                let (message, span) = split_parser_error(&message);
+                self.push_error(SchemaError::Parse {
+                    location: SourceLocation { span, ..location },
+                    message: message.to_owned(),
+                })
+            }
+        }
+    }
+}
+
+/// sqlparser only reports where an error happened in its message, so pull that back out to
+/// report it the same way as every other error.
+fn split_parser_error(message: &str) -> (&str, Span) {
+    let parsed = message
+        .rsplit_once(" at Line: ")
+        .and_then(|(message, position)| {
+            let (line, column) = position.split_once(", Column: ")?;
+            let start = Location::new(line.parse().ok()?, column.parse().ok()?);
+            Some((message, Span::new(start, start)))
+        });
+    parsed.unwrap_or((message, Span::empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versioned(parts: &[u128]) -> Option<MigrationVersion> {
+        Some(MigrationVersion::Versioned(parts.to_vec()))
+    }
+
+    /// Create a directory of empty migration files called `files`, unique to the test `name`.
+    fn migrations_dir(name: &str, files: &[&str]) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("rasql-migrations-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in files {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        dir
+    }
+
+    fn migration_names(migrations: &[Migration]) -> Vec<&str> {
+        migrations
+            .iter()
+            .map(|migration| migration.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn numbered_versions_ignore_leading_zeros() {
+        assert_eq!(MigrationVersion::from_name("0001_init"), versioned(&[1]));
+        assert_eq!(MigrationVersion::from_name("1_init"), versioned(&[1]));
+        assert_eq!(MigrationVersion::from_name("0010_users"), versioned(&[10]));
+        assert_eq!(MigrationVersion::from_name("0002"), versioned(&[2]));
+    }
+
+    #[test]
+    fn flyway_versions() {
+        assert_eq!(MigrationVersion::from_name("V1__init"), versioned(&[1]));
+        assert_eq!(MigrationVersion::from_name("V1.10__x"), versioned(&[1, 10]));
+        assert_eq!(MigrationVersion::from_name("V1_2__x"), versioned(&[1, 2]));
+        assert_eq!(MigrationVersion::from_name("V1.0__x"), versioned(&[1]));
+        assert_eq!(MigrationVersion::from_name("V0__x"), versioned(&[0]));
+        assert_eq!(
+            MigrationVersion::from_name("R__views"),
+            Some(MigrationVersion::Repeatable)
+        );
+        assert!(MigrationVersion::from_name("V1.10__x") > MigrationVersion::from_name("V1.9__x"));
+    }
+
+    #[test]
+    fn diesel_versions() {
+        assert_eq!(
+            MigrationVersion::from_name("2024-01-01-000000_init"),
+            versioned(&[20240101000000])
+        );
+    }
+
+    #[test]
+    fn missing_or_invalid_prefixes() {
+        for name in [
+            "init",
+            "_init",
+            "V__init",
+            "Vx__init",
+            "V1.x__init",
+            "v1__init",
+        ] {
+            assert_eq!(MigrationVersion::from_name(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn migrations_are_ordered_by_version() {
+        let dir = migrations_dir(
+            "ordered",
+            &[
+                "0010_c.sql",
+                "0002_b.sql",
+                "1_a.sql",
+                "0003_b.down.sql",
+                "0003_b.up.sql",
+                "notes.txt",
+                ".hidden.sql",
+            ],
+        );
+        let migrations = discover_migrations(&dir).unwrap();
+        assert_eq!(
+            migration_names(&migrations),
+            ["1_a", "0002_b", "0003_b", "0010_c"]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn repeatable_migrations_come_last() {
+        let dir = migrations_dir(
+            "repeatable",
+            &[
+                "R__views.sql",
+                "V2__b.sql",
+                "R__functions.sql",
+                "V1__a.sql",
+                "U2__b.sql",
+            ],
+        );
+        let migrations = discover_migrations(&dir).unwrap();
+        assert_eq!(
+            migration_names(&migrations),
+            ["V1__a", "V2__b", "R__functions", "R__views"]
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn unversioned_migrations_are_errors() {
+        let dir = migrations_dir("unversioned", &["0001_a.sql", "init.sql"]);
+        let errors = discover_migrations(&dir).unwrap_err();
+        assert!(
+            matches!(
+                errors.as_slice(),
+                [SchemaError::UnversionedMigration { location }]
+                    if location.file == Some(dir.join("init.sql"))
+            ),
+            "{errors:?}"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn duplicate_versions_are_errors() {
+        let dir = migrations_dir("duplicate", &["0001_a.sql", "1_b.sql", "0002_c.sql"]);
+        let errors = discover_migrations(&dir).unwrap_err();
+        assert!(
+            matches!(
+                errors.as_slice(),
+                [SchemaError::DuplicateMigrationVersion { location, other }]
+                    if location.file == Some(dir.join("1_b.sql"))
+                        && *other == dir.join("0001_a.sql")
+            ),
+            "{errors:?}"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn parser_errors_are_split_from_their_position() {
+        let (message, span) =
+            split_parser_error("sql parser error: Expected: ), found: ; at Line: 3, Column: 14");
+        assert_eq!(message, "sql parser error: Expected: ), found: ;");
+        assert_eq!(span.start, Location::new(3, 14));
+        assert_eq!(span.end, span.start);
+    }
+
+    #[test]
+    fn parser_errors_without_a_position() {
+        for message in [
+            "sql parser error: Unexpected EOF",
+            "sql parser error: at Line: x, Column: 1",
+            "sql parser error: at Line: 1",
+        ] {
+            assert_eq!(split_parser_error(message), (message, Span::empty()));
+        }
+    }
+
+    #[test]
+    fn parser_errors_in_migrations_are_located() {
+        let message = parse_schema_sql("CREATE TABLE t (\n    id int,,\n);")
+            .unwrap_err()
+            .to_string();
+        let (message, span) = split_parser_error(&message);
+        assert!(!message.contains(" at Line: "), "{message}");
+        assert_eq!(span.start.line, 2);
+    }
+
+    #[test]
+    fn duplicate_flyway_versions_are_errors() {
+        let dir = migrations_dir("duplicate-flyway", &["V1__a.sql", "V1.0__b.sql"]);
+        let errors = discover_migrations(&dir).unwrap_err();
+        assert!(
+            matches!(
+                errors.as_slice(),
+                [SchemaError::DuplicateMigrationVersion { .. }]
+            ),
+            "{errors:?}"
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
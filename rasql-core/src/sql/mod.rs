@@ -0,0 +1,1143 @@
+// Errors carry enough context to be reported on their own, which makes them larger than clippy
+// would like. They are only produced on the cold path of analysis, so boxing them isn't worth it.
+#![allow(clippy::result_large_err)]
+
+mod alter;
+mod alter_type;
+pub mod catalog;
+mod create_function;
+mod domain;
+mod drop;
+pub mod error;
+pub mod functions;
+pub mod migrations;
+pub mod query;
+pub mod types;
+
+use std::path::{Path, PathBuf};
+
+use indexmap::IndexMap;
+use sqlparser::{
+    ast::{
+        ArrayElemTypeDef, ColumnDef, ColumnOption, DataType, Expr, Ident, ObjectName, SchemaName,
+        Spanned, Statement, TableConstraint,
+    },
+    dialect::PostgreSqlDialect,
+    keywords::Keyword,
+    parser::{Parser, ParserError},
+    tokenizer::{Span, Token, Tokenizer},
+};
+
+pub use alter_type::{AlterType, AlterTypeOperation, LabelPosition};
+pub use catalog::Catalog;
+pub use domain::{CreateDomain, DomainConstraint, DropDomain};
+pub use error::{ObjectKind, QueryError, SchemaError, SignatureError, SourceLocation};
+pub use functions::FunctionCatalog;
+pub use migrations::load_migrations;
+
+/// Parse Postgres DDL into statements that can be given to [parse_sql_schema].
+///
+/// This understands some syntax that parsing with sqlparser directly doesn't, namely
+/// `RETURNS SETOF` and `RETURNS TABLE` columns of any type in `CREATE FUNCTION`,
+/// `ALTER TYPE`, `CREATE DOMAIN` and `DROP DOMAIN`.
+pub fn parse_schema_sql(sql: &str) -> Result<Vec<SchemaStatement>, ParserError> {
+    let tokens = Tokenizer::new(&PostgreSqlDialect {}, sql).tokenize_with_location()?;
+    let mut parser = Parser::new(&PostgreSqlDialect {})
+        .with_tokens_with_locations(create_function::rewrite_function_returns(tokens));
+
+    // The same as Parser::parse_statements, but with the statements sqlparser can't parse
+    // parsed here
+    let mut statements = vec![];
+    let mut expecting_statement_delimiter = false;
+    loop {
+        while parser.consume_token(&Token::SemiColon) {
+            expecting_statement_delimiter = false;
+        }
+        if parser.peek_token().token == Token::EOF {
+            break;
+        }
+        if expecting_statement_delimiter {
+            return parser.expected("end of statement", parser.peek_token());
+        }
+        let statement = if parser.parse_keywords(&[Keyword::ALTER, Keyword::TYPE]) {
+            SchemaStatement::AlterType(alter_type::parse_alter_type(&mut parser)?)
+        } else if domain::parse_domain_keyword(&mut parser, Keyword::CREATE) {
+            SchemaStatement::CreateDomain(domain::parse_create_domain(&mut parser)?)
+        } else if domain::parse_domain_keyword(&mut parser, Keyword::DROP) {
+            SchemaStatement::DropDomain(domain::parse_drop_domain(&mut parser)?)
+        } else {
+            SchemaStatement::Statement(parser.parse_statement()?)
+        };
+        statements.push(statement);
+        expecting_statement_delimiter = true;
+    }
+    Ok(statements)
+}
+
+/// A statement that [parse_schema_sql] parses, which is one that sqlparser understands or one
+/// that rasql parses itself.
+// Nearly every statement is a `Statement`, so boxing it would only add an allocation
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaStatement {
+    Statement(Statement),
+    AlterType(AlterType),
+    CreateDomain(CreateDomain),
+    DropDomain(DropDomain),
+}
+
+impl From<Statement> for SchemaStatement {
+    fn from(statement: Statement) -> Self {
+        SchemaStatement::Statement(statement)
+    }
+}
+
+/// Analyse a sequence of DDL statements, building up a [Catalog] of the resulting database
+/// schema.
+///
+/// All statements are analysed even if some fail, so that every problem can be reported at once.
+pub fn parse_sql_schema(
+    sql_statements: impl IntoIterator<
+        Item = impl TryInto<SchemaStatement, Error = impl std::fmt::Display>,
+    >,
+) -> Result<Catalog, Vec<SchemaError>> {
+    let mut analyser = SchemaAnalyser::new();
+    analyser.analyse_statements(None, sql_statements);
+    analyser.finish()
+}
+
+/// Incrementally builds a [Catalog] from DDL statements that may come from several files.
+#[derive(Default)]
+pub struct SchemaAnalyser {
+    catalog: Catalog,
+    errors: Vec<SchemaError>,
+    file: Option<PathBuf>,
+    /// Whether an extension rasql doesn't know the types of has been created, see
+    /// [SchemaAnalyser::resolve_type].
+    unknown_extensions: bool,
+}
+
+impl SchemaAnalyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Analyse `sql_statements`, attributing any errors to `file`.
+    pub fn analyse_statements(
+        &mut self,
+        file: Option<&Path>,
+        sql_statements: impl IntoIterator<
+            Item = impl TryInto<SchemaStatement, Error = impl std::fmt::Display>,
+        >,
+    ) {
+        self.file = file.map(Path::to_path_buf);
+        for statement in sql_statements {
+            match statement.try_into() {
+                Ok(SchemaStatement::Statement(statement)) => self.analyse_statement(statement),
+                Ok(statement) => {
+                    let result = match statement {
+                        SchemaStatement::Statement(_) => unreachable!(),
+                        SchemaStatement::AlterType(alter_type) => self.alter_type(alter_type),
+                        SchemaStatement::CreateDomain(create_domain) => {
+                            self.create_domain(create_domain)
+                        }
+                        SchemaStatement::DropDomain(drop_domain) => self.drop_domain(drop_domain),
+                    };
+                    if let Err(error) = result {
+                        self.errors.push(error);
+                    }
+                }
+                Err(error) => self.errors.push(SchemaError::Parse {
+                    location: self.location(Span::empty()),
+                    message: error.to_string(),
+                }),
+            }
+        }
+    }
+
+    pub fn push_error(&mut self, error: SchemaError) {
+        self.errors.push(error);
+    }
+
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    /// Finish analysis, returning the [Catalog] if no errors were found.
+    pub fn finish(self) -> Result<Catalog, Vec<SchemaError>> {
+        if self.errors.is_empty() {
+            Ok(self.catalog)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    pub(crate) fn location(&self, span: Span) -> SourceLocation {
+        SourceLocation {
+            file: self.file.clone(),
+            span,
+        }
+    }
+
+    fn analyse_statement(&mut self, statement: sqlparser::ast::Statement) {
+        let result = match statement {
+            sqlparser::ast::Statement::CreateSchema {
+                schema_name,
+                if_not_exists,
+            } => {
+                let schema_name = match schema_name {
+                    SchemaName::Simple(name) | SchemaName::NamedAuthorization(name, _) => {
+                        name.0.last().unwrap().clone()
+                    }
+                    SchemaName::UnnamedAuthorization(authorization) => authorization,
+                };
+                if self.catalog.schema(&schema_name).is_some() && !if_not_exists {
+                    Err(SchemaError::DuplicateObject {
+                        location: self.location(schema_name.span),
+                        kind: ObjectKind::Schema,
+                        name: ObjectName(vec![normalise_ident(&schema_name)]),
+                    })
+                } else {
+                    self.catalog.schema_entry(&schema_name);
+                    Ok(())
+                }
+            }
+            sqlparser::ast::Statement::CreateTable(create_table) => self.create_table(create_table),
+            sqlparser::ast::Statement::AlterTable {
+                name,
+                if_exists,
+                operations,
+                ..
+            } => self.alter_table(name, if_exists, operations),
+            sqlparser::ast::Statement::CreateView {
+                or_replace,
+                materialized,
+                name,
+                columns,
+                query,
+                if_not_exists,
+                ..
+            } => self.create_view(
+                name,
+                columns,
+                query,
+                materialized,
+                or_replace,
+                if_not_exists,
+            ),
+            sqlparser::ast::Statement::CreateIndex(create_index) => self.create_index(create_index),
+            sqlparser::ast::Statement::Drop {
+                object_type,
+                if_exists,
+                names,
+                cascade,
+                ..
+            } => self.drop(object_type, if_exists, names, cascade),
+            sqlparser::ast::Statement::CreateFunction(create_function) => {
+                self.create_function(create_function)
+            }
+            sqlparser::ast::Statement::DropFunction {
+                if_exists,
+                func_desc,
+                ..
+            } => self.drop_function(if_exists, func_desc),
+            sqlparser::ast::Statement::CreateType {
+                name,
+                representation:
+                    sqlparser::ast::UserDefinedTypeRepresentation::Composite { attributes },
+            } => attributes
+                .into_iter()
+                .map(|attr| {
+                    Ok(Field {
+                        name: normalise_ident(&attr.name),
+                        r#type: self.resolve_type(&attr.data_type)?,
+                        // Attributes of composite types can't be declared NOT NULL
+                        nullable: true,
+                    })
+                })
+                .collect::<Result<_, _>>()
+                .and_then(|fields| {
+                    let (schema, name, qualified_name) = self.create_type(&name)?;
+                    schema.types.insert(
+                        name,
+                        Type::Composite {
+                            name: qualified_name,
+                            fields,
+                        },
+                    );
+                    Ok(())
+                }),
+            sqlparser::ast::Statement::CreateType {
+                name,
+                representation: sqlparser::ast::UserDefinedTypeRepresentation::Enum { labels },
+            } => self
+                .create_type(&name)
+                .map(|(schema, name, qualified_name)| {
+                    schema.types.insert(
+                        name,
+                        Type::Enum {
+                            name: qualified_name,
+                            variants: labels,
+                        },
+                    );
+                }),
+            // Extensions rasql doesn't know the functions of can still be used, as long as
+            // their signatures are added to the catalog some other way
+            sqlparser::ast::Statement::CreateExtension { name, .. } => {
+                self.unknown_extensions |= !self
+                    .catalog
+                    .functions_mut()
+                    .add_extension(&normalise_ident(&name).value);
+                Ok(())
+            }
+            sqlparser::ast::Statement::SetVariable {
+                variables: sqlparser::ast::OneOrManyWithParens::One(variable),
+                value,
+                ..
+            } if variable.0.len() == 1
+                && normalise_ident(&variable.0[0]).value == "search_path" =>
+            {
+                self.catalog
+                    .set_search_path(value.iter().filter_map(|expr| match expr {
+                        sqlparser::ast::Expr::Identifier(ident) => Some(ident.clone()),
+                        sqlparser::ast::Expr::Value(sqlparser::ast::Value::SingleQuotedString(
+                            value,
+                        )) => Some(Ident::with_quote('"', value)),
+                        _ => None,
+                    }));
+                Ok(())
+            }
+            _ => Ok(()),
+        };
+        if let Err(error) = result {
+            self.errors.push(error);
+        }
+    }
+
+    fn create_table(
+        &mut self,
+        sqlparser::ast::CreateTable {
+            name,
+            if_not_exists,
+            columns,
+            constraints,
+            ..
+        }: sqlparser::ast::CreateTable,
+    ) -> Result<(), SchemaError> {
+        let location = self.location(name.span());
+        let Some((schema, table_name)) = self.catalog.schema_for_object(&name) else {
+            return Err(SchemaError::OverQualifiedName { location, name });
+        };
+        let qualified_name = ObjectName(vec![schema.name.clone(), table_name.clone()]);
+        if let Some(kind) = schema.relation_kind(&table_name) {
+            return if if_not_exists {
+                Ok(())
+            } else {
+                Err(SchemaError::DuplicateObject {
+                    location,
+                    kind,
+                    name: qualified_name,
+                })
+            };
+        }
+        if schema.types.contains_key(&table_name) {
+            return Err(SchemaError::DuplicateObject {
+                location,
+                kind: ObjectKind::Type,
+                name: qualified_name,
+            });
+        }
+
+        let mut table = Table {
+            name: qualified_name.clone(),
+            columns: Vec::with_capacity(columns.len()),
+            constraints: vec![],
+        };
+        let mut column_constraints = vec![];
+        for column in columns {
+            let mut column = ColumnDef {
+                name: normalise_ident(&column.name),
+                ..column
+            };
+            match self.resolve_type(&column.data_type) {
+                Ok(data_type) => column.data_type = data_type,
+                Err(error) => self.errors.push(error),
+            }
+            if table.column(&column.name).is_some() {
+                self.errors.push(SchemaError::DuplicateColumn {
+                    location: self.location(column.name.span),
+                    table: qualified_name.clone(),
+                    column: column.name,
+                });
+                continue;
+            }
+            column_constraints.extend(
+                take_column_constraints(&mut column)
+                    .into_iter()
+                    .map(|constraint| (constraint, Some(column.name.clone()))),
+            );
+            table.columns.push(column);
+        }
+
+        // Constraints are resolved against the table as it is being built, allowing them to
+        // refer to the table itself
+        for (constraint, column) in column_constraints
+            .into_iter()
+            .chain(constraints.into_iter().map(|constraint| (constraint, None)))
+        {
+            match self.resolve_constraint(&table, Some(&table), constraint) {
+                Ok(mut constraint) => {
+                    table.name_constraint(&mut constraint, column.as_ref());
+                    table.constraints.push(constraint);
+                }
+                Err(error) => self.errors.push(error),
+            }
+        }
+
+        let schema = self.catalog.schema_entry(&qualified_name.0[0]);
+        schema
+            .types
+            .insert(table_name.clone(), table.composite_type());
+        schema.tables.insert(table_name, table);
+        Ok(())
+    }
+
+    fn create_view(
+        &mut self,
+        name: ObjectName,
+        column_names: Vec<sqlparser::ast::ViewColumnDef>,
+        query: Box<sqlparser::ast::Query>,
+        materialized: bool,
+        or_replace: bool,
+        if_not_exists: bool,
+    ) -> Result<(), SchemaError> {
+        let location = self.location(name.span());
+        let Some((schema, view_name)) = self.catalog.schema_for_object(&name) else {
+            return Err(SchemaError::OverQualifiedName { location, name });
+        };
+        let qualified_name = ObjectName(vec![schema.name.clone(), view_name.clone()]);
+        match schema.relation_kind(&view_name) {
+            Some(ObjectKind::View) if or_replace => (),
+            Some(_) if if_not_exists => return Ok(()),
+            Some(kind) => {
+                return Err(SchemaError::DuplicateObject {
+                    location,
+                    kind,
+                    name: qualified_name,
+                })
+            }
+            None => (),
+        }
+
+        // Postgres works out a view's columns when it's created, so later changes to the tables
+        // it reads from don't change them
+        let invalid_view = |source| SchemaError::InvalidView {
+            location: location.clone(),
+            name: qualified_name.clone(),
+            source,
+        };
+        let analysed = query::analyse_query(&self.catalog, &Statement::Query(query.clone()))
+            .map_err(invalid_view)?;
+        if column_names.len() > analysed.columns.len() {
+            return Err(invalid_view(QueryError::ColumnAliasCount {
+                location: location.clone(),
+                relation: qualified_name.0[1].clone(),
+                available: analysed.columns.len(),
+                given: column_names.len(),
+            }));
+        }
+        let mut columns: Vec<Field> = Vec::with_capacity(analysed.columns.len());
+        for (index, column) in analysed.columns.into_iter().enumerate() {
+            let name = match column_names.get(index) {
+                Some(column_name) => normalise_ident(&column_name.name),
+                None => column.name,
+            };
+            if columns.iter().any(|column| column.name == name) {
+                return Err(SchemaError::DuplicateColumn {
+                    location: self.location(name.span),
+                    table: qualified_name,
+                    column: name,
+                });
+            }
+            columns.push(Field {
+                name,
+                r#type: column.r#type,
+                nullable: column.nullable,
+            });
+        }
+
+        let mut dependencies = vec![];
+        let _ = sqlparser::ast::visit_relations(&query, |relation| {
+            // Names that aren't tables or views are CTEs, which the view can't depend on
+            if let Some(dependency) = self.catalog.relation(relation) {
+                if !dependencies.contains(&dependency) {
+                    dependencies.push(dependency);
+                }
+            }
+            std::ops::ControlFlow::<()>::Continue(())
+        });
+
+        self.catalog
+            .schema_entry(&qualified_name.0[0])
+            .views
+            .insert(
+                view_name,
+                View {
+                    name: qualified_name,
+                    query,
+                    materialized,
+                    columns,
+                    dependencies,
+                },
+            );
+        Ok(())
+    }
+
+    fn create_index(
+        &mut self,
+        sqlparser::ast::CreateIndex {
+            name,
+            table_name,
+            columns,
+            unique,
+            if_not_exists,
+            predicate,
+            ..
+        }: sqlparser::ast::CreateIndex,
+    ) -> Result<(), SchemaError> {
+        let Some(table) = self.resolve_table(&table_name, None)? else {
+            return Err(SchemaError::UnknownTable {
+                location: self.location(table_name.span()),
+                name: table_name,
+            });
+        };
+        let table_name = table.name.clone();
+        let columns: Vec<_> = columns.into_iter().map(|column| column.expr).collect();
+
+        // Indexes always live in the same schema as their table
+        let index_name = match &name {
+            Some(name) if name.0.len() > 3 => {
+                return Err(SchemaError::OverQualifiedName {
+                    location: self.location(name.span()),
+                    name: name.clone(),
+                })
+            }
+            Some(name) => normalise_ident(name.0.last().unwrap()),
+            None => {
+                let column_names = columns
+                    .iter()
+                    .map(|column| match column {
+                        sqlparser::ast::Expr::Identifier(ident) => normalise_ident(ident).value,
+                        _ => "expr".to_owned(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("_");
+                let base_name = format!("{}_{column_names}_idx", table_name.0[1].value);
+                let schema = self.catalog.schema(&table_name.0[0]).unwrap();
+                let mut candidate = base_name.clone();
+                let mut suffix = 0;
+                let name = |candidate: &str| normalise_ident(&Ident::with_quote('"', candidate));
+                while schema.relation_kind(&name(&candidate)).is_some() {
+                    suffix += 1;
+                    candidate = format!("{base_name}{suffix}");
+                }
+                name(&candidate)
+            }
+        };
+        let qualified_name = ObjectName(vec![table_name.0[0].clone(), index_name.clone()]);
+        let schema = self.catalog.schema_entry(&table_name.0[0]);
+        if let Some(kind) = schema.relation_kind(&index_name) {
+            return if if_not_exists {
+                Ok(())
+            } else {
+                Err(SchemaError::DuplicateObject {
+                    location: self
+                        .location(name.as_ref().map_or(Span::empty(), |name| name.span())),
+                    kind,
+                    name: qualified_name,
+                })
+            };
+        }
+        schema.indexes.insert(
+            index_name,
+            Index {
+                name: qualified_name,
+                table: table_name,
+                unique,
+                columns,
+                predicate,
+            },
+        );
+        Ok(())
+    }
+
+    /// `data_type` with the types the schema creates given their fully qualified name, as
+    /// [Catalog::qualified_type] does, failing if it names a type that doesn't exist. Unknown
+    /// unqualified names are allowed once an extension rasql doesn't know has been created, as
+    /// they could be one of its types.
+    pub(crate) fn resolve_type(&self, data_type: &DataType) -> Result<DataType, SchemaError> {
+        match data_type {
+            DataType::Custom(name, _) => {
+                let builtin = match name.0.as_slice() {
+                    [type_name] => {
+                        let type_name = normalise_ident(type_name).value;
+                        !matches!(types::canonical_type(data_type), DataType::Custom(..))
+                            || types::is_builtin_type_name(&type_name)
+                            || self.catalog.functions().mentions_type(&type_name)
+                            || self.unknown_extensions
+                    }
+                    _ => false,
+                };
+                if self.catalog.r#type(name).is_none() && !builtin {
+                    return Err(SchemaError::UnknownObject {
+                        location: self.location(name.span()),
+                        kind: ObjectKind::Type,
+                        name: name.clone(),
+                    });
+                }
+                Ok(self.catalog.qualified_type(data_type))
+            }
+            DataType::Array(
+                ArrayElemTypeDef::AngleBracket(element)
+                | ArrayElemTypeDef::SquareBracket(element, _)
+                | ArrayElemTypeDef::Parenthesis(element),
+            ) => Ok(DataType::Array(ArrayElemTypeDef::SquareBracket(
+                Box::new(self.resolve_type(element)?),
+                None,
+            ))),
+            data_type => Ok(data_type.clone()),
+        }
+    }
+
+    /// Find the schema a new type should go in, checking it doesn't already exist.
+    fn create_type(
+        &mut self,
+        name: &ObjectName,
+    ) -> Result<(&mut Schema, Ident, ObjectName), SchemaError> {
+        let location = self.location(name.span());
+        let Some((schema, type_name)) = self.catalog.schema_for_object(name) else {
+            return Err(SchemaError::OverQualifiedName {
+                location,
+                name: name.clone(),
+            });
+        };
+        let qualified_name = ObjectName(vec![schema.name.clone(), type_name.clone()]);
+        if schema.types.contains_key(&type_name) {
+            return Err(SchemaError::DuplicateObject {
+                location,
+                kind: ObjectKind::Type,
+                name: qualified_name,
+            });
+        }
+        Ok((schema, type_name, qualified_name))
+    }
+
+    /// Look up a table by name. `pending` is a table that is in the process of being created,
+    /// which can be found despite not being in the catalog yet.
+    pub(crate) fn resolve_table<'a>(
+        &'a self,
+        name: &ObjectName,
+        pending: Option<&'a Table>,
+    ) -> Result<Option<&'a Table>, SchemaError> {
+        if name.0.len() > 3 {
+            return Err(SchemaError::OverQualifiedName {
+                location: self.location(name.span()),
+                name: name.clone(),
+            });
+        }
+        let is_pending = |schema_name: &Ident, table_name: &Ident| {
+            pending.is_some_and(|pending| {
+                pending.name.0[0] == *schema_name && pending.name.0[1] == *table_name
+            })
+        };
+        let Some((schema_name, table_name)) = self.catalog.resolve(name, |schema, table_name| {
+            schema.tables.contains_key(table_name) || is_pending(&schema.name, table_name)
+        }) else {
+            return Ok(None);
+        };
+        if is_pending(&schema_name, &table_name) {
+            return Ok(pending);
+        }
+        Ok(self
+            .catalog
+            .schema(&schema_name)
+            .and_then(|schema| schema.tables.get(&table_name)))
+    }
+
+    /// Check a foreign key's target exists, replacing its name with the fully qualified one.
+    /// If no columns are referred to, they are filled in with the target's primary key.
+    pub(crate) fn resolve_reference(
+        &self,
+        pending: Option<&Table>,
+        foreign_table: &mut ObjectName,
+        referred_columns: &mut Vec<Ident>,
+    ) -> Result<(), SchemaError> {
+        let Some(table) = self.resolve_table(foreign_table, pending)? else {
+            return Err(SchemaError::UnknownTable {
+                location: self.location(foreign_table.span()),
+                name: foreign_table.clone(),
+            });
+        };
+        if referred_columns.is_empty() {
+            if let Some(primary_key) = table.primary_key() {
+                referred_columns.extend(primary_key.iter().cloned());
+            }
+        }
+        for column in referred_columns {
+            *column = normalise_ident(column);
+            if table.column(column).is_none() {
+                return Err(SchemaError::UnknownColumn {
+                    location: self.location(column.span),
+                    table: table.name.clone(),
+                    column: column.clone(),
+                });
+            }
+        }
+        *foreign_table = table.name.clone();
+        Ok(())
+    }
+
+    /// Check the columns a constraint on `table` refers to all exist, normalising their names,
+    /// and that it isn't a second primary key.
+    pub(crate) fn resolve_constraint(
+        &self,
+        table: &Table,
+        pending: Option<&Table>,
+        mut constraint: TableConstraint,
+    ) -> Result<TableConstraint, SchemaError> {
+        let columns = match &mut constraint {
+            TableConstraint::Unique { columns, .. }
+            | TableConstraint::PrimaryKey { columns, .. }
+            | TableConstraint::Index { columns, .. }
+            | TableConstraint::FulltextOrSpatial { columns, .. } => columns,
+            TableConstraint::ForeignKey {
+                columns,
+                foreign_table,
+                referred_columns,
+                ..
+            } => {
+                self.resolve_reference(pending, foreign_table, referred_columns)?;
+                columns
+            }
+            TableConstraint::Check { .. } => return Ok(constraint),
+        };
+        for column in columns {
+            *column = normalise_ident(column);
+            if table.column(column).is_none() {
+                return Err(SchemaError::UnknownColumn {
+                    location: self.location(column.span),
+                    table: table.name.clone(),
+                    column: column.clone(),
+                });
+            }
+        }
+        if matches!(constraint, TableConstraint::PrimaryKey { .. }) && table.primary_key().is_some()
+        {
+            return Err(SchemaError::MultiplePrimaryKeys {
+                location: self.location(constraint.span()),
+                table: table.name.clone(),
+            });
+        }
+        Ok(constraint)
+    }
+}
+
+/// Remove the constraints declared inline on a column, returning them as the equivalent table
+/// constraints so that all of a table's constraints can be found in one place.
+fn take_column_constraints(column: &mut ColumnDef) -> Vec<TableConstraint> {
+    let mut constraints = vec![];
+    column.options.retain(|option| {
+        let name = option.name.clone();
+        let columns = vec![column.name.clone()];
+        let constraint = match &option.option {
+            ColumnOption::Unique {
+                is_primary: true,
+                characteristics,
+            } => TableConstraint::PrimaryKey {
+                name,
+                index_name: None,
+                index_type: None,
+                columns,
+                index_options: vec![],
+                characteristics: *characteristics,
+            },
+            ColumnOption::Unique {
+                is_primary: false,
+                characteristics,
+            } => TableConstraint::Unique {
+                name,
+                index_name: None,
+                index_type_display: sqlparser::ast::KeyOrIndexDisplay::None,
+                index_type: None,
+                columns,
+                index_options: vec![],
+                characteristics: *characteristics,
+                nulls_distinct: sqlparser::ast::NullsDistinctOption::None,
+            },
+            ColumnOption::ForeignKey {
+                foreign_table,
+                referred_columns,
+                on_delete,
+                on_update,
+                characteristics,
+            } => TableConstraint::ForeignKey {
+                name,
+                columns,
+                foreign_table: foreign_table.clone(),
+                referred_columns: referred_columns.clone(),
+                on_delete: *on_delete,
+                on_update: *on_update,
+                characteristics: *characteristics,
+            },
+            ColumnOption::Check(expr) => TableConstraint::Check {
+                name,
+                expr: Box::new(expr.clone()),
+            },
+            _ => return true,
+        };
+        constraints.push(constraint);
+        false
+    });
+    constraints
+}
+
+/// The columns a constraint applies to.
+pub fn constraint_columns(constraint: &TableConstraint) -> &[Ident] {
+    match constraint {
+        TableConstraint::Unique { columns, .. }
+        | TableConstraint::PrimaryKey { columns, .. }
+        | TableConstraint::ForeignKey { columns, .. }
+        | TableConstraint::Index { columns, .. }
+        | TableConstraint::FulltextOrSpatial { columns, .. } => columns,
+        TableConstraint::Check { .. } => &[],
+    }
+}
+
+pub fn constraint_name(constraint: &TableConstraint) -> Option<&Ident> {
+    match constraint {
+        TableConstraint::Unique { name, .. }
+        | TableConstraint::PrimaryKey { name, .. }
+        | TableConstraint::ForeignKey { name, .. }
+        | TableConstraint::Check { name, .. }
+        | TableConstraint::Index { name, .. } => name.as_ref(),
+        TableConstraint::FulltextOrSpatial { opt_index_name, .. } => opt_index_name.as_ref(),
+    }
+}
+
+fn constraint_name_mut(constraint: &mut TableConstraint) -> &mut Option<Ident> {
+    match constraint {
+        TableConstraint::Unique { name, .. }
+        | TableConstraint::PrimaryKey { name, .. }
+        | TableConstraint::ForeignKey { name, .. }
+        | TableConstraint::Check { name, .. }
+        | TableConstraint::Index { name, .. } => name,
+        TableConstraint::FulltextOrSpatial { opt_index_name, .. } => opt_index_name,
+    }
+}
+
+/// Postgres folds unquoted identifiers to lower case, so `Users` and `users` name the same object
+/// while `"Users"` names a different one. Everything stored in a [Catalog] has been normalised this
+/// way so that its `value` is the name Postgres itself would use. Names that wouldn't survive
+/// folding stay quoted, so normalising a name more than once doesn't change it.
+pub fn normalise_ident(ident: &Ident) -> Ident {
+    let value = match ident.quote_style {
+        Some(_) => ident.value.clone(),
+        None => ident.value.to_lowercase(),
+    };
+    Ident {
+        quote_style: (value != value.to_lowercase()).then_some('"'),
+        value,
+        span: ident.span,
+    }
+}
+
+pub struct Schema {
+    pub name: Ident,
+    pub tables: IndexMap<Ident, Table>,
+    pub views: IndexMap<Ident, View>,
+    pub indexes: IndexMap<Ident, Index>,
+    pub types: IndexMap<Ident, Type>,
+    /// Every overload of each function, in the order they were created
+    pub functions: IndexMap<Ident, Vec<Function>>,
+}
+
+impl Schema {
+    fn new(name: Ident) -> Self {
+        Self {
+            name,
+            tables: Default::default(),
+            views: Default::default(),
+            indexes: Default::default(),
+            types: Default::default(),
+            functions: Default::default(),
+        }
+    }
+
+    /// Tables, views and indexes all share a namespace, so this finds which of those, if any,
+    /// `name` refers to.
+    pub fn relation_kind(&self, name: &Ident) -> Option<ObjectKind> {
+        if self.tables.contains_key(name) {
+            Some(ObjectKind::Table)
+        } else if self.views.contains_key(name) {
+            Some(ObjectKind::View)
+        } else if self.indexes.contains_key(name) {
+            Some(ObjectKind::Index)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Table {
+    /// Fully qualified name of the table, `[schema, table]`
+    pub name: ObjectName,
+    pub columns: Vec<ColumnDef>,
+    /// Every constraint on the table, including those declared inline on a column, each named
+    /// either as declared or as Postgres would have named it.
+    pub constraints: Vec<TableConstraint>,
+}
+
+impl Table {
+    pub fn column(&self, name: &Ident) -> Option<&ColumnDef> {
+        let name = normalise_ident(name);
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    pub fn constraint(&self, name: &Ident) -> Option<&TableConstraint> {
+        let name = normalise_ident(name);
+        self.constraints
+            .iter()
+            .find(|constraint| constraint_name(constraint) == Some(&name))
+    }
+
+    /// The columns making up the table's primary key, if it has one.
+    pub fn primary_key(&self) -> Option<&[Ident]> {
+        self.constraints
+            .iter()
+            .find_map(|constraint| match constraint {
+                TableConstraint::PrimaryKey { columns, .. } => Some(columns.as_slice()),
+                _ => None,
+            })
+    }
+
+    /// Whether `column` of this table can hold NULL. Columns are nullable unless they're
+    /// declared `NOT NULL` or are part of the primary key.
+    pub fn is_nullable(&self, column: &ColumnDef) -> bool {
+        let not_null = column
+            .options
+            .iter()
+            .rev()
+            .find_map(|option| match option.option {
+                ColumnOption::NotNull => Some(true),
+                ColumnOption::Null => Some(false),
+                _ => None,
+            })
+            .unwrap_or(false);
+        !not_null
+            && !self
+                .primary_key()
+                .is_some_and(|primary_key| primary_key.contains(&column.name))
+    }
+
+    /// Give `constraint` the name Postgres would if it doesn't already have one. `column` is
+    /// the column the constraint was declared on, if it was declared inline.
+    fn name_constraint(&self, constraint: &mut TableConstraint, column: Option<&Ident>) {
+        let table_name = &self.name.0[1].value;
+        let columns = constraint_columns(constraint)
+            .iter()
+            .map(|column| column.value.as_str())
+            .collect::<Vec<_>>()
+            .join("_");
+        let base_name = match constraint {
+            TableConstraint::PrimaryKey { .. } => format!("{table_name}_pkey"),
+            TableConstraint::Unique { .. } => format!("{table_name}_{columns}_key"),
+            TableConstraint::ForeignKey { .. } => format!("{table_name}_{columns}_fkey"),
+            TableConstraint::Check { .. } => match column {
+                Some(column) => format!("{table_name}_{}_check", column.value),
+                None => format!("{table_name}_check"),
+            },
+            TableConstraint::Index { .. } | TableConstraint::FulltextOrSpatial { .. } => {
+                format!("{table_name}_{columns}_idx")
+            }
+        };
+        let name = constraint_name_mut(constraint);
+        if let Some(name) = name {
+            *name = normalise_ident(name);
+            return;
+        }
+        let mut candidate = base_name.clone();
+        let mut suffix = 0;
+        let quoted = |candidate: &str| Ident::with_quote('"', candidate);
+        while self.constraint(&quoted(&candidate)).is_some() {
+            suffix += 1;
+            candidate = format!("{base_name}{suffix}");
+        }
+        *name = Some(normalise_ident(&quoted(&candidate)));
+    }
+
+    /// The composite type Postgres implicitly creates alongside every table.
+    pub fn composite_type(&self) -> Type {
+        Type::Composite {
+            name: self.name.clone(),
+            fields: self
+                .columns
+                .iter()
+                .map(|column| Field {
+                    name: column.name.clone(),
+                    r#type: column.data_type.clone(),
+                    nullable: self.is_nullable(column),
+                })
+                .collect(),
+        }
+    }
+}
+
+pub struct View {
+    /// Fully qualified name of the view, `[schema, view]`
+    pub name: ObjectName,
+    pub query: Box<sqlparser::ast::Query>,
+    pub materialized: bool,
+    /// The columns of the view's rows, as worked out from its query when it was created. Their
+    /// types are in canonical form.
+    pub columns: Vec<Field>,
+    /// Fully qualified names of the tables and views this view reads from
+    pub dependencies: Vec<ObjectName>,
+}
+
+pub struct Index {
+    /// Fully qualified name of the index, `[schema, index]`
+    pub name: ObjectName,
+    /// Fully qualified name of the table the index is on
+    pub table: ObjectName,
+    pub unique: bool,
+    pub columns: Vec<sqlparser::ast::Expr>,
+    /// The `WHERE` clause of a partial index
+    pub predicate: Option<sqlparser::ast::Expr>,
+}
+
+pub enum Type {
+    Composite {
+        /// Fully qualified name of the type, `[schema, type]`
+        name: ObjectName,
+        fields: Vec<Field>,
+    },
+    Enum {
+        /// Fully qualified name of the type, `[schema, type]`
+        name: ObjectName,
+        variants: Vec<Ident>,
+    },
+    Domain {
+        /// Fully qualified name of the type, `[schema, type]`
+        name: ObjectName,
+        /// The type the domain is based on, with any types the schema creates fully qualified
+        base: DataType,
+        /// The domain's `CHECK` constraints, which refer to the value being checked as `VALUE`
+        checks: Vec<Expr>,
+    },
+}
+
+impl Type {
+    pub fn name(&self) -> &ObjectName {
+        match self {
+            Type::Composite { name, .. } | Type::Enum { name, .. } | Type::Domain { name, .. } => {
+                name
+            }
+        }
+    }
+}
+
+pub struct Field {
+    pub name: Ident,
+    pub r#type: DataType,
+    pub nullable: bool,
+}
+
+/// A function created with `CREATE FUNCTION`. Functions can be overloaded, so there may be
+/// several with the same name that take different parameters.
+pub struct Function {
+    /// Fully qualified name of the function, `[schema, function]`
+    pub name: ObjectName,
+    /// The parameters the function is called with, which doesn't include `OUT` parameters
+    pub parameters: Vec<FunctionParameter>,
+    pub returns: FunctionReturns,
+    /// Whether the function returns any number of rows, as with `RETURNS SETOF` and
+    /// `RETURNS TABLE`, rather than exactly one
+    pub returns_set: bool,
+    /// Whether the function is `STRICT`, returning NULL whenever any argument is NULL
+    pub strict: bool,
+}
+
+impl Function {
+    /// The canonical type of a call to the function used as an expression. Functions that
+    /// return several columns give a `record`.
+    pub fn return_type(&self) -> DataType {
+        match &self.returns {
+            FunctionReturns::Void => DataType::Custom(ObjectName(vec![Ident::new("void")]), vec![]),
+            FunctionReturns::Value(r#type) => types::canonical_type(r#type),
+            FunctionReturns::Columns(columns) => match columns.as_slice() {
+                [column] => types::canonical_type(&column.r#type),
+                _ => DataType::Custom(ObjectName(vec![Ident::new("record")]), vec![]),
+            },
+        }
+    }
+
+    /// The signatures calls to the function can match, one for each number of arguments it
+    /// can be called with, since parameters with defaults can be left out.
+    pub fn signatures(&self) -> Vec<functions::Signature> {
+        let parameters: Vec<_> = self
+            .parameters
+            .iter()
+            .map(|parameter| types::canonical_type(&parameter.r#type))
+            .collect();
+        let required = self
+            .parameters
+            .iter()
+            .rposition(|parameter| !parameter.has_default)
+            .map_or(0, |index| index + 1);
+        (required..=parameters.len())
+            .map(|arity| functions::Signature {
+                parameters: parameters[..arity].to_vec(),
+                variadic: false,
+                return_type: self.return_type(),
+                kind: functions::FunctionKind::Scalar,
+                nullability: if self.strict {
+                    functions::Nullability::Strict
+                } else {
+                    functions::Nullability::Nullable
+                },
+            })
+            .collect()
+    }
+
+    /// Whether a call whose arguments were converted to `parameter_types` calls this
+    /// function, rather than another overload.
+    pub fn is_called_with(&self, parameter_types: &[Option<DataType>]) -> bool {
+        parameter_types.len() <= self.parameters.len()
+            && self
+                .parameters
+                .iter()
+                .zip(parameter_types)
+                .all(|(parameter, r#type)| {
+                    r#type.as_ref() == Some(&types::canonical_type(&parameter.r#type))
+                })
+            && self.parameters[parameter_types.len()..]
+                .iter()
+                .all(|parameter| parameter.has_default)
+    }
+}
+
+pub struct FunctionParameter {
+    pub name: Option<Ident>,
+    pub r#type: DataType,
+    /// Whether the parameter has a default, allowing it to be left out of calls
+    pub has_default: bool,
+}
+
+/// What a [Function] returns, or each row of what it returns if it returns a set.
+pub enum FunctionReturns {
+    /// `RETURNS void`
+    Void,
+    Value(DataType),
+    /// Named columns, as with `RETURNS TABLE` or `OUT` parameters. These are always nullable.
+    Columns(Vec<Field>),
+}
@@ -0,0 +1,321 @@
+//! Analysis of `INSERT`, `UPDATE` and `DELETE` statements.
+
+use sqlparser::{
+    ast::{
+        Assignment, AssignmentTarget, ColumnDef, DataType, Delete, DoUpdate, Expr, FromTable,
+        Ident, Insert, ObjectName, OnConflict, OnConflictAction, OnInsert, SelectItem, SetExpr,
+        Spanned, TableFactor, TableObject, TableWithJoins, UpdateTableFromKind,
+    },
+    tokenizer::Span,
+};
+
+use super::{
+    expr::is_parameter,
+    location,
+    scope::{Relation, Scope},
+    table_columns, unsupported, QueryAnalyser, QueryColumn,
+};
+use crate::sql::{normalise_ident, Catalog, QueryError, Table};
+
+impl<'a> QueryAnalyser<'a> {
+    pub(super) fn insert(&mut self, insert: &Insert) -> Result<Vec<QueryColumn>, QueryError> {
+        let TableObject::TableName(name) = &insert.table else {
+            return Err(unsupported(
+                insert.table.span(),
+                "inserting into a function",
+            ));
+        };
+        let table = self.target_table(name)?;
+        let targets = if insert.columns.is_empty() {
+            table.columns.iter().collect()
+        } else {
+            insert
+                .columns
+                .iter()
+                .map(|column| target_column(table, column))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+
+        // A missing source means `DEFAULT VALUES`
+        if let Some(source) = &insert.source {
+            match source.body.as_ref() {
+                SetExpr::Values(values) if source.with.is_none() => {
+                    for row in &values.rows {
+                        check_insert_column_count(insert, &targets, row.len(), name.span())?;
+                        for (value, column) in row.iter().zip(&targets) {
+                            if !is_default(value) {
+                                self.assign(&Scope::default(), table, column, value)?;
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    let columns = self.query(source, None)?;
+                    check_insert_column_count(insert, &targets, columns.len(), name.span())?;
+                    for (output, column) in columns.into_iter().zip(&targets) {
+                        let expected = self.catalog.canonical_type(&column.data_type);
+                        match output.r#type {
+                            Some(found)
+                                if !self.catalog.functions().can_assign(&found, &expected) =>
+                            {
+                                return Err(QueryError::AssignmentType {
+                                    location: location(source.span()),
+                                    column: column.name.clone(),
+                                    expected,
+                                    found,
+                                });
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(OnInsert::OnConflict(OnConflict {
+            action:
+                OnConflictAction::DoUpdate(DoUpdate {
+                    assignments,
+                    selection,
+                }),
+            ..
+        })) = &insert.on
+        {
+            // The row that couldn't be inserted can be referred to as `excluded`
+            let relation = target_relation(self.catalog, table, insert.table_alias.as_ref());
+            let excluded = Relation {
+                name: Ident::new("excluded"),
+                schema: None,
+                columns: relation.columns.clone(),
+            };
+            let mut scope = Scope::default();
+            scope.add_relation(relation, name.span())?;
+            scope.add_relation(excluded, name.span())?;
+            self.assignments(&scope, table, assignments)?;
+            if let Some(selection) = selection {
+                self.expect_type(&scope, selection, &DataType::Boolean)?;
+            }
+        }
+
+        let mut scope = Scope::default();
+        scope.add_relation(
+            target_relation(self.catalog, table, insert.table_alias.as_ref()),
+            name.span(),
+        )?;
+        self.returning(&scope, insert.returning.as_deref())
+    }
+
+    pub(super) fn update(
+        &mut self,
+        table: &TableWithJoins,
+        assignments: &[Assignment],
+        from: Option<&UpdateTableFromKind>,
+        selection: Option<&Expr>,
+        returning: Option<&[SelectItem]>,
+    ) -> Result<Vec<QueryColumn>, QueryError> {
+        let (target, relation) = self.target(table)?;
+        let mut scope = Scope::default();
+        scope.add_relation(relation, table.span())?;
+        if let Some(UpdateTableFromKind::BeforeSet(from) | UpdateTableFromKind::AfterSet(from)) =
+            from
+        {
+            self.table_with_joins(&mut scope, from)?;
+        }
+        self.assignments(&scope, target, assignments)?;
+        if let Some(selection) = selection {
+            self.expect_type(&scope, selection, &DataType::Boolean)?;
+        }
+        self.returning(&scope, returning)
+    }
+
+    pub(super) fn delete(&mut self, delete: &Delete) -> Result<Vec<QueryColumn>, QueryError> {
+        let (FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from)) = &delete.from;
+        let ([table], []) = (from.as_slice(), delete.tables.as_slice()) else {
+            return Err(unsupported(
+                delete.from.span(),
+                "deleting from anything but one table",
+            ));
+        };
+        let (_, relation) = self.target(table)?;
+        let mut scope = Scope::default();
+        scope.add_relation(relation, table.span())?;
+        for using in delete.using.iter().flatten() {
+            self.table_with_joins(&mut scope, using)?;
+        }
+        if let Some(selection) = &delete.selection {
+            self.expect_type(&scope, selection, &DataType::Boolean)?;
+        }
+        self.returning(&scope, delete.returning.as_deref())
+    }
+
+    /// Work out the columns of the rows a `RETURNING` clause returns, which can refer to
+    /// anything in `scope`.
+    fn returning(
+        &mut self,
+        scope: &Scope,
+        returning: Option<&[SelectItem]>,
+    ) -> Result<Vec<QueryColumn>, QueryError> {
+        match returning {
+            Some(items) => self.projection(scope, items),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// The table an `UPDATE` or `DELETE` modifies, along with how it can be referred to.
+    fn target(&self, table: &TableWithJoins) -> Result<(&'a Table, Relation), QueryError> {
+        let TableFactor::Table {
+            name,
+            alias,
+            args: None,
+            ..
+        } = &table.relation
+        else {
+            return Err(unsupported(table.span(), "modifying anything but a table"));
+        };
+        if !table.joins.is_empty() {
+            return Err(unsupported(table.span(), "JOIN in the target table"));
+        }
+        let target = self.target_table(name)?;
+        Ok((
+            target,
+            target_relation(
+                self.catalog,
+                target,
+                alias.as_ref().map(|alias| &alias.name),
+            ),
+        ))
+    }
+
+    fn target_table(&self, name: &ObjectName) -> Result<&'a Table, QueryError> {
+        self.catalog
+            .table(name)
+            .ok_or_else(|| QueryError::UnknownTable {
+                location: location(name.span()),
+                name: name.clone(),
+            })
+    }
+
+    fn assignments(
+        &mut self,
+        scope: &Scope,
+        table: &Table,
+        assignments: &[Assignment],
+    ) -> Result<(), QueryError> {
+        for assignment in assignments {
+            match (&assignment.target, &assignment.value) {
+                (AssignmentTarget::ColumnName(ObjectName(name)), value) => {
+                    let [name] = name.as_slice() else {
+                        return Err(QueryError::UnknownColumn {
+                            location: location(assignment.target.span()),
+                            column: ObjectName(name.clone()),
+                        });
+                    };
+                    self.assign(scope, table, target_column(table, name)?, value)?;
+                }
+                (AssignmentTarget::Tuple(names), Expr::Tuple(values))
+                    if names.len() == values.len() =>
+                {
+                    for (ObjectName(name), value) in names.iter().zip(values) {
+                        let [name] = name.as_slice() else {
+                            return Err(QueryError::UnknownColumn {
+                                location: location(assignment.target.span()),
+                                column: ObjectName(name.clone()),
+                            });
+                        };
+                        self.assign(scope, table, target_column(table, name)?, value)?;
+                    }
+                }
+                (AssignmentTarget::Tuple(_), value) => {
+                    return Err(unsupported(
+                        value.span(),
+                        "assigning to several columns from anything but a list of values",
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check `value` can be stored in `column` of `table`, giving it the column's type if it's a
+    /// parameter, which can be `NULL` if the column can.
+    fn assign(
+        &mut self,
+        scope: &Scope,
+        table: &Table,
+        column: &ColumnDef,
+        value: &Expr,
+    ) -> Result<(), QueryError> {
+        if is_default(value) {
+            return Ok(());
+        }
+        if table.is_nullable(column) {
+            self.allow_null(value);
+        }
+        let expected = self.catalog.canonical_type(&column.data_type);
+        let typed = self.expr(scope, value)?;
+        match typed.r#type {
+            Some(found)
+                if !is_parameter(value)
+                    && !self.catalog.functions().can_assign(&found, &expected) =>
+            {
+                Err(QueryError::AssignmentType {
+                    location: location(value.span()),
+                    column: column.name.clone(),
+                    expected,
+                    found,
+                })
+            }
+            Some(_) if !is_parameter(value) => Ok(()),
+            _ => self.infer(value, &expected),
+        }
+    }
+}
+
+fn target_relation(catalog: &Catalog, table: &Table, alias: Option<&Ident>) -> Relation {
+    match alias {
+        Some(alias) => Relation {
+            name: normalise_ident(alias),
+            schema: None,
+            columns: table_columns(catalog, table),
+        },
+        None => Relation {
+            name: table.name.0[1].clone(),
+            schema: Some(table.name.0[0].clone()),
+            columns: table_columns(catalog, table),
+        },
+    }
+}
+
+fn target_column<'t>(table: &'t Table, name: &Ident) -> Result<&'t ColumnDef, QueryError> {
+    table.column(name).ok_or_else(|| QueryError::UnknownColumn {
+        location: location(name.span),
+        column: ObjectName(vec![table.name.0[1].clone(), normalise_ident(name)]),
+    })
+}
+
+fn check_insert_column_count(
+    insert: &Insert,
+    targets: &[&ColumnDef],
+    values: usize,
+    span: Span,
+) -> Result<(), QueryError> {
+    // Without a column list, trailing columns can be left to their defaults
+    let too_few = !insert.columns.is_empty() && values < targets.len();
+    if values > targets.len() || too_few {
+        return Err(QueryError::InsertColumnCount {
+            location: location(span),
+            columns: targets.len(),
+            values,
+        });
+    }
+    Ok(())
+}
+
+/// Whether `expr` is the `DEFAULT` keyword, which sqlparser parses as an identifier.
+fn is_default(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Identifier(ident)
+            if ident.quote_style.is_none() && ident.value.eq_ignore_ascii_case("default")
+    )
+}
@@ -0,0 +1,670 @@
+//! Working out the type and nullability of expressions.
+
+use sqlparser::ast::{
+    ArrayElemTypeDef, BinaryOperator, CeilFloorKind, DataType, DateTimeField, ExactNumberInfo,
+    Expr, Ident, ObjectName, Query, Spanned, TimezoneInfo, TrimWhereField, Value,
+};
+
+use super::{
+    function::call_result, location, parameter_index, scope::Scope, unsupported, QueryAnalyser,
+    QueryColumn,
+};
+use crate::sql::{
+    functions::{array_of, Nullability, ResolvedCall},
+    normalise_ident,
+    types::canonical_type,
+    QueryError,
+};
+
+/// The type of an expression, as far as it can be worked out.
+#[derive(Debug, Clone)]
+pub(super) struct Typed {
+    /// The expression's type in canonical form, or `None` for string literals, `NULL` and
+    /// parameters, whose type is decided by where they're used.
+    pub r#type: Option<DataType>,
+    pub nullable: bool,
+}
+
+impl Typed {
+    fn new(r#type: DataType, nullable: bool) -> Self {
+        Self {
+            r#type: Some(r#type),
+            nullable,
+        }
+    }
+}
+
+impl QueryAnalyser<'_> {
+    pub(super) fn expr(&mut self, scope: &Scope, expr: &Expr) -> Result<Typed, QueryError> {
+        match expr {
+            Expr::Identifier(ident) => self.column_ref(scope, std::slice::from_ref(ident), expr),
+            Expr::CompoundIdentifier(idents) => self.column_ref(scope, idents, expr),
+            Expr::Value(value) => self.value(value, expr),
+            Expr::TypedString { data_type, .. } => {
+                Ok(Typed::new(self.catalog.canonical_type(data_type), false))
+            }
+            Expr::Interval(_) => Ok(Typed::new(DataType::Interval, false)),
+            Expr::Cast {
+                expr: inner,
+                data_type,
+                format: None,
+                ..
+            } => {
+                let data_type = self.catalog.canonical_type(data_type);
+                let typed = self.expr(scope, inner)?;
+                // Casting a parameter that isn't used anywhere else is how its type is usually
+                // given
+                if typed.r#type.is_none() {
+                    self.infer(inner, &data_type)?;
+                }
+                Ok(Typed::new(data_type, typed.nullable))
+            }
+            Expr::Nested(inner) => self.expr(scope, inner),
+            Expr::UnaryOp { op, expr: operand } => {
+                let typed = self.expr(scope, operand)?;
+                let operator = op.to_string();
+                let Some(call) = self
+                    .catalog
+                    .functions()
+                    .resolve_prefix_operator(&operator, typed.r#type.as_ref())
+                else {
+                    return Err(QueryError::UndefinedPrefixOperator {
+                        location: location(expr.span()),
+                        operator,
+                        operand: typed.r#type.unwrap_or_else(unknown_type),
+                    });
+                };
+                if let (None, Some(parameter_type)) = (&typed.r#type, &call.parameter_types[0]) {
+                    self.infer(operand, parameter_type)?;
+                }
+                Ok(call_result(&call, typed.nullable))
+            }
+            Expr::BinaryOp { left, op, right } => self.binary_op(scope, expr, left, op, right),
+            Expr::IsNull(operand) | Expr::IsNotNull(operand) => {
+                self.expr(scope, operand)?;
+                Ok(Typed::new(DataType::Boolean, false))
+            }
+            Expr::IsTrue(operand)
+            | Expr::IsNotTrue(operand)
+            | Expr::IsFalse(operand)
+            | Expr::IsNotFalse(operand)
+            | Expr::IsUnknown(operand)
+            | Expr::IsNotUnknown(operand) => {
+                self.expect_type(scope, operand, &DataType::Boolean)?;
+                Ok(Typed::new(DataType::Boolean, false))
+            }
+            Expr::IsDistinctFrom(left_expr, right_expr)
+            | Expr::IsNotDistinctFrom(left_expr, right_expr) => {
+                let left = self.expr(scope, left_expr)?;
+                let right = self.expr(scope, right_expr)?;
+                self.operator(
+                    expr,
+                    &BinaryOperator::Eq.to_string(),
+                    (Some(left_expr), &left),
+                    (Some(right_expr), &right),
+                )?;
+                // Unlike `=`, these treat NULL as a value, so a parameter compared with
+                // something nullable is given as an `Option`
+                if left.nullable {
+                    self.allow_null(right_expr);
+                }
+                if right.nullable {
+                    self.allow_null(left_expr);
+                }
+                Ok(Typed::new(DataType::Boolean, false))
+            }
+            Expr::InList {
+                expr: operand,
+                list,
+                ..
+            } => {
+                let mut nullable = false;
+                for item in list {
+                    nullable |= self
+                        .binary_op(scope, expr, operand, &BinaryOperator::Eq, item)?
+                        .nullable;
+                }
+                Ok(Typed::new(DataType::Boolean, nullable))
+            }
+            Expr::Between {
+                expr: operand,
+                low,
+                high,
+                ..
+            } => {
+                let low = self.binary_op(scope, expr, operand, &BinaryOperator::GtEq, low)?;
+                let high = self.binary_op(scope, expr, operand, &BinaryOperator::LtEq, high)?;
+                Ok(Typed::new(DataType::Boolean, low.nullable || high.nullable))
+            }
+            Expr::Like {
+                expr: operand,
+                pattern,
+                any: false,
+                ..
+            }
+            | Expr::ILike {
+                expr: operand,
+                pattern,
+                any: false,
+                ..
+            }
+            | Expr::SimilarTo {
+                expr: operand,
+                pattern,
+                ..
+            } => {
+                let operand = self.expect_type(scope, operand, &DataType::Text)?;
+                let pattern = self.expect_type(scope, pattern, &DataType::Text)?;
+                Ok(Typed::new(
+                    DataType::Boolean,
+                    operand.nullable || pattern.nullable,
+                ))
+            }
+            Expr::Case {
+                operand,
+                conditions,
+                results,
+                else_result,
+            } => self.case(
+                scope,
+                operand.as_deref(),
+                conditions,
+                results,
+                else_result.as_deref(),
+            ),
+            Expr::Function(function) => self.function(scope, function),
+            // These are functions called with special syntax
+            Expr::Extract {
+                field,
+                expr: operand,
+                ..
+            } => {
+                let field =
+                    Expr::Value(Value::SingleQuotedString(field.to_string().to_lowercase()));
+                self.call(scope, expr.span(), "extract", &[&field, operand])
+            }
+            Expr::Substring {
+                expr: operand,
+                substring_from,
+                substring_for,
+                ..
+            } => {
+                let args: Vec<_> = std::iter::once(operand.as_ref())
+                    .chain(substring_from.as_deref())
+                    .chain(substring_for.as_deref())
+                    .collect();
+                self.call(scope, expr.span(), "substring", &args)
+            }
+            Expr::Position {
+                expr: operand,
+                r#in,
+            } => self.call(scope, expr.span(), "position", &[r#in, operand]),
+            Expr::Trim {
+                expr: operand,
+                trim_where,
+                trim_what,
+                trim_characters: None,
+            } => {
+                let name = trim_name(trim_where.as_ref());
+                let args: Vec<_> = std::iter::once(operand.as_ref())
+                    .chain(trim_what.as_deref())
+                    .collect();
+                self.call(scope, expr.span(), name, &args)
+            }
+            Expr::Ceil {
+                expr: operand,
+                field: CeilFloorKind::DateTimeField(DateTimeField::NoDateTime),
+            } => self.call(scope, expr.span(), "ceil", &[operand]),
+            Expr::Floor {
+                expr: operand,
+                field: CeilFloorKind::DateTimeField(DateTimeField::NoDateTime),
+            } => self.call(scope, expr.span(), "floor", &[operand]),
+            // A scalar subquery is NULL when it returns no rows
+            Expr::Subquery(subquery) => Ok(Typed {
+                r#type: self.subquery_column(scope, subquery)?.r#type,
+                nullable: true,
+            }),
+            Expr::Exists { subquery, .. } => {
+                self.query(subquery, Some(scope))?;
+                Ok(Typed::new(DataType::Boolean, false))
+            }
+            Expr::InSubquery {
+                expr: operand,
+                subquery,
+                ..
+            } => {
+                let column = self.subquery_column(scope, subquery)?;
+                let operand_typed = self.expr(scope, operand)?;
+                let column = Typed {
+                    r#type: column.r#type,
+                    nullable: column.nullable,
+                };
+                self.operator(expr, "=", (Some(operand), &operand_typed), (None, &column))?;
+                Ok(Typed::new(
+                    DataType::Boolean,
+                    operand_typed.nullable || column.nullable,
+                ))
+            }
+            Expr::AnyOp {
+                left,
+                compare_op,
+                right,
+                ..
+            }
+            | Expr::AllOp {
+                left,
+                compare_op,
+                right,
+            } => self.quantified_op(scope, expr, left, compare_op, right),
+            Expr::Array(array) => {
+                let (mut r#type, _) = self.unify(scope, &array.elem)?;
+                // Like Postgres, an array of nothing but string literals and parameters is an
+                // array of text
+                if r#type.is_none() && !array.elem.is_empty() {
+                    for elem in &array.elem {
+                        self.infer(elem, &DataType::Text)?;
+                    }
+                    r#type = Some(DataType::Text);
+                }
+                Ok(Typed {
+                    r#type: r#type.map(array_of),
+                    nullable: false,
+                })
+            }
+            expr => Err(unsupported(expr.span(), "this kind of expression")),
+        }
+    }
+
+    /// Type `expr`, which must be usable as a value of type `expected`.
+    pub(super) fn expect_type(
+        &mut self,
+        scope: &Scope,
+        expr: &Expr,
+        expected: &DataType,
+    ) -> Result<Typed, QueryError> {
+        let typed = self.expr(scope, expr)?;
+        match &typed.r#type {
+            Some(found)
+                if !is_parameter(expr)
+                    && !self
+                        .catalog
+                        .functions()
+                        .can_cast_implicitly(found, expected) =>
+            {
+                return Err(QueryError::UnexpectedType {
+                    location: location(expr.span()),
+                    expected: expected.clone(),
+                    found: found.clone(),
+                })
+            }
+            Some(_) if !is_parameter(expr) => {}
+            _ => self.infer(expr, expected)?,
+        }
+        Ok(Typed::new(expected.clone(), typed.nullable))
+    }
+
+    /// Give `expr`, which is a parameter or of unknown type, the type `r#type` it's being used
+    /// as. This only matters for parameters, as Postgres works out the types of literals for
+    /// itself, and fails if the parameter has already been used as a different type.
+    pub(super) fn infer(&mut self, expr: &Expr, r#type: &DataType) -> Result<(), QueryError> {
+        match expr {
+            Expr::Nested(inner) => self.infer(inner, r#type),
+            Expr::Value(Value::Placeholder(placeholder)) => {
+                let Some(index) = parameter_index(placeholder) else {
+                    return Ok(());
+                };
+                match self.parameters.get(&index) {
+                    None => {
+                        self.parameters.insert(index, r#type.clone());
+                        Ok(())
+                    }
+                    Some(existing) if existing == r#type => Ok(()),
+                    Some(existing) => Err(QueryError::ConflictingParameterTypes {
+                        location: self.parameter_location(index),
+                        index,
+                        first: existing.clone(),
+                        second: r#type.clone(),
+                    }),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Let `expr` be `NULL` if it's a parameter, possibly cast to another type.
+    pub(super) fn allow_null(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Nested(inner) | Expr::Cast { expr: inner, .. } => self.allow_null(inner),
+            Expr::Value(Value::Placeholder(placeholder)) => {
+                if let Some(index) = parameter_index(placeholder) {
+                    self.nullable_parameters.insert(index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn column_ref(&self, scope: &Scope, name: &[Ident], expr: &Expr) -> Result<Typed, QueryError> {
+        let column = scope.column(name, expr.span())?;
+        Ok(Typed::new(column.r#type.clone(), column.nullable))
+    }
+
+    fn value(&self, value: &Value, expr: &Expr) -> Result<Typed, QueryError> {
+        Ok(match value {
+            Value::Number(number, _) => Typed::new(number_type(number), false),
+            Value::Boolean(_) => Typed::new(DataType::Boolean, false),
+            Value::Null => Typed {
+                r#type: None,
+                nullable: true,
+            },
+            Value::Placeholder(placeholder) => {
+                let index = parameter_index(placeholder)
+                    .ok_or_else(|| unsupported(expr.span(), "placeholders other than `$N`"))?;
+                Typed {
+                    r#type: self.parameters.get(&index).cloned(),
+                    nullable: false,
+                }
+            }
+            // Every kind of string literal starts out untyped
+            _ => Typed {
+                r#type: None,
+                nullable: false,
+            },
+        })
+    }
+
+    fn binary_op(
+        &mut self,
+        scope: &Scope,
+        expr: &Expr,
+        left_expr: &Expr,
+        op: &BinaryOperator,
+        right_expr: &Expr,
+    ) -> Result<Typed, QueryError> {
+        let left = self.expr(scope, left_expr)?;
+        let right = self.expr(scope, right_expr)?;
+        let call = self.operator(
+            expr,
+            &op.to_string(),
+            (Some(left_expr), &left),
+            (Some(right_expr), &right),
+        )?;
+        Ok(call_result(&call, left.nullable || right.nullable))
+    }
+
+    /// Type `left op ANY (right)` or `left op ALL (right)`, which compare `left` with each
+    /// element of an array or each row of a subquery.
+    fn quantified_op(
+        &mut self,
+        scope: &Scope,
+        expr: &Expr,
+        left_expr: &Expr,
+        op: &BinaryOperator,
+        right_expr: &Expr,
+    ) -> Result<Typed, QueryError> {
+        let left = self.expr(scope, left_expr)?;
+        let (element, nullable) = match right_expr {
+            Expr::Subquery(subquery) => {
+                let column = self.subquery_column(scope, subquery)?;
+                let element = Typed {
+                    r#type: column.r#type,
+                    nullable: column.nullable,
+                };
+                (element, column.nullable)
+            }
+            _ => {
+                let right = self.expr(scope, right_expr)?;
+                let element_type = match right.r#type {
+                    Some(DataType::Array(ArrayElemTypeDef::SquareBracket(element_type, _))) => {
+                        Some(*element_type)
+                    }
+                    Some(found) => {
+                        return Err(QueryError::UnexpectedType {
+                            location: location(right_expr.span()),
+                            expected: array_of(unknown_type()),
+                            found,
+                        })
+                    }
+                    None => None,
+                };
+                // Arrays passed as parameters can't hold NULLs, but arrays from anywhere else
+                // might
+                let nullable = right.nullable || !is_parameter(right_expr);
+                (
+                    Typed {
+                        r#type: element_type,
+                        nullable,
+                    },
+                    nullable,
+                )
+            }
+        };
+        let call = self.operator(
+            expr,
+            &op.to_string(),
+            (Some(left_expr), &left),
+            (None, &element),
+        )?;
+        if let (None, Some(element_type)) = (&element.r#type, &call.parameter_types[1]) {
+            self.infer(right_expr, &array_of(element_type.clone()))?;
+        }
+        Ok(Typed::new(
+            DataType::Boolean,
+            left.nullable || nullable || call.nullability == Nullability::Nullable,
+        ))
+    }
+
+    /// Resolve the binary `operator` applied to operands that have already been typed, giving
+    /// any parameters among them the type of the operand they're passed as. Operands without an
+    /// expression, such as the rows of a subquery, can't be parameters.
+    fn operator(
+        &mut self,
+        expr: &Expr,
+        operator: &str,
+        (left_expr, left): (Option<&Expr>, &Typed),
+        (right_expr, right): (Option<&Expr>, &Typed),
+    ) -> Result<ResolvedCall, QueryError> {
+        // Parameters are resolved as if their type were unknown when the other operand's type
+        // is known, so they're given its type even if they've already been given a type
+        // elsewhere, and conflicts are caught
+        let operand_type =
+            |expr: Option<&Expr>, typed: &Typed, other_expr: Option<&Expr>, other: &Typed| {
+                if expr.is_some_and(is_parameter)
+                    && other.r#type.is_some()
+                    && !other_expr.is_some_and(is_parameter)
+                {
+                    None
+                } else {
+                    typed.r#type.clone()
+                }
+            };
+        let left_type = operand_type(left_expr, left, right_expr, right);
+        let right_type = operand_type(right_expr, right, left_expr, left);
+        let Some(call) = self.catalog.functions().resolve_binary_operator(
+            operator,
+            left_type.as_ref(),
+            right_type.as_ref(),
+        ) else {
+            return Err(QueryError::UndefinedOperator {
+                location: location(expr.span()),
+                operator: operator.to_owned(),
+                left: left.r#type.clone().unwrap_or_else(unknown_type),
+                right: right.r#type.clone().unwrap_or_else(unknown_type),
+            });
+        };
+        for (operand_expr, operand_type, parameter_type) in [
+            (left_expr, &left_type, &call.parameter_types[0]),
+            (right_expr, &right_type, &call.parameter_types[1]),
+        ] {
+            if let (Some(operand_expr), None, Some(parameter_type)) =
+                (operand_expr, operand_type, parameter_type)
+            {
+                self.infer(operand_expr, parameter_type)?;
+            }
+        }
+        Ok(call)
+    }
+
+    fn case(
+        &mut self,
+        scope: &Scope,
+        operand: Option<&Expr>,
+        conditions: &[Expr],
+        results: &[Expr],
+        else_result: Option<&Expr>,
+    ) -> Result<Typed, QueryError> {
+        for condition in conditions {
+            match operand {
+                Some(operand) => {
+                    self.binary_op(scope, condition, operand, &BinaryOperator::Eq, condition)?;
+                }
+                None => {
+                    self.expect_type(scope, condition, &DataType::Boolean)?;
+                }
+            }
+        }
+
+        let (r#type, nullable) = self.unify(scope, results.iter().chain(else_result))?;
+        // Without an ELSE, the result is NULL when nothing matches
+        Ok(Typed {
+            r#type,
+            nullable: else_result.is_none() || nullable.contains(&true),
+        })
+    }
+
+    /// The column of a subquery used as an expression, which must return exactly one.
+    fn subquery_column(
+        &mut self,
+        scope: &Scope,
+        subquery: &Query,
+    ) -> Result<QueryColumn, QueryError> {
+        let mut columns = self.query(subquery, Some(scope))?;
+        if columns.len() != 1 {
+            return Err(QueryError::SubqueryColumnCount {
+                location: location(subquery.span()),
+                columns: columns.len(),
+            });
+        }
+        Ok(columns.remove(0))
+    }
+
+    /// Work out the type that the values of every one of `exprs` can be converted to, as
+    /// happens for the branches of a `CASE`. This is returned along with whether each
+    /// expression is nullable.
+    pub(super) fn unify<'e>(
+        &mut self,
+        scope: &Scope,
+        exprs: impl IntoIterator<Item = &'e Expr>,
+    ) -> Result<(Option<DataType>, Vec<bool>), QueryError> {
+        let mut r#type: Option<DataType> = None;
+        let mut nullable = vec![];
+        let mut untyped = vec![];
+        for expr in exprs {
+            let typed = self.expr(scope, expr)?;
+            nullable.push(typed.nullable);
+            r#type = match (r#type, typed.r#type) {
+                (Some(left), Some(right)) => Some(
+                    self.catalog
+                        .functions()
+                        .common_type(&left, &right)
+                        .ok_or_else(|| QueryError::IncompatibleTypes {
+                            location: location(expr.span()),
+                            left,
+                            right,
+                        })?,
+                ),
+                (left, None) => {
+                    untyped.push(expr);
+                    left
+                }
+                (None, right) => right,
+            };
+        }
+        if let Some(r#type) = &r#type {
+            for expr in untyped {
+                self.infer(expr, r#type)?;
+            }
+        }
+        Ok((r#type, nullable))
+    }
+}
+
+/// The type Postgres reports for string literals, `NULL` and parameters that haven't been
+/// given a type yet, for use in error messages.
+pub(super) fn unknown_type() -> DataType {
+    DataType::Custom(ObjectName(vec![Ident::new("unknown")]), vec![])
+}
+
+/// The function `TRIM` calls, depending on which end of the string it trims.
+fn trim_name(trim_where: Option<&TrimWhereField>) -> &'static str {
+    match trim_where {
+        Some(TrimWhereField::Leading) => "ltrim",
+        Some(TrimWhereField::Trailing) => "rtrim",
+        Some(TrimWhereField::Both) | None => "btrim",
+    }
+}
+
+/// Whether `expr` is a `$N` parameter, possibly in parentheses.
+pub(super) fn is_parameter(expr: &Expr) -> bool {
+    match expr {
+        Expr::Nested(inner) => is_parameter(inner),
+        Expr::Value(Value::Placeholder(_)) => true,
+        _ => false,
+    }
+}
+
+/// The type Postgres gives a numeric literal, which is the smallest of `INT4`, `INT8` and
+/// `NUMERIC` that can hold it.
+fn number_type(number: &str) -> DataType {
+    if number.parse::<i32>().is_ok() {
+        DataType::Int4(None)
+    } else if number.parse::<i64>().is_ok() {
+        DataType::Int8(None)
+    } else {
+        DataType::Numeric(ExactNumberInfo::None)
+    }
+}
+
+/// The name Postgres gives an output column that isn't aliased, if it gives it one other than
+/// `?column?`.
+pub(super) fn output_name(expr: &Expr) -> Option<Ident> {
+    match expr {
+        Expr::Identifier(ident) => Some(normalise_ident(ident)),
+        Expr::CompoundIdentifier(idents) => idents.last().map(normalise_ident),
+        Expr::Nested(inner) => output_name(inner),
+        Expr::Cast {
+            expr: inner,
+            data_type,
+            ..
+        } => output_name(inner).or_else(|| Some(Ident::new(type_name(data_type)))),
+        Expr::Function(function) => function.name.0.last().map(normalise_ident),
+        Expr::Extract { .. } => Some(Ident::new("extract")),
+        Expr::Substring { .. } => Some(Ident::new("substring")),
+        Expr::Position { .. } => Some(Ident::new("position")),
+        Expr::Trim { trim_where, .. } => Some(Ident::new(trim_name(trim_where.as_ref()))),
+        Expr::Ceil { .. } => Some(Ident::new("ceil")),
+        Expr::Floor { .. } => Some(Ident::new("floor")),
+        Expr::Case { .. } => Some(Ident::new("case")),
+        _ => None,
+    }
+}
+
+/// The name Postgres uses for a type internally, which is what a cast is named after.
+fn type_name(data_type: &DataType) -> String {
+    match canonical_type(data_type) {
+        DataType::Int2(_) => "int2".to_owned(),
+        DataType::Int4(_) => "int4".to_owned(),
+        DataType::Int8(_) => "int8".to_owned(),
+        DataType::Float4 => "float4".to_owned(),
+        DataType::Float8 => "float8".to_owned(),
+        DataType::Boolean => "bool".to_owned(),
+        DataType::Timestamp(_, TimezoneInfo::WithTimeZone) => "timestamptz".to_owned(),
+        DataType::Time(_, TimezoneInfo::WithTimeZone) => "timetz".to_owned(),
+        DataType::Custom(name, _) => name
+            .0
+            .last()
+            .map(|ident| normalise_ident(ident).value)
+            .unwrap_or_default(),
+        data_type => data_type.to_string().to_lowercase(),
+    }
+}
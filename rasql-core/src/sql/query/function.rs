@@ -0,0 +1,331 @@
+//! Calls to functions, which are resolved against the [FunctionCatalog](crate::sql::FunctionCatalog).
+
+use sqlparser::{
+    ast::{
+        DataType, DuplicateTreatment, Expr, Function, FunctionArg, FunctionArgExpr,
+        FunctionArgumentClause, FunctionArguments, Ident, ObjectName, Spanned, TableAlias,
+        WindowType,
+    },
+    tokenizer::Span,
+};
+
+use super::{
+    aliased_relation,
+    expr::{unknown_type, Typed},
+    location,
+    scope::{Relation, RelationColumn, Scope},
+    unsupported, QueryAnalyser,
+};
+use crate::sql::{
+    functions::{FunctionKind, Nullability, ResolvedCall},
+    normalise_ident, FunctionReturns, QueryError, Type,
+};
+
+impl QueryAnalyser<'_> {
+    pub(super) fn function(
+        &mut self,
+        scope: &Scope,
+        function: &Function,
+    ) -> Result<Typed, QueryError> {
+        let FunctionCall {
+            args,
+            distinct,
+            order_by,
+        } = function_call(function)?;
+
+        if function.over.is_none() && function.filter.is_none() && !distinct && order_by.is_empty()
+        {
+            match (builtin_name(&function.name).as_deref(), args.as_slice()) {
+                // These only return NULL when every argument is NULL
+                (Some("coalesce" | "greatest" | "least"), [_, ..]) => {
+                    let (r#type, nullable) = self.unify(scope, args.iter().copied())?;
+                    return Ok(Typed {
+                        r#type,
+                        nullable: !nullable.contains(&false),
+                    });
+                }
+                // This returns its first argument, or NULL when both are equal
+                (Some("nullif"), [_, _]) => {
+                    let (r#type, _) = self.unify(scope, args.iter().copied())?;
+                    return Ok(Typed {
+                        r#type,
+                        nullable: true,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        let (call, nullable) = self.resolve_call(scope, function.span(), &function.name, &args)?;
+        match (call.kind, &function.over) {
+            (FunctionKind::Window, None) => {
+                return Err(QueryError::WindowFunctionWithoutOver {
+                    location: location(function.span()),
+                    name: function.name.clone(),
+                })
+            }
+            (FunctionKind::Scalar, Some(_)) => {
+                return Err(QueryError::OverOnScalarFunction {
+                    location: location(function.span()),
+                    name: function.name.clone(),
+                })
+            }
+            (_, Some(WindowType::WindowSpec(window))) => {
+                for expr in &window.partition_by {
+                    self.expr(scope, expr)?;
+                }
+                for order_by_expr in &window.order_by {
+                    self.expr(scope, &order_by_expr.expr)?;
+                }
+            }
+            _ => {}
+        }
+        if call.kind != FunctionKind::Aggregate
+            && (function.filter.is_some() || distinct || !order_by.is_empty())
+        {
+            return Err(unsupported(
+                function.span(),
+                "FILTER, DISTINCT or ORDER BY on functions that aren't aggregates",
+            ));
+        }
+        if let Some(filter) = &function.filter {
+            self.expect_type(scope, filter, &sqlparser::ast::DataType::Boolean)?;
+        }
+        for expr in order_by {
+            self.expr(scope, expr)?;
+        }
+        Ok(call_result(&call, nullable))
+    }
+
+    /// Analyse a call to the function `name`, returning the type and nullability of its result.
+    pub(super) fn call(
+        &mut self,
+        scope: &Scope,
+        span: Span,
+        name: &str,
+        args: &[&Expr],
+    ) -> Result<Typed, QueryError> {
+        let name = ObjectName(vec![Ident::new(name)]);
+        let (call, nullable) = self.resolve_call(scope, span, &name, args)?;
+        Ok(call_result(&call, nullable))
+    }
+
+    /// Analyse a function called in `FROM`, giving the relation of the rows it returns.
+    /// Functions in `FROM` can use the relations before them, as if they were `LATERAL`.
+    pub(super) fn function_relation(
+        &mut self,
+        scope: &Scope,
+        span: Span,
+        name: &ObjectName,
+        args: &[FunctionArg],
+        alias: Option<&TableAlias>,
+    ) -> Result<Relation, QueryError> {
+        let args = args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Ok(expr),
+                arg => Err(unsupported(arg.span(), "this kind of function argument")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let (call, nullable) = self.resolve_call(scope, span, name, &args)?;
+        if call.kind != FunctionKind::Scalar {
+            return Err(unsupported(span, "aggregate and window functions in FROM"));
+        }
+
+        let returns = self
+            .catalog
+            .function(name)
+            .iter()
+            .find(|function| function.is_called_with(&call.parameter_types))
+            .map(|function| &function.returns);
+        let fields = match returns {
+            Some(FunctionReturns::Columns(fields)) => Some(fields),
+            // Functions returning a composite type give a column for each of its fields
+            Some(FunctionReturns::Value(DataType::Custom(type_name, _))) => {
+                match self.catalog.r#type(type_name) {
+                    Some(Type::Composite { fields, .. }) => Some(fields),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        let function_name = normalise_ident(name.0.last().unwrap());
+        let columns = match fields {
+            // The function can return a NULL row, so none of its columns can be relied on
+            Some(fields) => fields
+                .iter()
+                .map(|field| RelationColumn {
+                    name: field.name.clone(),
+                    r#type: self.catalog.canonical_type(&field.r#type),
+                    nullable: true,
+                    merged: false,
+                })
+                .collect(),
+            // Otherwise there's one column, named after the alias if there is one
+            None => {
+                let Typed { r#type, nullable } = call_result(&call, nullable);
+                let Some(r#type) = r#type else {
+                    return Err(unsupported(
+                        span,
+                        "functions in FROM whose return type can't be worked out",
+                    ));
+                };
+                vec![RelationColumn {
+                    name: alias.map_or_else(
+                        || function_name.clone(),
+                        |alias| normalise_ident(&alias.name),
+                    ),
+                    r#type,
+                    nullable,
+                    merged: false,
+                }]
+            }
+        };
+        match alias {
+            Some(alias) => aliased_relation(alias, columns),
+            None => Ok(Relation {
+                name: function_name,
+                schema: None,
+                columns,
+            }),
+        }
+    }
+
+    /// Pick the overload of the function `name` that a call with `args` uses, giving any
+    /// parameters among `args` the type of the parameter they're passed as. This is returned
+    /// along with whether any argument is nullable.
+    ///
+    /// Both built-in functions and those the schema creates are candidates, as Postgres looks
+    /// for functions in `pg_catalog` as well as the search path.
+    fn resolve_call(
+        &mut self,
+        scope: &Scope,
+        span: Span,
+        name: &ObjectName,
+        args: &[&Expr],
+    ) -> Result<(ResolvedCall, bool), QueryError> {
+        let mut arg_types = vec![];
+        let mut nullable = false;
+        for arg in args {
+            let typed = self.expr(scope, arg)?;
+            nullable |= typed.nullable;
+            arg_types.push(typed.r#type);
+        }
+        let functions = self.catalog.functions();
+        let mut candidates = builtin_name(name)
+            .map(|name| functions.function(&name).to_vec())
+            .unwrap_or_default();
+        for function in self.catalog.function(name) {
+            candidates.extend(function.signatures());
+        }
+        let Some(call) = functions.resolve_overload(&candidates, &arg_types) else {
+            let arg_types: Vec<_> = arg_types
+                .into_iter()
+                .map(|r#type| r#type.unwrap_or_else(unknown_type).to_string())
+                .collect();
+            let name = ObjectName(name.0.iter().map(normalise_ident).collect());
+            return Err(QueryError::UndefinedFunction {
+                location: location(span),
+                signature: format!("{name}({})", arg_types.join(", ")),
+            });
+        };
+        for ((arg, arg_type), parameter_type) in
+            args.iter().zip(&arg_types).zip(&call.parameter_types)
+        {
+            if let (None, Some(parameter_type)) = (arg_type, parameter_type) {
+                self.infer(arg, parameter_type)?;
+            }
+        }
+        Ok((call, nullable))
+    }
+}
+
+/// The name of the built-in function `name` refers to, if it can refer to one. Built-in
+/// functions live in `pg_catalog`, so they can only be called unqualified or qualified with
+/// that.
+fn builtin_name(name: &ObjectName) -> Option<String> {
+    match name.0.as_slice() {
+        [name] => Some(normalise_ident(name).value),
+        [schema, name] if normalise_ident(schema).value == "pg_catalog" => {
+            Some(normalise_ident(name).value)
+        }
+        _ => None,
+    }
+}
+
+/// The parts of a function call's argument list that affect its type.
+struct FunctionCall<'f> {
+    args: Vec<&'f Expr>,
+    distinct: bool,
+    /// The expressions an aggregate orders its input by.
+    order_by: Vec<&'f Expr>,
+}
+
+/// Split up the arguments of a function call, which must all be plain positional arguments.
+/// `count(*)` is taken to have no arguments, as Postgres does.
+fn function_call(function: &Function) -> Result<FunctionCall<'_>, QueryError> {
+    if !function.within_group.is_empty() {
+        return Err(unsupported(function.span(), "WITHIN GROUP"));
+    }
+    let list = match &function.args {
+        FunctionArguments::None => {
+            return Ok(FunctionCall {
+                args: vec![],
+                distinct: false,
+                order_by: vec![],
+            })
+        }
+        FunctionArguments::List(list) => list,
+        args => {
+            return Err(unsupported(
+                args.span(),
+                "this kind of function argument list",
+            ))
+        }
+    };
+
+    let args = match list.args.as_slice() {
+        [FunctionArg::Unnamed(FunctionArgExpr::Wildcard)] => vec![],
+        args => args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => Ok(expr),
+                arg => Err(unsupported(arg.span(), "this kind of function argument")),
+            })
+            .collect::<Result<_, _>>()?,
+    };
+    let mut order_by = vec![];
+    for clause in &list.clauses {
+        match clause {
+            FunctionArgumentClause::OrderBy(order_by_exprs) => order_by.extend(
+                order_by_exprs
+                    .iter()
+                    .map(|order_by_expr| &order_by_expr.expr),
+            ),
+            _ => {
+                return Err(unsupported(
+                    function.span(),
+                    "this function argument clause",
+                ))
+            }
+        }
+    }
+    Ok(FunctionCall {
+        args,
+        distinct: list.duplicate_treatment == Some(DuplicateTreatment::Distinct),
+        order_by,
+    })
+}
+
+/// The type and nullability of what `call` returns, given whether any of its arguments are
+/// nullable.
+pub(super) fn call_result(call: &ResolvedCall, args_nullable: bool) -> Typed {
+    Typed {
+        r#type: call.return_type.clone(),
+        nullable: match call.nullability {
+            Nullability::Strict => args_nullable,
+            Nullability::NonNull => false,
+            Nullability::Nullable => true,
+        },
+    }
+}
@@ -0,0 +1,843 @@
+//! Static analysis of queries against a [Catalog], working out the type of every output column
+//! and parameter.
+
+mod dml;
+mod expr;
+mod function;
+mod scope;
+#[cfg(test)]
+mod tests;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ops::ControlFlow,
+};
+
+use sqlparser::{
+    ast::{
+        BinaryOperator, Cte, DataType, Distinct, Expr, FunctionArg, FunctionArgExpr, GroupByExpr,
+        Ident, JoinConstraint, JoinOperator, ObjectName, OrderBy, Query, Select, SelectItem,
+        SetExpr, SetOperator, Spanned, Statement, TableAlias, TableFactor, TableWithJoins, Value,
+    },
+    dialect::PostgreSqlDialect,
+    parser::Parser,
+    tokenizer::{Span, Token, Tokenizer},
+};
+
+use self::{
+    expr::output_name,
+    scope::{Relation, RelationColumn, Scope},
+};
+use super::{
+    catalog::Catalog, error::QueryError, functions::FunctionCatalog, normalise_ident,
+    SourceLocation, Table,
+};
+
+/// The shape of a query's input and output.
+#[derive(Debug, Clone)]
+pub struct AnalysedQuery {
+    pub kind: StatementKind,
+    /// The columns of each row the query returns, in order. This is empty unless `kind` is
+    /// [StatementKind::Query].
+    pub columns: Vec<OutputColumn>,
+    /// The query's parameters, where `parameters[0]` is `$1`.
+    pub parameters: Vec<Parameter>,
+}
+
+/// How a statement needs to be run, which depends on whether it returns rows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    /// A statement that returns rows: a `SELECT`, or an `INSERT`, `UPDATE` or `DELETE` with a
+    /// `RETURNING` clause.
+    Query,
+    /// An `INSERT` without `RETURNING`.
+    Insert,
+    /// An `UPDATE` without `RETURNING`.
+    Update,
+    /// A `DELETE` without `RETURNING`.
+    Delete,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutputColumn {
+    pub name: Ident,
+    /// The column's type, in the form given by [Catalog::canonical_type].
+    pub r#type: DataType,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub r#type: DataType,
+    /// Whether the parameter can be `NULL`, which it can when it's stored in a nullable column,
+    /// or compared with something nullable with `IS [NOT] DISTINCT FROM`.
+    pub nullable: bool,
+}
+
+/// A column of the rows a query or subquery returns. Unlike [OutputColumn] its type can still
+/// be unknown, as in `SELECT NULL`, so it can be decided by how the column is used.
+#[derive(Debug, Clone)]
+struct QueryColumn {
+    name: Ident,
+    r#type: Option<DataType>,
+    nullable: bool,
+}
+
+/// Parse `sql`, which must hold exactly one statement, and analyse it against `catalog`.
+pub fn parse_and_analyse_query(catalog: &Catalog, sql: &str) -> Result<AnalysedQuery, QueryError> {
+    let mut statements =
+        Parser::parse_sql(&PostgreSqlDialect {}, sql).map_err(|error| QueryError::Parse {
+            location: location(Span::empty()),
+            message: error.to_string(),
+        })?;
+    if statements.len() != 1 {
+        return Err(QueryError::Parse {
+            location: location(Span::empty()),
+            message: format!("expected exactly one statement, found {}", statements.len()),
+        });
+    }
+    analyse(catalog, &statements.remove(0), parameter_spans(sql))
+}
+
+/// Analyse a single statement against `catalog`.
+pub fn analyse_query(
+    catalog: &Catalog,
+    statement: &Statement,
+) -> Result<AnalysedQuery, QueryError> {
+    analyse(catalog, statement, BTreeMap::new())
+}
+
+fn analyse(
+    catalog: &Catalog,
+    statement: &Statement,
+    parameter_spans: BTreeMap<usize, Span>,
+) -> Result<AnalysedQuery, QueryError> {
+    let mut analyser = QueryAnalyser {
+        parameter_spans,
+        ..QueryAnalyser::new(catalog)
+    };
+    let (columns, kind, returning) = match statement {
+        Statement::Query(query) => (analyser.query(query, None)?, StatementKind::Query, None),
+        Statement::Insert(insert) => (
+            analyser.insert(insert)?,
+            StatementKind::Insert,
+            insert.returning.as_ref(),
+        ),
+        Statement::Update {
+            table,
+            assignments,
+            from,
+            selection,
+            returning,
+            ..
+        } => (
+            analyser.update(
+                table,
+                assignments,
+                from.as_ref(),
+                selection.as_ref(),
+                returning.as_deref(),
+            )?,
+            StatementKind::Update,
+            returning.as_ref(),
+        ),
+        Statement::Delete(delete) => (
+            analyser.delete(delete)?,
+            StatementKind::Delete,
+            delete.returning.as_ref(),
+        ),
+        statement => {
+            return Err(QueryError::Unsupported {
+                location: location(statement.span()),
+                description: "this kind of statement".to_owned(),
+            })
+        }
+    };
+    let parameters = analyser.finish_parameters(statement)?;
+    Ok(AnalysedQuery {
+        kind: match returning {
+            Some(_) => StatementKind::Query,
+            None => kind,
+        },
+        columns: columns.into_iter().map(output_column).collect(),
+        parameters,
+    })
+}
+
+struct QueryAnalyser<'a> {
+    catalog: &'a Catalog,
+    /// The types of parameters worked out so far, keyed by their number.
+    parameters: BTreeMap<usize, DataType>,
+    /// The parameters that can be `NULL`, see [Parameter::nullable].
+    nullable_parameters: BTreeSet<usize>,
+    /// Where each parameter is first used. sqlparser doesn't keep track of this itself.
+    parameter_spans: BTreeMap<usize, Span>,
+    /// The common table expressions in scope, innermost last.
+    ctes: Vec<Relation>,
+}
+
+impl<'a> QueryAnalyser<'a> {
+    fn new(catalog: &'a Catalog) -> Self {
+        Self {
+            catalog,
+            parameters: BTreeMap::new(),
+            nullable_parameters: BTreeSet::new(),
+            parameter_spans: BTreeMap::new(),
+            ctes: vec![],
+        }
+    }
+
+    fn parameter_location(&self, index: usize) -> SourceLocation {
+        location(
+            self.parameter_spans
+                .get(&index)
+                .copied()
+                .unwrap_or(Span::empty()),
+        )
+    }
+
+    /// Analyse a query, which is a subquery of another query if `outer` is the scope of that
+    /// query.
+    fn query(
+        &mut self,
+        query: &Query,
+        outer: Option<&Scope>,
+    ) -> Result<Vec<QueryColumn>, QueryError> {
+        let cte_count = self.ctes.len();
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                self.cte(cte, with.recursive, outer)?;
+            }
+        }
+        let scope = Scope::with_outer(outer);
+        let columns = match query.body.as_ref() {
+            SetExpr::Select(select) => self.select(select, query.order_by.as_ref(), outer)?,
+            body => {
+                let columns = self.set_expr(body, outer)?;
+                if let Some(order_by) = &query.order_by {
+                    self.order_by(&scope, &columns, order_by)?;
+                }
+                columns
+            }
+        };
+
+        let limits = query
+            .limit
+            .iter()
+            .chain(query.offset.as_ref().map(|offset| &offset.value))
+            .chain(
+                query
+                    .fetch
+                    .as_ref()
+                    .and_then(|fetch| fetch.quantity.as_ref()),
+            );
+        for limit in limits {
+            self.expect_type(&scope, limit, &DataType::Int8(None))?;
+        }
+        self.ctes.truncate(cte_count);
+        Ok(columns)
+    }
+
+    /// Analyse a common table expression, making it available to the rest of the query.
+    fn cte(&mut self, cte: &Cte, recursive: bool, outer: Option<&Scope>) -> Result<(), QueryError> {
+        // A recursive CTE is a non-recursive term unioned with a term that can refer to the CTE
+        // itself, whose columns are those of the non-recursive term
+        let non_recursive_term = match cte.query.body.as_ref() {
+            SetExpr::SetOperation {
+                op: SetOperator::Union,
+                left,
+                ..
+            } if recursive => Some(left),
+            _ => None,
+        };
+        if let Some(non_recursive_term) = non_recursive_term {
+            let columns = self.set_expr(non_recursive_term, outer)?;
+            let columns = columns.into_iter().map(relation_column).collect();
+            self.ctes.push(aliased_relation(&cte.alias, columns)?);
+        }
+        let columns = self.query(&cte.query, outer)?;
+        if non_recursive_term.is_some() {
+            self.ctes.pop();
+        }
+        let columns = columns.into_iter().map(relation_column).collect();
+        self.ctes.push(aliased_relation(&cte.alias, columns)?);
+        Ok(())
+    }
+
+    fn set_expr(
+        &mut self,
+        set_expr: &SetExpr,
+        outer: Option<&Scope>,
+    ) -> Result<Vec<QueryColumn>, QueryError> {
+        match set_expr {
+            SetExpr::Select(select) => self.select(select, None, outer),
+            SetExpr::Query(query) => self.query(query, outer),
+            SetExpr::SetOperation {
+                op, left, right, ..
+            } => {
+                let left_columns = self.set_expr(left, outer)?;
+                let right_columns = self.set_expr(right, outer)?;
+                if left_columns.len() != right_columns.len() {
+                    return Err(QueryError::SetOperationColumnCount {
+                        location: location(set_expr.span()),
+                        operator: op.to_string(),
+                        left: left_columns.len(),
+                        right: right_columns.len(),
+                    });
+                }
+                left_columns
+                    .into_iter()
+                    .zip(right_columns)
+                    .map(|(left_column, right_column)| {
+                        let r#type = match (left_column.r#type, right_column.r#type) {
+                            (Some(left_type), Some(right_type)) => Some(
+                                self.catalog
+                                    .functions()
+                                    .common_type(&left_type, &right_type)
+                                    .ok_or_else(|| QueryError::IncompatibleTypes {
+                                        location: location(right.span()),
+                                        left: left_type,
+                                        right: right_type,
+                                    })?,
+                            ),
+                            (left_type, right_type) => left_type.or(right_type),
+                        };
+                        let nullable = match op {
+                            SetOperator::Union => left_column.nullable || right_column.nullable,
+                            SetOperator::Intersect => left_column.nullable && right_column.nullable,
+                            SetOperator::Except | SetOperator::Minus => left_column.nullable,
+                        };
+                        Ok(QueryColumn {
+                            name: left_column.name,
+                            r#type,
+                            nullable,
+                        })
+                    })
+                    .collect()
+            }
+            set_expr => Err(unsupported(set_expr.span(), "this kind of query")),
+        }
+    }
+
+    fn select(
+        &mut self,
+        select: &Select,
+        order_by: Option<&OrderBy>,
+        outer: Option<&Scope>,
+    ) -> Result<Vec<QueryColumn>, QueryError> {
+        if let Some(into) = &select.into {
+            return Err(unsupported(into.span(), "SELECT INTO"));
+        }
+
+        let mut scope = Scope::with_outer(outer);
+        for from in &select.from {
+            self.table_with_joins(&mut scope, from)?;
+        }
+        if let Some(selection) = &select.selection {
+            self.expect_type(&scope, selection, &DataType::Boolean)?;
+            narrow_not_null(&mut scope, selection)?;
+        }
+
+        let columns = self.projection(&scope, &select.projection)?;
+
+        if let Some(Distinct::On(exprs)) = &select.distinct {
+            for expr in exprs {
+                self.sort_expr(&scope, &columns, expr)?;
+            }
+        }
+        if let GroupByExpr::Expressions(exprs, _) = &select.group_by {
+            for expr in exprs {
+                self.sort_expr(&scope, &columns, expr)?;
+            }
+        }
+        if let Some(having) = &select.having {
+            self.expect_type(&scope, having, &DataType::Boolean)?;
+        }
+        if let Some(order_by) = order_by {
+            self.order_by(&scope, &columns, order_by)?;
+        }
+        Ok(columns)
+    }
+
+    /// Work out the output columns of a `SELECT` list or `RETURNING` clause.
+    fn projection(
+        &mut self,
+        scope: &Scope,
+        items: &[SelectItem],
+    ) -> Result<Vec<QueryColumn>, QueryError> {
+        let mut columns = vec![];
+        for item in items {
+            match item {
+                SelectItem::UnnamedExpr(expr) => {
+                    let typed = self.expr(scope, expr)?;
+                    columns.push(QueryColumn {
+                        name: output_name(expr).unwrap_or_else(|| Ident::new("?column?")),
+                        r#type: typed.r#type,
+                        nullable: typed.nullable,
+                    });
+                }
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    let typed = self.expr(scope, expr)?;
+                    columns.push(QueryColumn {
+                        name: normalise_ident(alias),
+                        r#type: typed.r#type,
+                        nullable: typed.nullable,
+                    });
+                }
+                SelectItem::Wildcard(_) => columns.extend(
+                    scope
+                        .relations
+                        .iter()
+                        .flat_map(|relation| &relation.columns)
+                        .filter(|column| !column.merged)
+                        .map(relation_query_column),
+                ),
+                SelectItem::QualifiedWildcard(name, _) => {
+                    let relation =
+                        scope
+                            .relation(&name.0)
+                            .ok_or_else(|| QueryError::UnknownRelation {
+                                location: location(name.span()),
+                                name: name.clone(),
+                            })?;
+                    columns.extend(relation.columns.iter().map(relation_query_column));
+                }
+            }
+        }
+        Ok(columns)
+    }
+
+    /// Add the relations of one item of a `FROM` clause to `scope`.
+    fn table_with_joins(
+        &mut self,
+        scope: &mut Scope,
+        from: &TableWithJoins,
+    ) -> Result<(), QueryError> {
+        let start = scope.relations.len();
+        self.table_factor(scope, &from.relation)?;
+        for join in &from.joins {
+            let right_start = scope.relations.len();
+            self.table_factor(scope, &join.relation)?;
+            // Rows on the outer side of the join that don't match anything are padded with NULLs
+            let nullable = match &join.join_operator {
+                JoinOperator::LeftOuter(_) => right_start..scope.relations.len(),
+                JoinOperator::RightOuter(_) => start..right_start,
+                JoinOperator::FullOuter(_) => start..scope.relations.len(),
+                _ => 0..0,
+            };
+            for relation in &mut scope.relations[nullable] {
+                for column in &mut relation.columns {
+                    column.nullable = true;
+                }
+            }
+            let constraint = match &join.join_operator {
+                JoinOperator::Inner(constraint)
+                | JoinOperator::LeftOuter(constraint)
+                | JoinOperator::RightOuter(constraint)
+                | JoinOperator::FullOuter(constraint) => constraint,
+                JoinOperator::CrossJoin => &JoinConstraint::None,
+                _ => return Err(unsupported(join.span(), "this kind of join")),
+            };
+            match constraint {
+                JoinConstraint::On(condition) => {
+                    self.expect_type(scope, condition, &DataType::Boolean)?;
+                }
+                JoinConstraint::Using(columns) => {
+                    for column in columns {
+                        let [column] = column.0.as_slice() else {
+                            return Err(QueryError::UnknownColumn {
+                                location: location(column.span()),
+                                column: column.clone(),
+                            });
+                        };
+                        merge_join_column(
+                            self.catalog.functions(),
+                            scope,
+                            start,
+                            right_start,
+                            column,
+                        )?;
+                    }
+                }
+                JoinConstraint::Natural => {
+                    let left_columns: Vec<_> = scope.relations[start..right_start]
+                        .iter()
+                        .flat_map(|relation| &relation.columns)
+                        .filter(|column| !column.merged)
+                        .map(|column| column.name.clone())
+                        .collect();
+                    let common_columns: Vec<_> = scope.relations[right_start..]
+                        .iter()
+                        .flat_map(|relation| &relation.columns)
+                        .filter(|column| !column.merged && left_columns.contains(&column.name))
+                        .map(|column| Ident::with_span(join.span(), column.name.value.clone()))
+                        .collect();
+                    for column in &common_columns {
+                        merge_join_column(
+                            self.catalog.functions(),
+                            scope,
+                            start,
+                            right_start,
+                            column,
+                        )?;
+                    }
+                }
+                JoinConstraint::None => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn table_factor(&mut self, scope: &mut Scope, factor: &TableFactor) -> Result<(), QueryError> {
+        match factor {
+            TableFactor::Table {
+                name,
+                alias,
+                args: None,
+                ..
+            } => {
+                let relation = self.named_relation(name)?;
+                let relation = match alias {
+                    Some(alias) => aliased_relation(alias, relation.columns)?,
+                    None => relation,
+                };
+                scope.add_relation(relation, factor.span())
+            }
+            TableFactor::Table {
+                name,
+                alias,
+                args: Some(args),
+                with_ordinality: false,
+                ..
+            } if args.settings.is_none() => {
+                let relation =
+                    self.function_relation(scope, factor.span(), name, &args.args, alias.as_ref())?;
+                scope.add_relation(relation, factor.span())
+            }
+            TableFactor::Function {
+                name, args, alias, ..
+            } => {
+                let relation =
+                    self.function_relation(scope, factor.span(), name, args, alias.as_ref())?;
+                scope.add_relation(relation, factor.span())
+            }
+            // sqlparser has its own syntax tree for `unnest`, but it's an ordinary function
+            TableFactor::UNNEST {
+                alias,
+                array_exprs,
+                with_offset: false,
+                with_ordinality: false,
+                ..
+            } => {
+                let args: Vec<_> = array_exprs
+                    .iter()
+                    .map(|expr| FunctionArg::Unnamed(FunctionArgExpr::Expr(expr.clone())))
+                    .collect();
+                let name = ObjectName(vec![Ident::new("unnest")]);
+                let relation =
+                    self.function_relation(scope, factor.span(), &name, &args, alias.as_ref())?;
+                scope.add_relation(relation, factor.span())
+            }
+            TableFactor::Derived {
+                lateral,
+                subquery,
+                alias,
+            } => {
+                let Some(alias) = alias else {
+                    return Err(unsupported(
+                        factor.span(),
+                        "subqueries in FROM without an alias",
+                    ));
+                };
+                // Only a LATERAL subquery can refer to the FROM items before it
+                let outer = if *lateral { Some(&*scope) } else { scope.outer };
+                let columns = self
+                    .query(subquery, outer)?
+                    .into_iter()
+                    .map(relation_column)
+                    .collect();
+                scope.add_relation(aliased_relation(alias, columns)?, factor.span())
+            }
+            TableFactor::NestedJoin {
+                table_with_joins,
+                alias: None,
+            } => self.table_with_joins(scope, table_with_joins),
+            factor => Err(unsupported(factor.span(), "this kind of FROM item")),
+        }
+    }
+
+    /// Look up the CTE, table or view `name`.
+    fn named_relation(&mut self, name: &ObjectName) -> Result<Relation, QueryError> {
+        if let [cte_name] = name.0.as_slice() {
+            let cte_name = normalise_ident(cte_name);
+            if let Some(cte) = self.ctes.iter().rev().find(|cte| cte.name == cte_name) {
+                return Ok(cte.clone());
+            }
+        }
+        if let Some(table) = self.catalog.table(name) {
+            return Ok(Relation {
+                name: table.name.0[1].clone(),
+                schema: Some(table.name.0[0].clone()),
+                columns: table_columns(self.catalog, table),
+            });
+        }
+        if let Some(view) = self.catalog.view(name) {
+            let columns = view
+                .columns
+                .iter()
+                .map(|column| RelationColumn {
+                    name: column.name.clone(),
+                    r#type: column.r#type.clone(),
+                    nullable: column.nullable,
+                    merged: false,
+                })
+                .collect();
+            return Ok(Relation {
+                name: view.name.0[1].clone(),
+                schema: Some(view.name.0[0].clone()),
+                columns,
+            });
+        }
+        Err(QueryError::UnknownTable {
+            location: location(name.span()),
+            name: name.clone(),
+        })
+    }
+
+    fn order_by(
+        &mut self,
+        scope: &Scope,
+        columns: &[QueryColumn],
+        order_by: &OrderBy,
+    ) -> Result<(), QueryError> {
+        for order_by_expr in &order_by.exprs {
+            self.sort_expr(scope, columns, &order_by_expr.expr)?;
+        }
+        Ok(())
+    }
+
+    /// Check an expression in `ORDER BY`, `GROUP BY` or `DISTINCT ON`, which can also refer to
+    /// an output column by name or position.
+    fn sort_expr(
+        &mut self,
+        scope: &Scope,
+        columns: &[QueryColumn],
+        expr: &Expr,
+    ) -> Result<(), QueryError> {
+        match expr {
+            Expr::Identifier(ident)
+                if columns
+                    .iter()
+                    .any(|column| column.name == normalise_ident(ident)) =>
+            {
+                Ok(())
+            }
+            Expr::Value(Value::Number(position, _)) => match position.parse::<usize>() {
+                Ok(position) if (1..=columns.len()).contains(&position) => Ok(()),
+                _ => Err(QueryError::UnknownPosition {
+                    location: location(expr.span()),
+                    position: position.clone(),
+                }),
+            },
+            expr => self.expr(scope, expr).map(drop),
+        }
+    }
+
+    /// Check every parameter used in `statement` has a type, returning them in order.
+    fn finish_parameters(mut self, statement: &Statement) -> Result<Vec<Parameter>, QueryError> {
+        let mut max_index = 0;
+        let _ = sqlparser::ast::visit_expressions(statement, |expr| {
+            if let Expr::Value(Value::Placeholder(placeholder)) = expr {
+                max_index = max_index.max(parameter_index(placeholder).unwrap_or(0));
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        (1..=max_index)
+            .map(|index| {
+                self.parameters
+                    .remove(&index)
+                    .map(|r#type| Parameter {
+                        r#type,
+                        nullable: self.nullable_parameters.contains(&index),
+                    })
+                    .ok_or_else(|| QueryError::UnknownParameterType {
+                        location: self.parameter_location(index),
+                        index,
+                    })
+            })
+            .collect()
+    }
+}
+
+fn table_columns(catalog: &Catalog, table: &Table) -> Vec<RelationColumn> {
+    table
+        .columns
+        .iter()
+        .map(|column| RelationColumn {
+            name: column.name.clone(),
+            r#type: catalog.canonical_type(&column.data_type),
+            nullable: table.is_nullable(column),
+            merged: false,
+        })
+        .collect()
+}
+
+/// Find where each `$N` parameter is first used in `sql`.
+fn parameter_spans(sql: &str) -> BTreeMap<usize, Span> {
+    let mut spans = BTreeMap::new();
+    let tokens = Tokenizer::new(&PostgreSqlDialect {}, sql).tokenize_with_location();
+    for token in tokens.into_iter().flatten() {
+        if let Token::Placeholder(placeholder) = &token.token {
+            if let Some(index) = parameter_index(placeholder) {
+                spans.entry(index).or_insert(token.span);
+            }
+        }
+    }
+    spans
+}
+
+/// Merge the columns called `name` on either side of a join, as `USING` and `NATURAL` joins
+/// do. The left side is made up of the relations in `scope` from `start` to `right_start`, and
+/// the right side is every relation after that.
+///
+/// The merged column is the coalesced value of both sides, so it's only NULL when both are.
+fn merge_join_column(
+    functions: &FunctionCatalog,
+    scope: &mut Scope,
+    start: usize,
+    right_start: usize,
+    name: &Ident,
+) -> Result<(), QueryError> {
+    let name = normalise_ident(name);
+    let (left, right) = scope.relations[start..].split_at_mut(right_start - start);
+    let left = find_join_column(left, &name)?;
+    let right = find_join_column(right, &name)?;
+    if functions
+        .resolve_binary_operator("=", Some(&left.r#type), Some(&right.r#type))
+        .is_none()
+    {
+        return Err(QueryError::UndefinedOperator {
+            location: location(name.span),
+            operator: "=".to_owned(),
+            left: left.r#type.clone(),
+            right: right.r#type.clone(),
+        });
+    }
+    left.nullable &= right.nullable;
+    right.merged = true;
+    Ok(())
+}
+
+fn find_join_column<'r>(
+    relations: &'r mut [Relation],
+    name: &Ident,
+) -> Result<&'r mut RelationColumn, QueryError> {
+    let mut matches = relations
+        .iter_mut()
+        .flat_map(|relation| &mut relation.columns)
+        .filter(|column| !column.merged && column.name == *name);
+    let found = matches.next().ok_or_else(|| QueryError::UnknownColumn {
+        location: location(name.span),
+        column: ObjectName(vec![name.clone()]),
+    })?;
+    match matches.next() {
+        Some(_) => Err(QueryError::AmbiguousColumn {
+            location: location(name.span),
+            column: ObjectName(vec![name.clone()]),
+        }),
+        None => Ok(found),
+    }
+}
+
+/// Mark the columns a `WHERE` clause requires not to be NULL as non-nullable, as in
+/// `WHERE a IS NOT NULL AND b IS NOT NULL`.
+fn narrow_not_null(scope: &mut Scope, selection: &Expr) -> Result<(), QueryError> {
+    match selection {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            narrow_not_null(scope, left)?;
+            narrow_not_null(scope, right)
+        }
+        Expr::Nested(inner) => narrow_not_null(scope, inner),
+        Expr::IsNotNull(column) => match column.as_ref() {
+            Expr::Identifier(ident) => {
+                scope.narrow_not_null(std::slice::from_ref(ident), column.span())
+            }
+            Expr::CompoundIdentifier(idents) => scope.narrow_not_null(idents, column.span()),
+            _ => Ok(()),
+        },
+        _ => Ok(()),
+    }
+}
+
+fn relation_query_column(column: &RelationColumn) -> QueryColumn {
+    QueryColumn {
+        name: column.name.clone(),
+        r#type: Some(column.r#type.clone()),
+        nullable: column.nullable,
+    }
+}
+
+/// Columns whose type is never pinned down, like `SELECT 'a'`, are returned as text.
+fn output_column(column: QueryColumn) -> OutputColumn {
+    OutputColumn {
+        name: column.name,
+        r#type: column.r#type.unwrap_or(DataType::Text),
+        nullable: column.nullable,
+    }
+}
+
+/// A column of a subquery in `FROM`, a CTE or a view, which can then be referred to like a
+/// table's column.
+fn relation_column(column: QueryColumn) -> RelationColumn {
+    let column = output_column(column);
+    RelationColumn {
+        name: column.name,
+        r#type: column.r#type,
+        nullable: column.nullable,
+        merged: false,
+    }
+}
+
+/// A relation called `alias`, renaming its first columns if the alias has a column list, as in
+/// `users AS u (user_id, user_name)`.
+fn aliased_relation(
+    alias: &TableAlias,
+    mut columns: Vec<RelationColumn>,
+) -> Result<Relation, QueryError> {
+    if alias.columns.len() > columns.len() {
+        return Err(QueryError::ColumnAliasCount {
+            location: location(alias.name.span),
+            relation: normalise_ident(&alias.name),
+            available: columns.len(),
+            given: alias.columns.len(),
+        });
+    }
+    for (column, column_alias) in columns.iter_mut().zip(&alias.columns) {
+        column.name = normalise_ident(&column_alias.name);
+    }
+    Ok(Relation {
+        name: normalise_ident(&alias.name),
+        schema: None,
+        columns,
+    })
+}
+
+/// The number of a `$N` placeholder.
+fn parameter_index(placeholder: &str) -> Option<usize> {
+    placeholder.strip_prefix('$')?.parse().ok()
+}
+
+fn location(span: Span) -> SourceLocation {
+    SourceLocation { file: None, span }
+}
+
+fn unsupported(span: Span, description: &str) -> QueryError {
+    QueryError::Unsupported {
+        location: location(span),
+        description: description.to_owned(),
+    }
+}
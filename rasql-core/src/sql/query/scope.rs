@@ -0,0 +1,169 @@
+//! The relations a query reads from, and resolving column references against them.
+
+use sqlparser::{
+    ast::{DataType, Ident, ObjectName},
+    tokenizer::Span,
+};
+
+use super::location;
+use crate::sql::{normalise_ident, QueryError};
+
+/// A table, view or other source of rows in a query's `FROM` clause.
+#[derive(Debug, Clone)]
+pub(super) struct Relation {
+    /// The name columns can be qualified with, which is the alias if there is one, otherwise
+    /// the table's name.
+    pub name: Ident,
+    /// The schema of the table when it isn't aliased, so columns can also be qualified as
+    /// `schema.table.column`.
+    pub schema: Option<Ident>,
+    pub columns: Vec<RelationColumn>,
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct RelationColumn {
+    pub name: Ident,
+    /// The column's type, in the form given by [canonical_type](crate::sql::types::canonical_type).
+    pub r#type: DataType,
+    pub nullable: bool,
+    /// Whether the column was merged into a column of an earlier relation by `JOIN ... USING`
+    /// or `NATURAL JOIN`, in which case it can only be referred to qualified, and isn't part of
+    /// `*`.
+    pub merged: bool,
+}
+
+/// Every relation a part of a query can refer to.
+#[derive(Debug, Default)]
+pub(super) struct Scope<'o> {
+    pub relations: Vec<Relation>,
+    /// The scope of the query this is a subquery of, whose columns can be referred to when
+    /// they aren't shadowed by this one's.
+    pub outer: Option<&'o Scope<'o>>,
+}
+
+impl<'o> Scope<'o> {
+    /// An empty scope for a subquery of a query whose scope is `outer`.
+    pub fn with_outer(outer: Option<&'o Scope<'o>>) -> Self {
+        Self {
+            relations: vec![],
+            outer,
+        }
+    }
+
+    pub fn add_relation(&mut self, relation: Relation, span: Span) -> Result<(), QueryError> {
+        let duplicate = self.relations.iter().any(|existing| {
+            existing.name == relation.name
+                && (existing.schema.is_none()
+                    || relation.schema.is_none()
+                    || existing.schema == relation.schema)
+        });
+        if duplicate {
+            return Err(QueryError::DuplicateRelation {
+                location: location(span),
+                name: relation.name,
+            });
+        }
+        self.relations.push(relation);
+        Ok(())
+    }
+
+    /// Find the relation a qualifier like `t` or `public.t` refers to.
+    pub fn relation(&self, qualifier: &[Ident]) -> Option<&Relation> {
+        self.relation_index(qualifier)
+            .map(|index| &self.relations[index])
+    }
+
+    /// Resolve a possibly qualified column reference, looking in outer scopes if it isn't in
+    /// this one.
+    pub fn column(&self, name: &[Ident], span: Span) -> Result<&RelationColumn, QueryError> {
+        match self.column_index(name, span) {
+            Ok((relation, column)) => Ok(&self.relations[relation].columns[column]),
+            Err(error) if is_unknown(&error) => match self.outer {
+                // Report the error from the innermost scope when no scope has the column
+                Some(outer) => match outer.column(name, span) {
+                    Err(outer_error) if is_unknown(&outer_error) => Err(error),
+                    result => result,
+                },
+                None => Err(error),
+            },
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Mark the column `name` refers to as never being NULL, for when the rows where it's NULL
+    /// have been filtered out.
+    pub fn narrow_not_null(&mut self, name: &[Ident], span: Span) -> Result<(), QueryError> {
+        let (relation, column) = self.column_index(name, span)?;
+        self.relations[relation].columns[column].nullable = false;
+        Ok(())
+    }
+
+    fn relation_index(&self, qualifier: &[Ident]) -> Option<usize> {
+        let (schema, name) = match qualifier {
+            [name] => (None, normalise_ident(name)),
+            [schema, name] | [_, schema, name] => {
+                (Some(normalise_ident(schema)), normalise_ident(name))
+            }
+            _ => return None,
+        };
+        self.relations.iter().position(|relation| {
+            relation.name == name && (schema.is_none() || relation.schema == schema)
+        })
+    }
+
+    /// Find the index of the relation a column reference refers to, and of the column within
+    /// that relation.
+    fn column_index(&self, name: &[Ident], span: Span) -> Result<(usize, usize), QueryError> {
+        let unknown_column = || QueryError::UnknownColumn {
+            location: location(span),
+            column: ObjectName(name.to_vec()),
+        };
+        let Some((column, qualifier)) = name.split_last() else {
+            return Err(unknown_column());
+        };
+        let column = normalise_ident(column);
+
+        if qualifier.is_empty() {
+            let mut matches =
+                self.relations
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(relation_index, relation)| {
+                        relation
+                            .columns
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, candidate)| !candidate.merged && candidate.name == column)
+                            .map(move |(column_index, _)| (relation_index, column_index))
+                    });
+            let found = matches.next().ok_or_else(unknown_column)?;
+            if matches.next().is_some() {
+                return Err(QueryError::AmbiguousColumn {
+                    location: location(span),
+                    column: ObjectName(name.to_vec()),
+                });
+            }
+            return Ok(found);
+        }
+
+        let relation_index =
+            self.relation_index(qualifier)
+                .ok_or_else(|| QueryError::UnknownRelation {
+                    location: location(span),
+                    name: ObjectName(qualifier.to_vec()),
+                })?;
+        let column_index = self.relations[relation_index]
+            .columns
+            .iter()
+            .position(|candidate| candidate.name == column)
+            .ok_or_else(unknown_column)?;
+        Ok((relation_index, column_index))
+    }
+}
+
+fn is_unknown(error: &QueryError) -> bool {
+    matches!(
+        error,
+        QueryError::UnknownColumn { .. } | QueryError::UnknownRelation { .. }
+    )
+}
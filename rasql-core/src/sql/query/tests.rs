@@ -0,0 +1,271 @@
+//! Inference of column and parameter types for queries against a small schema.
+
+use sqlparser::ast::DataType;
+
+use super::{parse_and_analyse_query, AnalysedQuery, StatementKind};
+use crate::sql::{parse_schema_sql, parse_sql_schema, Catalog, QueryError};
+
+const SCHEMA: &str = "
+    CREATE TABLE users (id int PRIMARY KEY, name text NOT NULL, email text);
+    CREATE TABLE posts (
+        id int8 PRIMARY KEY,
+        author int NOT NULL REFERENCES users (id),
+        title text NOT NULL,
+        body text
+    );
+    CREATE TABLE tree (id int PRIMARY KEY, parent int REFERENCES tree (id));
+";
+
+fn catalog() -> Catalog {
+    parse_sql_schema(parse_schema_sql(SCHEMA).unwrap()).unwrap()
+}
+
+fn analyse(sql: &str) -> Result<AnalysedQuery, QueryError> {
+    parse_and_analyse_query(&catalog(), sql)
+}
+
+/// Check the columns `sql` returns are `expected`, each a name, type and whether it's
+/// nullable.
+fn assert_columns(sql: &str, expected: &[(&str, DataType, bool)]) {
+    let analysed = analyse(sql).unwrap_or_else(|error| panic!("{sql}: {error}"));
+    let columns: Vec<_> = analysed
+        .columns
+        .iter()
+        .map(|column| {
+            (
+                column.name.value.as_str(),
+                column.r#type.clone(),
+                column.nullable,
+            )
+        })
+        .collect();
+    let expected: Vec<_> = expected
+        .iter()
+        .map(|(name, r#type, nullable)| (*name, r#type.clone(), *nullable))
+        .collect();
+    assert_eq!(columns, expected, "{sql}");
+}
+
+/// Check the parameters of `sql` are `expected`, each a type and whether it's nullable.
+fn assert_parameters(sql: &str, expected: &[(DataType, bool)]) {
+    let analysed = analyse(sql).unwrap_or_else(|error| panic!("{sql}: {error}"));
+    let parameters: Vec<_> = analysed
+        .parameters
+        .iter()
+        .map(|parameter| (parameter.r#type.clone(), parameter.nullable))
+        .collect();
+    assert_eq!(parameters, expected, "{sql}");
+}
+
+const INT4: DataType = DataType::Int4(None);
+const INT8: DataType = DataType::Int8(None);
+const TEXT: DataType = DataType::Text;
+
+#[test]
+fn table_columns() {
+    assert_columns(
+        "SELECT * FROM users",
+        &[
+            ("id", INT4, false),
+            ("name", TEXT, false),
+            ("email", TEXT, true),
+        ],
+    );
+    assert_columns(
+        "SELECT u.id AS user_id, lower(u.email) FROM users u",
+        &[("user_id", INT4, false), ("lower", TEXT, true)],
+    );
+}
+
+#[test]
+fn parameters() {
+    assert_parameters(
+        "SELECT id FROM users WHERE id = $1 AND name = $2",
+        &[(INT4, false), (TEXT, false)],
+    );
+    assert_parameters("SELECT $1::int8", &[(INT8, false)]);
+    assert_parameters(
+        "SELECT title FROM posts WHERE author = $1 LIMIT $2",
+        &[(INT4, false), (INT8, false)],
+    );
+}
+
+#[test]
+fn nullable_parameters() {
+    assert_parameters(
+        "INSERT INTO users (id, name, email) VALUES ($1, $2, $3)",
+        &[(INT4, false), (TEXT, false), (TEXT, true)],
+    );
+    assert_parameters(
+        "UPDATE users SET email = $1 WHERE email = $2",
+        &[(TEXT, true), (TEXT, false)],
+    );
+    assert_parameters(
+        "SELECT id FROM users \
+        WHERE (email IS NOT DISTINCT FROM $1) AND (name IS DISTINCT FROM $2)",
+        &[(TEXT, true), (TEXT, false)],
+    );
+}
+
+#[test]
+fn parameter_conflicts() {
+    let error = analyse("SELECT id FROM users WHERE id = $1 AND name = $1").unwrap_err();
+    assert!(
+        matches!(
+            &error,
+            QueryError::ConflictingParameterTypes {
+                index: 1,
+                first,
+                second,
+                ..
+            } if *first == INT4 && *second == TEXT
+        ),
+        "{error}"
+    );
+    let error = analyse("SELECT $1").unwrap_err();
+    assert!(
+        matches!(error, QueryError::UnknownParameterType { index: 1, .. }),
+        "{error}"
+    );
+}
+
+#[test]
+fn join_nullability() {
+    let cases = [
+        ("JOIN", false, false),
+        ("LEFT JOIN", false, true),
+        ("RIGHT JOIN", true, false),
+        ("FULL JOIN", true, true),
+    ];
+    for (join, user_nullable, post_nullable) in cases {
+        assert_columns(
+            &format!("SELECT u.name, p.title FROM users u {join} posts p ON p.author = u.id"),
+            &[
+                ("name", TEXT, user_nullable),
+                ("title", TEXT, post_nullable),
+            ],
+        );
+    }
+    assert_columns(
+        "SELECT u.name, p.title FROM users u CROSS JOIN posts p",
+        &[("name", TEXT, false), ("title", TEXT, false)],
+    );
+}
+
+#[test]
+fn is_not_null_narrows() {
+    assert_columns(
+        "SELECT email FROM users WHERE email IS NOT NULL",
+        &[("email", TEXT, false)],
+    );
+    assert_columns(
+        "SELECT u.email, p.title FROM users u LEFT JOIN posts p ON p.author = u.id \
+        WHERE u.email IS NOT NULL AND p.title IS NOT NULL",
+        &[("email", TEXT, false), ("title", TEXT, false)],
+    );
+    // Either side of an OR could be the one that's true
+    assert_columns(
+        "SELECT email FROM users WHERE email IS NOT NULL OR id = 1",
+        &[("email", TEXT, true)],
+    );
+}
+
+#[test]
+fn statement_kinds() {
+    let cases = [
+        ("SELECT 1", StatementKind::Query),
+        ("INSERT INTO tree (id) VALUES (1)", StatementKind::Insert),
+        ("UPDATE tree SET parent = NULL", StatementKind::Update),
+        ("DELETE FROM tree", StatementKind::Delete),
+        ("DELETE FROM tree RETURNING id", StatementKind::Query),
+    ];
+    for (sql, kind) in cases {
+        assert_eq!(analyse(sql).unwrap().kind, kind, "{sql}");
+    }
+}
+
+#[test]
+fn returning_columns() {
+    assert_columns(
+        "INSERT INTO users (id, name) VALUES ($1, $2) RETURNING id, email",
+        &[("id", INT4, false), ("email", TEXT, true)],
+    );
+    assert_columns(
+        "UPDATE posts SET body = NULL WHERE id = $1 RETURNING id AS post, upper(title)",
+        &[("post", INT8, false), ("upper", TEXT, false)],
+    );
+    assert_columns(
+        "DELETE FROM posts p USING users u WHERE p.author = u.id RETURNING p.title, u.email",
+        &[("title", TEXT, false), ("email", TEXT, true)],
+    );
+}
+
+#[test]
+fn set_operations() {
+    assert_columns(
+        "SELECT id, email FROM users UNION SELECT author, title FROM posts",
+        &[("id", INT4, false), ("email", TEXT, true)],
+    );
+    assert_columns(
+        "SELECT id FROM users UNION ALL SELECT id FROM posts",
+        &[("id", INT8, false)],
+    );
+    assert_columns(
+        "SELECT email FROM users INTERSECT SELECT body FROM posts",
+        &[("email", TEXT, true)],
+    );
+    assert_columns(
+        "SELECT email FROM users INTERSECT SELECT title FROM posts",
+        &[("email", TEXT, false)],
+    );
+    assert_columns(
+        "SELECT name FROM users EXCEPT SELECT body FROM posts",
+        &[("name", TEXT, false)],
+    );
+    assert_columns("SELECT NULL UNION SELECT 1", &[("?column?", INT4, true)]);
+}
+
+#[test]
+fn set_operation_arity() {
+    let error = analyse("SELECT id, name FROM users UNION SELECT id FROM users").unwrap_err();
+    assert!(
+        matches!(
+            error,
+            QueryError::SetOperationColumnCount {
+                left: 2,
+                right: 1,
+                ..
+            }
+        ),
+        "{error}"
+    );
+}
+
+#[test]
+fn common_table_expressions() {
+    assert_columns(
+        "WITH named AS (SELECT id, email AS address FROM users) SELECT * FROM named",
+        &[("id", INT4, false), ("address", TEXT, true)],
+    );
+    assert_columns(
+        "WITH counts (author, posts) AS (SELECT author, count(*) FROM posts GROUP BY author) \
+        SELECT u.name, c.posts FROM users u LEFT JOIN counts c ON c.author = u.id",
+        &[("name", TEXT, false), ("posts", INT8, true)],
+    );
+}
+
+#[test]
+fn recursive_common_table_expressions() {
+    assert_columns(
+        "WITH RECURSIVE ancestors (id, parent, depth) AS ( \
+            SELECT id, parent, 0 FROM tree WHERE id = 1 \
+            UNION ALL \
+            SELECT t.id, t.parent, a.depth + 1 FROM tree t JOIN ancestors a ON t.id = a.parent \
+        ) SELECT id, parent, depth FROM ancestors",
+        &[
+            ("id", INT4, false),
+            ("parent", INT4, true),
+            ("depth", INT4, false),
+        ],
+    );
+}
@@ -0,0 +1,135 @@
+//! Helpers for comparing SQL types the way Postgres does. How types combine is decided by the
+//! casts in the [FunctionCatalog](super::FunctionCatalog).
+
+use sqlparser::ast::{ArrayElemTypeDef, DataType, ExactNumberInfo, ObjectName, TimezoneInfo};
+
+/// The form of `data_type` that Postgres would store, so that synonyms compare equal. For
+/// example `INT`, `INTEGER` and `INT4` all become `INT4`, and length limits are dropped as
+/// they don't affect the type of a value.
+pub fn canonical_type(data_type: &DataType) -> DataType {
+    match data_type {
+        DataType::Int2(_) | DataType::SmallInt(_) | DataType::Int16 | DataType::TinyInt(_) => {
+            DataType::Int2(None)
+        }
+        DataType::Int(_)
+        | DataType::Int4(_)
+        | DataType::Integer(_)
+        | DataType::Int32
+        | DataType::MediumInt(_) => DataType::Int4(None),
+        DataType::Int8(_) | DataType::BigInt(_) | DataType::Int64 => DataType::Int8(None),
+        DataType::Float(Some(precision)) if *precision <= 24 => DataType::Float4,
+        DataType::Float4 | DataType::Real | DataType::Float32 => DataType::Float4,
+        DataType::Float(_)
+        | DataType::Float8
+        | DataType::Float64
+        | DataType::Double(_)
+        | DataType::DoublePrecision => DataType::Float8,
+        DataType::Numeric(_)
+        | DataType::Decimal(_)
+        | DataType::Dec(_)
+        | DataType::BigNumeric(_)
+        | DataType::BigDecimal(_) => DataType::Numeric(ExactNumberInfo::None),
+        DataType::Character(_)
+        | DataType::Char(_)
+        | DataType::CharacterVarying(_)
+        | DataType::CharVarying(_)
+        | DataType::Varchar(_)
+        | DataType::Nvarchar(_)
+        | DataType::Text
+        | DataType::TinyText
+        | DataType::MediumText
+        | DataType::LongText
+        | DataType::String(_)
+        | DataType::FixedString(_)
+        | DataType::CharacterLargeObject(_)
+        | DataType::CharLargeObject(_)
+        | DataType::Clob(_) => DataType::Text,
+        DataType::Bytea
+        | DataType::Binary(_)
+        | DataType::Varbinary(_)
+        | DataType::Blob(_)
+        | DataType::TinyBlob
+        | DataType::MediumBlob
+        | DataType::LongBlob
+        | DataType::Bytes(_) => DataType::Bytea,
+        DataType::Bool | DataType::Boolean => DataType::Boolean,
+        DataType::Timestamp(_, timezone) => DataType::Timestamp(None, canonical_timezone(timezone)),
+        DataType::Datetime(_) => DataType::Timestamp(None, TimezoneInfo::None),
+        DataType::Time(_, timezone) => DataType::Time(None, canonical_timezone(timezone)),
+        DataType::Bit(_) => DataType::Bit(None),
+        DataType::BitVarying(_) => DataType::BitVarying(None),
+        DataType::Array(
+            ArrayElemTypeDef::AngleBracket(element)
+            | ArrayElemTypeDef::SquareBracket(element, _)
+            | ArrayElemTypeDef::Parenthesis(element),
+        ) => DataType::Array(ArrayElemTypeDef::SquareBracket(
+            Box::new(canonical_type(element)),
+            None,
+        )),
+        DataType::Custom(ObjectName(name), _) => match name.as_slice() {
+            [name] => match name.value.to_lowercase().as_str() {
+                "serial" | "serial4" => DataType::Int4(None),
+                "bigserial" | "serial8" => DataType::Int8(None),
+                "smallserial" | "serial2" => DataType::Int2(None),
+                "bpchar" | "name" => DataType::Text,
+                "timestamptz" => DataType::Timestamp(None, TimezoneInfo::WithTimeZone),
+                "timetz" => DataType::Time(None, TimezoneInfo::WithTimeZone),
+                "varbit" => DataType::BitVarying(None),
+                _ => data_type.clone(),
+            },
+            _ => data_type.clone(),
+        },
+        data_type => data_type.clone(),
+    }
+}
+
+/// Whether `name` is a type built into Postgres that sqlparser doesn't have its own [DataType]
+/// for, so it's parsed as [DataType::Custom]. Synonyms [canonical_type] turns into other types,
+/// like `serial`, aren't included.
+pub fn is_builtin_type_name(name: &str) -> bool {
+    matches!(
+        name,
+        "oid"
+            | "regclass"
+            | "regtype"
+            | "regproc"
+            | "xid"
+            | "money"
+            | "inet"
+            | "cidr"
+            | "macaddr"
+            | "macaddr8"
+            | "point"
+            | "line"
+            | "lseg"
+            | "box"
+            | "path"
+            | "polygon"
+            | "circle"
+            | "tsvector"
+            | "tsquery"
+            | "xml"
+            | "jsonpath"
+            | "pg_lsn"
+            | "pg_snapshot"
+            | "int4range"
+            | "int8range"
+            | "numrange"
+            | "tsrange"
+            | "tstzrange"
+            | "daterange"
+            | "int4multirange"
+            | "int8multirange"
+            | "nummultirange"
+            | "tsmultirange"
+            | "tstzmultirange"
+            | "datemultirange"
+    )
+}
+
+fn canonical_timezone(timezone: &TimezoneInfo) -> TimezoneInfo {
+    match timezone {
+        TimezoneInfo::WithTimeZone | TimezoneInfo::Tz => TimezoneInfo::WithTimeZone,
+        TimezoneInfo::None | TimezoneInfo::WithoutTimeZone => TimezoneInfo::None,
+    }
+}
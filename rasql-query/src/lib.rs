@@ -0,0 +1,374 @@
+//! Procedural macros for writing queries that are type checked against the schema set up by
+//! rasql-build.
+
+// Without any client features enabled there's nothing to generate queries for
+#![cfg_attr(not(feature = "tokio-postgres"), allow(dead_code))]
+
+use std::{path::Path, sync::OnceLock};
+
+use quote::{format_ident, quote, quote_spanned};
+use rasql_core::{
+    rust::{
+        build_env,
+        client_gen::AsyncClientCodeGenerator,
+        sql_ident_to_field_name,
+        type_gen::{TypeGenerator, UnsupportedDataType},
+    },
+    sql::{
+        query::{parse_and_analyse_query, AnalysedQuery, StatementKind},
+        Catalog,
+    },
+};
+use syn::{parse::Parse, punctuated::Punctuated, spanned::Spanned};
+
+/// Run a query that's checked against the schema at compile time, evaluating to a future of
+/// `Result<Vec<Row>, Client::Error>`, where `Row` is a struct with a field for each column the
+/// query returns.
+///
+/// ```ignore
+/// let users = rasql_query::query!(client, "SELECT id, name FROM users WHERE id = $1", user_id)
+///     .await?;
+/// println!("{}", users[0].name);
+/// ```
+///
+/// The first argument is the database client, the second is the SQL, and the rest are the
+/// values of each parameter, `$1` first. Parameters are borrowed, and must be of the Rust type
+/// the SQL expects. That's an `Option` for parameters stored in nullable columns, or compared
+/// with something nullable using `IS [NOT] DISTINCT FROM`.
+///
+/// `INSERT`, `UPDATE` and `DELETE` statements evaluate to the client's outcome for that kind of
+/// statement instead, which for tokio-postgres is the number of rows affected. With a
+/// `RETURNING` clause they return rows like a `SELECT` does.
+///
+/// ```ignore
+/// let deleted = rasql_query::query!(client, "DELETE FROM users WHERE id = $1", user_id).await?;
+/// let inserted = rasql_query::query!(client, "INSERT INTO users (name) VALUES ($1) RETURNING id", name)
+///     .await?;
+/// println!("deleted {deleted} users and inserted user {}", inserted[0].id);
+/// ```
+///
+/// This depends on rasql-build being used in the crate's build script, which tells this macro
+/// where to find the schema.
+#[proc_macro]
+pub fn query(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as QueryInput);
+    expand_query(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct QueryInput {
+    client: syn::Expr,
+    sql: syn::LitStr,
+    parameters: Vec<syn::Expr>,
+}
+
+impl Parse for QueryInput {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let client = input.parse()?;
+        input.parse::<syn::Token![,]>()?;
+        let sql = input.parse()?;
+        let parameters = if input.is_empty() {
+            vec![]
+        } else {
+            input.parse::<syn::Token![,]>()?;
+            Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated(input)?
+                .into_iter()
+                .collect()
+        };
+        Ok(Self {
+            client,
+            sql,
+            parameters,
+        })
+    }
+}
+
+fn expand_query(input: &QueryInput) -> syn::Result<proc_macro2::TokenStream> {
+    let catalog = catalog().map_err(|message| syn::Error::new(input.sql.span(), message))?;
+    let analysed = parse_and_analyse_query(catalog, &input.sql.value())
+        .map_err(|error| syn::Error::new(input.sql.span(), error))?;
+    if analysed.parameters.len() != input.parameters.len() {
+        return Err(syn::Error::new(
+            input.sql.span(),
+            format!(
+                "query has {} parameters but {} were given",
+                analysed.parameters.len(),
+                input.parameters.len()
+            ),
+        ));
+    }
+    for (index, column) in analysed.columns.iter().enumerate() {
+        // Postgres' name for columns it can't name after anything
+        if column.name.value == "?column?" {
+            return Err(syn::Error::new(
+                input.sql.span(),
+                format!(
+                    "column {} of the query has no name, name it with `AS`",
+                    index + 1
+                ),
+            ));
+        }
+        let field_name = sql_ident_to_field_name(&column.name);
+        if let Some(earlier) = analysed.columns[..index]
+            .iter()
+            .find(|earlier| sql_ident_to_field_name(&earlier.name) == field_name)
+        {
+            let message = if earlier.name == column.name {
+                format!(
+                    "the query returns more than one column called `{}`, give them different \
+                    names with `AS`",
+                    column.name.value
+                )
+            } else {
+                format!(
+                    "the query's columns `{}` and `{}` are both called `{field_name}` in Rust, \
+                    give them different names with `AS`",
+                    earlier.name.value, column.name.value
+                )
+            };
+            return Err(syn::Error::new(input.sql.span(), message));
+        }
+    }
+
+    let client = std::env::var(build_env::CLIENT).unwrap_or_default();
+    match client.as_str() {
+        #[cfg(feature = "tokio-postgres")]
+        "tokio-postgres" => {
+            use rasql_core::rust::type_gen::{
+                TokioPostgresGenerator, UseDateTime, UseRustDecimal, UseSerdeJson, UseUuid,
+            };
+
+            let generator = TokioPostgresGenerator {
+                use_rust_decimal: std::env::var(build_env::USE_RUST_DECIMAL)
+                    .ok()
+                    .and_then(|name| UseRustDecimal::from_name(&name))
+                    .unwrap_or(UseRustDecimal::DontUse),
+                use_uuid: std::env::var(build_env::USE_UUID)
+                    .ok()
+                    .and_then(|name| UseUuid::from_name(&name))
+                    .unwrap_or(UseUuid::DontUse),
+                use_date_time: std::env::var(build_env::USE_DATE_TIME)
+                    .ok()
+                    .and_then(|name| UseDateTime::from_name(&name))
+                    .unwrap_or(UseDateTime::DontUse),
+                use_serde_json: std::env::var(build_env::USE_SERDE_JSON)
+                    .ok()
+                    .and_then(|name| UseSerdeJson::from_name(&name))
+                    .unwrap_or(UseSerdeJson::DontUse),
+                generated_module_path: generated_module_path()
+                    .map_err(|message| syn::Error::new(input.sql.span(), message))?,
+            };
+            generate_query::<_, tokio_postgres::Client, _>(input, catalog, &analysed, &generator)
+        }
+        "" => Err(syn::Error::new(
+            input.sql.span(),
+            format!(
+                "`{}` isn't set, is rasql-build used in the build script?",
+                build_env::CLIENT
+            ),
+        )),
+        client => Err(syn::Error::new(
+            input.sql.span(),
+            format!("rasql-query doesn't support the client `{client}`, is its feature enabled?"),
+        )),
+    }
+}
+
+fn generate_query<
+    Traits: rasql_traits::DbTraits,
+    Client: rasql_traits::r#async::Client<Traits = Traits>,
+    Generator: TypeGenerator<Traits> + AsyncClientCodeGenerator<Client>,
+>(
+    input: &QueryInput,
+    catalog: &Catalog,
+    analysed: &AnalysedQuery,
+    generator: &Generator,
+) -> syn::Result<proc_macro2::TokenStream> {
+    // Types the generator can't map are reported against the value or the SQL, so the macro
+    // fails like any other type error rather than crashing
+    let unsupported = |span, subject: String, error: UnsupportedDataType| {
+        syn::Error::new(
+            span,
+            format!("{subject} has a type with no Rust equivalent: {error}"),
+        )
+    };
+
+    let client_type = Generator::generate_client_type();
+    let client: syn::Expr = syn::parse_quote!(__rasql_client);
+    let statement: syn::Expr = syn::parse_quote!(&__rasql_statement);
+    let row: syn::Expr = syn::parse_quote!((&__rasql_row));
+
+    let parameter_names: Vec<_> = (1..=analysed.parameters.len())
+        .map(|index| format_ident!("__rasql_param_{index}"))
+        .collect();
+    let parameter_types = analysed
+        .parameters
+        .iter()
+        .zip(&input.parameters)
+        .enumerate()
+        .map(|(index, (parameter, value))| {
+            let r#type = catalog.qualified_type(&parameter.r#type);
+            // Optional values have to be sized, so they're given as e.g. `Option<String>`
+            // rather than `str`
+            if parameter.nullable {
+                generator
+                    .sql_datatype_to_rust_type(&r#type)
+                    .map(|r#type| syn::parse_quote!(Option<#r#type>))
+            } else {
+                generator.sql_datatype_to_rust_param_type(&r#type)
+            }
+            .map_err(|error| unsupported(value.span(), format!("${}", index + 1), error))
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    // Span the bindings with the values, so type errors point at the value that's wrong
+    let parameter_bindings = parameter_names
+        .iter()
+        .zip(&parameter_types)
+        .zip(&input.parameters)
+        .map(|((name, r#type), value)| {
+            quote_spanned!(value.span()=> let #name: &#r#type = &(#value);)
+        });
+    let parameter_refs: Vec<syn::Expr> = parameter_names
+        .iter()
+        .map(|name| syn::parse_quote!(&#name))
+        .collect();
+    let parameter_refs: Vec<_> = parameter_refs.iter().collect();
+
+    let sql = &input.sql;
+    let prepare = Generator::generate_prepare_statement(&client, &syn::parse_quote!(#sql));
+    let client_value = &input.client;
+
+    let execute = match analysed.kind {
+        StatementKind::Query => None,
+        StatementKind::Insert => Some(Generator::generate_insert_with_statement(
+            &client,
+            &statement,
+            &parameter_refs,
+        )),
+        StatementKind::Update => Some(Generator::generate_update_with_statement(
+            &client,
+            &statement,
+            &parameter_refs,
+        )),
+        StatementKind::Delete => Some(Generator::generate_delete_with_statement(
+            &client,
+            &statement,
+            &parameter_refs,
+        )),
+    };
+    if let Some(execute) = execute {
+        return Ok(quote! {
+            async {
+                let __rasql_client = &(#client_value);
+                #(#parameter_bindings)*
+                let __rasql_statement = #prepare?;
+                Ok::<_, <#client_type as rasql_traits::r#async::Client>::Error>(#execute?)
+            }
+        });
+    }
+
+    let field_names: Vec<_> = analysed
+        .columns
+        .iter()
+        .map(|column| sql_ident_to_field_name(&column.name))
+        .collect();
+    let field_types = analysed
+        .columns
+        .iter()
+        .map(|column| {
+            let r#type = generator
+                .sql_datatype_to_rust_type(&catalog.qualified_type(&column.r#type))
+                .map_err(|error| {
+                    unsupported(
+                        input.sql.span(),
+                        format!("column `{}`", column.name.value),
+                        error,
+                    )
+                })?;
+            Ok(if column.nullable {
+                syn::Type::Verbatim(quote!(Option<#r#type>))
+            } else {
+                r#type
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    let field_reads = analysed.columns.iter().map(|column| {
+        let column_name = &column.name.value;
+        Generator::generate_row_read_column(&row, &syn::parse_quote!(#column_name))
+    });
+
+    let query = Generator::generate_query_many_with_statement(&client, &statement, &parameter_refs);
+
+    Ok(quote! {
+        {
+            #[derive(Debug, Clone)]
+            struct Row {
+                #(pub #field_names: #field_types,)*
+            }
+
+            async {
+                let __rasql_client = &(#client_value);
+                #(#parameter_bindings)*
+                let __rasql_statement = #prepare?;
+                let __rasql_rows = #query?;
+                let mut __rasql_output = Vec::new();
+                for __rasql_row in __rasql_rows {
+                    __rasql_output.push(Row {
+                        #(#field_names: #field_reads?,)*
+                    });
+                }
+                Ok::<Vec<Row>, <#client_type as rasql_traits::r#async::Client>::Error>(
+                    __rasql_output,
+                )
+            }
+        }
+    })
+}
+
+/// Where the code rasql-build generates is included, which is how the types it generates for
+/// the schema's custom types are referred to.
+fn generated_module_path() -> Result<syn::Path, String> {
+    let path =
+        std::env::var(build_env::GENERATED_MODULE_PATH).unwrap_or_else(|_| "crate".to_owned());
+    syn::parse_str(&path).map_err(|_| format!("`{path}` isn't a valid path for the generated code"))
+}
+
+/// Load the schema rasql-build pointed us at. This only happens once per compilation, however
+/// many queries there are.
+fn catalog() -> Result<&'static Catalog, String> {
+    static CATALOG: OnceLock<Result<Catalog, String>> = OnceLock::new();
+    CATALOG
+        .get_or_init(|| {
+            let schema_dir = std::env::var(build_env::SCHEMA_DIR).map_err(|_| {
+                format!(
+                    "`{}` isn't set, is rasql-build used in the build script?",
+                    build_env::SCHEMA_DIR
+                )
+            })?;
+            let mut catalog =
+                rasql_core::sql::load_migrations(Path::new(&schema_dir)).map_err(|errors| {
+                    let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+                    format!("the schema has errors:\n{}", errors.join("\n"))
+                })?;
+            let function_signatures =
+                std::env::var_os(build_env::FUNCTION_SIGNATURES).unwrap_or_default();
+            // Splitting an empty list gives one empty path
+            for path in std::env::split_paths(&function_signatures)
+                .filter(|path| !path.as_os_str().is_empty())
+            {
+                std::fs::read_to_string(&path)
+                    .map_err(|error| error.to_string())
+                    .and_then(|signatures| {
+                        catalog
+                            .functions_mut()
+                            .add_signatures(&signatures)
+                            .map_err(|error| error.to_string())
+                    })
+                    .map_err(|error| format!("{}: {error}", path.display()))?;
+            }
+            Ok(catalog)
+        })
+        .as_ref()
+        .map_err(Clone::clone)
+}
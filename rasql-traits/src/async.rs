@@ -7,7 +7,7 @@ pub trait Client {
     type PrepareError;
     type PreparedStatement;
 
-    type Rows;
+    type Rows: IntoIterator<Item = Self::Row>;
     type Row;
 
     type RowReadColumnError;
@@ -19,6 +19,15 @@ pub trait Client {
     type UpdateError;
     type DeleteOutcome;
     type DeleteError;
+
+    /// An error that any of the client's other errors can be converted into, so a sequence of
+    /// operations can be handled with a single error type.
+    type Error: From<Self::PrepareError>
+        + From<Self::RowReadColumnError>
+        + From<Self::QueryError>
+        + From<Self::InsertError>
+        + From<Self::UpdateError>
+        + From<Self::DeleteError>;
 }
 
 #[cfg(feature = "tokio-postgres")]
@@ -48,11 +57,13 @@ impl Client for tokio_postgres::Client {
     type DeleteOutcome = u64;
 
     type DeleteError = tokio_postgres::Error;
+
+    type Error = tokio_postgres::Error;
 }
 
-/// Base trait that all table types implement. Allows for inserting rows and selecting all rows.
+/// Base trait that all table and view types implement. Allows for selecting all rows.
 #[allow(async_fn_in_trait)]
-pub trait Table<C: Client>: Sized {
+pub trait Relation<C: Client>: Sized {
     const SCHEMA: Option<&str>;
     const NAME: &str;
 
@@ -60,12 +71,18 @@ pub trait Table<C: Client>: Sized {
 
     async fn prepare_select_all(client: &C) -> Result<Self::SelectAllStatement, C::PrepareError>;
 
-    /// Query all rows in the database table and load them into `Container`.
+    /// Query all rows in the database table or view and load them into `Container`.
     async fn select_all<Container: FromIterator<Self>>(
         client: &C,
         select_all_statement: &Self::SelectAllStatement,
     ) -> Result<Container, C::QueryError>;
+}
 
+/// Trait that all table types implement.
+///
+/// In addition to what's allowed by [Relation], allows for inserting rows.
+#[allow(async_fn_in_trait)]
+pub trait Table<C: Client>: Relation<C> {
     type InsertStatement;
 
     async fn prepare_insert(client: &C) -> Result<Self::InsertStatement, C::PrepareError>;
@@ -134,3 +151,73 @@ pub trait TableWithPK<C: Client>: Table<C> {
         .await
     }
 }
+
+/// Trait that table types implement for each of their unique constraints and unique indexes
+/// other than the primary key, where `K` borrows the values of the key's columns.
+///
+/// In addition to what's allowed by [Table], also allows single row select and deletion by
+/// that key.
+#[allow(async_fn_in_trait)]
+pub trait TableWithUnique<C: Client, K>: Table<C> {
+    type SelectByUniqueStatement;
+
+    async fn prepare_select_by_unique(
+        client: &C,
+    ) -> Result<Self::SelectByUniqueStatement, C::PrepareError>;
+
+    /// Query the row in the database table whose key is equal to `key`, if there is one.
+    async fn select_by_unique(
+        client: &C,
+        select_by_unique_statement: &Self::SelectByUniqueStatement,
+        key: K,
+    ) -> Result<Option<Self>, C::QueryError>;
+
+    type DeleteByUniqueStatement;
+
+    async fn prepare_delete_by_unique(
+        client: &C,
+    ) -> Result<Self::DeleteByUniqueStatement, C::PrepareError>;
+
+    /// Delete the row in the database table whose key is equal to `key`, if there is one.
+    async fn delete_by_unique(
+        client: &C,
+        delete_by_unique_statement: &Self::DeleteByUniqueStatement,
+        key: K,
+    ) -> Result<C::DeleteOutcome, C::DeleteError>;
+}
+
+/// Trait that table types with a primary key or unique constraint implement.
+///
+/// In addition to what's allowed by [Table], allows for inserting rows that may conflict with
+/// an existing row's key.
+#[allow(async_fn_in_trait)]
+pub trait Upsert<C: Client>: Table<C> {
+    type InsertOrUpdateStatement;
+
+    async fn prepare_insert_or_update(
+        client: &C,
+    ) -> Result<Self::InsertOrUpdateStatement, C::PrepareError>;
+
+    /// Insert this value into the database table, or if a row with the same key already exists,
+    /// update that row to be equal to this value. The key is the primary key, or the first
+    /// unique key if there isn't one, and conflicts with any other key are still errors.
+    async fn insert_or_update(
+        &self,
+        client: &C,
+        insert_or_update_statement: &Self::InsertOrUpdateStatement,
+    ) -> Result<C::InsertOutcome, C::InsertError>;
+
+    type InsertOrIgnoreStatement;
+
+    async fn prepare_insert_or_ignore(
+        client: &C,
+    ) -> Result<Self::InsertOrIgnoreStatement, C::PrepareError>;
+
+    /// Insert this value into the database table, unless it conflicts with an existing row on
+    /// any of the table's keys, not only the one [Upsert::insert_or_update] updates on.
+    async fn insert_or_ignore(
+        &self,
+        client: &C,
+        insert_or_ignore_statement: &Self::InsertOrIgnoreStatement,
+    ) -> Result<C::InsertOutcome, C::InsertError>;
+}
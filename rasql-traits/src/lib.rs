@@ -3,7 +3,7 @@ pub mod r#async;
 
 pub trait DbTraits {}
 
-pub trait Id: Copy + Eq {
+pub trait Id: Clone + Eq {
     type Underlying;
 
     fn to_underlying(self) -> Self::Underlying;
@@ -11,11 +11,14 @@ pub trait Id: Copy + Eq {
     fn from_underlying(underlying: Self::Underlying) -> Self;
 }
 
-pub trait TrustedId: Copy + Eq {
+pub trait TrustedId: Clone + Eq {
     type Underlying;
 
     type Untrusted: UntrustedId<Underlying = Self::Underlying>;
 
+    /// # Safety
+    ///
+    /// `untrusted` must be known to identify a row that exists in the database.
     unsafe fn from_untrusted_unchecked(untrusted: Self::Untrusted) -> Self;
 
     fn to_untrusted(self) -> Self::Untrusted;
@@ -23,7 +26,7 @@ pub trait TrustedId: Copy + Eq {
     fn to_underlying(self) -> Self::Underlying;
 }
 
-pub trait UntrustedId: Copy + Eq  {
+pub trait UntrustedId: Clone + Eq  {
     type Underlying;
 
     type Trusted: TrustedId<Underlying = Self::Underlying>;
@@ -32,9 +35,34 @@ pub trait UntrustedId: Copy + Eq  {
 
     fn from_trusted(trusted: Self::Trusted) -> Self;
 
+    /// # Safety
+    ///
+    /// `self` must be known to identify a row that exists in the database.
     unsafe fn to_trusted_unchecked(self) -> Self::Trusted;
 }
 
+/// The error from the constructor generated for a domain when the value doesn't satisfy one of
+/// the domain's `CHECK` constraints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckViolation {
+    /// The name of the domain.
+    pub domain: &'static str,
+    /// The SQL of the constraint's check expression.
+    pub check: &'static str,
+}
+
+impl std::fmt::Display for CheckViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "value for domain `{}` violates `CHECK ({})`",
+            self.domain, self.check
+        )
+    }
+}
+
+impl std::error::Error for CheckViolation {}
+
 
 #[cfg(feature = "tokio-postgres")]
 pub struct PostgresTypesTraits;